@@ -1,7 +1,29 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
 fn main() {
+    let storage = env::var_os("CARGO_FEATURE_STORAGE").is_some();
+
     // https://github.com/embassy-rs/embassy/blob/main/examples/stm32f4/build.rs
     println!("cargo:rustc-link-arg=--nmagic");
+    if storage {
+        // The linker finds ./memory.x before the copy below for the BSP's own
+        // examples; `_stext` must be set before link.x places `.text`
+        println!("cargo:rustc-link-arg-examples=-Tstorage.x");
+    }
     println!("cargo:rustc-link-arg=-Tlink.x");
     println!("cargo:rustc-link-arg=-Tdefmt.x");
+
+    // Put memory.x on the linker search path, also for crates using the BSP. The
+    // `storage` feature moves the code past the flash sectors of the key-value store.
+    let mut memory = fs::read_to_string("memory.x").unwrap();
+    if storage {
+        memory += &fs::read_to_string("storage.x").unwrap();
+    }
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out.join("memory.x"), memory).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=storage.x");
 }
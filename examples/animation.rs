@@ -0,0 +1,99 @@
+//! # LED Animation Example
+//!
+//! This example plays LED patterns from a background task instead of
+//! hand-written `Timer::after` loops.
+//!
+//! ## What This Example Does
+//!
+//! - Spawns the LED task and plays a green heartbeat in the background
+//! - Cycles the status layer through chase, blink and breathe patterns
+//! - Shows a 3-blink red error code for 5 seconds whenever the user button is
+//!   pressed, then returns to whatever was playing before
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example animation
+//! ```
+//!
+//! ## Hardware Used
+//!
+//! - All four user LEDs (PD12-PD15)
+//! - User button (B1) on pin PA0
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use stm32f411ve_disco::animation::{led_task, LedAnimator, Pattern, Priority};
+use stm32f411ve_disco::button::Button;
+use stm32f411ve_disco::leds::{Led, Leds};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Pattern state shared with the LED task
+static ANIMATOR: LedAnimator = LedAnimator::new();
+
+/// Status patterns cycled through by the main loop
+const STATUS_PATTERNS: [Option<Pattern>; 4] = [
+    None,
+    Some(Pattern::Chase {
+        step: Duration::from_millis(150),
+        clockwise: true,
+    }),
+    Some(Pattern::Blink {
+        leds: 0b1111,
+        on: Duration::from_millis(100),
+        off: Duration::from_millis(400),
+    }),
+    Some(Pattern::Breathe {
+        leds: 0b0101,
+        period: Duration::from_millis(2000),
+    }),
+];
+
+/// Main entry point - drives patterns while the LED task renders them
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("LED animation demo");
+
+    let leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    let button = Button::new(p.PA0);
+    unwrap!(spawner.spawn(led_task(&ANIMATOR, leds)));
+
+    ANIMATOR.play(Priority::Background, Pattern::Heartbeat { leds: Led::Green.mask() });
+
+    let mut ticks = 0u32;
+    let mut last_pressed = false;
+    loop {
+        // Change the status pattern every 4 seconds
+        if ticks.is_multiple_of(80) {
+            match STATUS_PATTERNS[(ticks / 80) as usize % STATUS_PATTERNS.len()] {
+                Some(pattern) => {
+                    info!("Status: {}", pattern);
+                    ANIMATOR.play(Priority::Status, pattern);
+                }
+                None => {
+                    info!("Status: none (heartbeat)");
+                    ANIMATOR.stop(Priority::Status);
+                }
+            }
+        }
+
+        let pressed = button.is_pressed();
+        if pressed && !last_pressed {
+            info!("Button pressed - error code 3");
+            ANIMATOR.play_for(
+                Priority::Alert,
+                Pattern::ErrorCode { leds: Led::Red.mask(), code: 3 },
+                Duration::from_secs(5),
+            );
+        }
+        last_pressed = pressed;
+
+        ticks = ticks.wrapping_add(1);
+        Timer::after_millis(50).await;
+    }
+}
@@ -1,15 +1,19 @@
 //! # Audio DAC Example
 //!
-//! This example demonstrates the CS43L22 audio DAC control interface.
-//! While full audio playback requires I2S configuration (not yet implemented),
-//! this shows how to initialize and control the audio chip via I2C.
+//! This example demonstrates the CS43L22 audio DAC control interface and its
+//! built-in beep generator. Streaming audio data is not shown here; the beeps
+//! only need the master clock, which I2S3 provides through `AudioClock`.
 //!
 //! ## What This Example Does
 //!
 //! - Initializes I2C communication with the CS43L22 audio DAC
+//! - Hands I2S3 to the driver as the DAC's master clock (MCLK)
 //! - Configures audio output (speaker/headphone)
-//! - Sets and adjusts volume levels
-//! - Attempts to generate beep tones (limited without I2S)
+//! - Switches between speaker and headphones with the jack, reporting faults
+//! - Plays ascending and descending beep scales
+//! - Beeps at three beep volumes
+//! - Sweeps a tone from the left to the right channel with the balance control
+//! - Runs the generator in multiple-beep mode for a few seconds
 //!
 //! ## Running the Example
 //!
@@ -17,9 +21,7 @@
 //! cargo run --example audio_dac
 //! ```
 //!
-//! **Note:** Due to current limitations, this example demonstrates control
-//! functionality but does not produce actual audio output. Full audio requires
-//! I2S peripheral configuration.
+//! Plug headphones into the audio jack (CN4) to hear the beeps.
 //!
 //! ## Hardware Used
 //!
@@ -29,54 +31,59 @@
 //!   - SDA: PB9
 //!   - RESET: PD4
 //!   - I2C address: 0x4A
-//!
-//! ## Current Limitations
-//!
-//! This example shows I2C control only. Full audio functionality would require:
-//! - I2S peripheral setup for audio data streaming
-//! - DMA configuration for continuous audio transfer
-//! - Audio PLL configuration for precise sample rates
-//! - Proper clock configuration (MCLK, SCK, WS)
+//! - I2S3 as clock source: MCK PC7, SCK PC10, SD PC12, WS PA4, DMA1 stream 5
 //!
 //! ## CS43L22 Capabilities
 //!
 //! - 24-bit stereo DAC
 //! - Headphone and speaker amplifiers
 //! - Programmable volume control
-//! - Beep tone generator
+//! - Beep generator: 16 pitches from C4 to C7, single, multiple or continuous
 //! - Multiple sample rates (8kHz to 96kHz)
-//!
-//! ## What You Should See
-//!
-//! The example will:
-//! 1. Initialize the audio DAC chip
-//! 2. Configure output and volume
-//! 3. Attempt beep sequences (no sound without I2S)
-//! 4. Demonstrate volume control
-//!
-//! Debug output will show the control operations being performed.
 
 #![no_std]
 #![no_main]
 
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_time::Timer;
-use stm32f411ve_disco::audio::{CS43L22, OutputDevice, Volume};
+use embassy_time::{Duration, Timer};
+use stm32f411ve_disco::audio::{
+    AudioClock, Balance, Beep, BeepFrequency, BeepOffTime, BeepOnTime, BeepVolume, CS43L22, OutputDevice,
+    Volume,
+};
+use stm32f411ve_disco::playback::configure_audio_pll;
 use {defmt_rtt as _, panic_probe as _};
 
+/// C major scale on the beep generator
+const SCALE: [BeepFrequency; 8] = [
+    BeepFrequency::C5,
+    BeepFrequency::D5,
+    BeepFrequency::E5,
+    BeepFrequency::F5,
+    BeepFrequency::G5,
+    BeepFrequency::A5,
+    BeepFrequency::B5,
+    BeepFrequency::C6,
+];
+
 /// Main entry point - demonstrates audio DAC control
 ///
 /// This example shows how to:
 /// - Initialize audio hardware via I2C
+/// - Provide the master clock from I2S3
 /// - Configure audio output settings
-/// - Control volume programmatically
-/// - Attempt tone generation (limited without I2S)
+/// - Play tones with the beep generator
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
-    let p = embassy_stm32::init(Default::default());
+    let mut config = embassy_stm32::Config::default();
+    // The master clock comes from PLLI2S
+    configure_audio_pll(&mut config);
+    let p = embassy_stm32::init(config);
     info!("Audio DAC demo - CS43L22");
     
+    // DMA buffer for the silent I2S stream that carries MCLK
+    let mut clock_buffer = [0u16; 64];
+    
     // Initialize audio DAC
     let mut dac = CS43L22::new(
         p.I2C1,
@@ -85,6 +92,9 @@ async fn main(_spawner: Spawner) {
         p.PD4,  // RESET
     );
     
+    // The beep generator needs MCLK; the driver starts it while beeping
+    dac.set_clock(AudioClock::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut clock_buffer));
+    
     // Configure audio output
     // Auto mode will detect if headphones are plugged in
     dac.set_output(OutputDevice::Auto);
@@ -97,40 +107,49 @@ async fn main(_spawner: Spawner) {
     info!("Playing beep sequence...");
     
     loop {
-        // Attempt to play ascending tones
-        // Note: Without I2S setup, these won't produce actual sound
-        info!("Attempting ascending tones (I2C control only)");
-        for freq in [10, 20, 30, 40, 50] {
-            dac.beep(freq, 200);  // Frequency value, 200ms duration
-            Timer::after_millis(100).await;
+        // Report faults and which output the jack selected
+        dac.update_output();
+        info!("Output: {}, headphones: {}", dac.output(), dac.headphone_detected());
+        
+        info!("Ascending scale");
+        for note in SCALE {
+            dac.tone(note, Duration::from_millis(200)).await;
+            Timer::after_millis(50).await;
         }
         
         Timer::after_millis(500).await;
         
-        // Attempt to play descending tones
-        info!("Attempting descending tones (I2C control only)");
-        for freq in [50, 40, 30, 20, 10] {
-            dac.beep(freq, 200);  // Frequency value, 200ms duration
-            Timer::after_millis(100).await;
+        info!("Descending scale");
+        for note in SCALE.iter().rev() {
+            dac.tone(*note, Duration::from_millis(200)).await;
+            Timer::after_millis(50).await;
         }
         
         Timer::after_millis(1000).await;
         
-        // Demonstrate volume control
-        // These commands work via I2C and would affect actual audio if I2S was configured
-        info!("Testing volume control (30%, 60%, 90%)");
-        
-        dac.set_volume(Volume::new(30));  // Low volume
-        dac.beep(30, 500);
-        
-        dac.set_volume(Volume::new(60));  // Medium volume
-        dac.beep(30, 500);
+        // The beep generator has its own volume, on top of the master volume
+        info!("Beep volume -30 dB, -18 dB, -6 dB");
+        for db in [-30, -18, -6] {
+            dac.set_beep_volume(BeepVolume::from_db(db));
+            dac.beep(BeepFrequency::A5, BeepOnTime::Ms430).await;
+            Timer::after_millis(200).await;
+        }
         
-        dac.set_volume(Volume::new(90));  // High volume
-        dac.beep(30, 500);
+        // Move the tone from left to right
+        info!("Balance sweep");
+        dac.start_beep(Beep::continuous(BeepFrequency::C6));
+        for step in -4..=4 {
+            dac.set_balance(Balance::new(step as f32 / 4.0));
+            Timer::after_millis(300).await;
+        }
+        dac.stop_beep();
+        dac.set_balance(Balance::CENTER);
         
-        // Reset to default volume
-        dac.set_volume(Volume::new(80));
+        // The generator can repeat beeps on its own
+        info!("Multiple beeps for 5 seconds");
+        dac.start_beep(Beep::multiple(BeepFrequency::E6, BeepOnTime::Ms86, BeepOffTime::Ms1230));
+        Timer::after_secs(5).await;
+        dac.stop_beep();
         
         // Wait before repeating the sequence
         info!("Waiting 2 seconds before next cycle...");
@@ -7,14 +7,14 @@
 //!
 //! - Initializes the user button (B1) on PA0
 //! - Configures all four user LEDs
-//! - Polls the button state with debouncing
+//! - Sleeps until the button is pressed (EXTI interrupt, debounced)
 //! - Cycles through LED colors on each button press:
 //!   1. Orange LED only
 //!   2. Green LED only
 //!   3. Red LED only
 //!   4. Blue LED only
 //!   5. All LEDs on
-//!   (then repeats)
+//!      (then repeats)
 //!
 //! ## Running the Example
 //!
@@ -38,75 +38,52 @@
 //! - Released = LOW (0V)
 //! - Pressed = HIGH (3.3V)
 //!
-//! The example includes 50ms debouncing to prevent false triggers from
-//! mechanical button bounce.
+//! `ExtiButton` wakes the task on the EXTI0 interrupt and applies a 50ms
+//! debounce to prevent false triggers from mechanical button bounce.
 
 #![no_std]
 #![no_main]
 
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_time::Timer;
-use stm32f411ve_disco::{button::Button, leds::Leds};
+use stm32f411ve_disco::button::ExtiButton;
+use stm32f411ve_disco::leds::{Led, Leds};
 use {defmt_rtt as _, panic_probe as _};
 
 /// Main entry point - demonstrates button input handling
 ///
 /// This example shows how to:
-/// - Read digital input from the user button
-/// - Detect button press events (rising edge)
+/// - Wait for debounced button presses without polling
 /// - Use button input to control multiple outputs
-/// - Implement simple debouncing
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
     let p = embassy_stm32::init(Default::default());
-    info!("Button demo - polling user button to cycle LEDs");
+    info!("Button demo - press the user button to cycle LEDs");
 
-    let button = Button::new(p.PA0);
+    let mut button = ExtiButton::new(p.PA0, p.EXTI0);
     let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
 
     // State machine for cycling through LED patterns
-    let mut state = 0u8;
-    // Track previous button state for edge detection
-    let mut last_pressed = false;
+    let mut state = 0usize;
 
     loop {
-        let pressed = button.is_pressed();
-        
-        // Detect rising edge (button just pressed)
-        if pressed && !last_pressed {
-            info!("Button pressed!");
-            
-            // Cycle through LED states
-            leds.all_off();
-            match state {
-                0 => {
-                    info!("Orange LED");
-                    leds.ld3_orange.set_high();
-                }
-                1 => {
-                    info!("Green LED");
-                    leds.ld4_green.set_high();
-                }
-                2 => {
-                    info!("Red LED");
-                    leds.ld5_red.set_high();
-                }
-                3 => {
-                    info!("Blue LED");
-                    leds.ld6_blue.set_high();
-                }
-                _ => {
-                    info!("All LEDs");
-                    leds.all_on();
-                }
+        // The task sleeps here until the button is pressed
+        button.wait_for_press().await;
+        info!("Button pressed!");
+
+        // Cycle through LED states
+        leds.all_off();
+        match Led::ALL.get(state) {
+            Some(&led) => {
+                info!("{} LED", led);
+                leds[led].set_high();
+            }
+            None => {
+                info!("All LEDs");
+                leds.all_on();
             }
-            
-            state = (state + 1) % 5;
         }
-        
-        last_pressed = pressed;
-        // Small delay for button debouncing (prevents false triggers)
-        Timer::after_millis(50).await;
+
+        state = (state + 1) % (Led::ALL.len() + 1);
     }
 }
@@ -0,0 +1,108 @@
+//! # Persistent Calibration Example
+//!
+//! This example measures the gyroscope's zero-rate offset once, stores it in the
+//! internal flash and reuses it after every reset.
+//!
+//! ## What This Example Does
+//!
+//! - Opens the key-value store in flash sectors 6 and 7
+//! - Counts boots in an application-defined key
+//! - Loads the gyro bias, or measures and saves it if none is stored yet
+//! - Hold the user button during reset to force a new calibration
+//! - Prints bias-corrected angular rates twice per second
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example calibration
+//! ```
+//!
+//! Keep the board still while the blue LED is lit (calibration in progress).
+//! Reset the board to see the boot count go up and the bias being reused.
+//!
+//! ## Hardware Used
+//!
+//! - Internal flash, sectors 6-7 (0x0804_0000 - 0x0807_FFFF)
+//! - L3GD20 gyroscope (SPI1: PA5/PA6/PA7, CS: PE3)
+//! - User button B1 on PA0
+//! - LD4 (Green) on PD12, LD6 (Blue) on PD15
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use stm32f411ve_disco::button::Button;
+use stm32f411ve_disco::gyro::{AngularRate, L3GD20};
+use stm32f411ve_disco::leds::Leds;
+use stm32f411ve_disco::storage::{keys, Key, Storage};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Application keys start at 0x1000
+const BOOT_COUNT: Key<u32> = Key::new(0x1000);
+
+/// Number of readings averaged for the bias
+const SAMPLES: u16 = 200;
+
+/// Main entry point - loads or measures the gyro bias, then prints corrected rates
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Persistent calibration example");
+
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    let button = Button::new(p.PA0);
+    let mut gyro = L3GD20::new(p.SPI1, p.PA5, p.PA6, p.PA7, p.PE3);
+    let mut storage = unwrap!(Storage::new(p.FLASH));
+
+    let boots = unwrap!(storage.load(BOOT_COUNT)).unwrap_or(0) + 1;
+    unwrap!(storage.save(BOOT_COUNT, &boots));
+    info!("Boot #{}, {} bytes free in the active sector", boots, storage.free());
+
+    let stored = if button.is_pressed() {
+        info!("Button held, recalibrating");
+        None
+    } else {
+        unwrap!(storage.load(keys::GYRO_BIAS))
+    };
+
+    let bias = match stored {
+        Some(bias) => {
+            info!("Loaded gyro bias: {} dps", bias);
+            bias
+        }
+        None => {
+            leds.ld6_blue.set_high();
+            let bias = measure_bias(&mut gyro).await;
+            leds.ld6_blue.set_low();
+
+            unwrap!(storage.save(keys::GYRO_BIAS, &bias));
+            info!("Measured and saved gyro bias: {} dps", bias);
+            bias
+        }
+    };
+
+    leds.ld4_green.set_high();
+    loop {
+        let rate = gyro.read_angular_rate() - bias;
+        info!(
+            "Rate - X: {} dps, Y: {} dps, Z: {} dps",
+            rate.x, rate.y, rate.z
+        );
+        Timer::after_millis(500).await;
+    }
+}
+
+/// Average the gyro output while the board is still
+async fn measure_bias(gyro: &mut L3GD20<'_>) -> AngularRate {
+    info!("Measuring gyro bias, keep the board still...");
+    let mut sum = AngularRate::ZERO;
+    for _ in 0..SAMPLES {
+        while !gyro.data_ready() {
+            Timer::after_millis(1).await;
+        }
+        sum += gyro.read_angular_rate();
+    }
+    sum / SAMPLES as f32
+}
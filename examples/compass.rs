@@ -126,8 +126,8 @@ async fn main(_spawner: Spawner) {
         );
         
         // Display temperature from internal sensor
-        // Temperature has 8 LSB per degree Celsius resolution
-        info!("Temperature: {} °C", temp / 8);
+        // Uncalibrated readings are relative to a nominal 25 °C
+        info!("Temperature: {} °C", temp.celsius as i32);
         
         // Update rate: 5 Hz (every 200ms)
         // This is sufficient for most compass applications
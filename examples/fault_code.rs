@@ -0,0 +1,88 @@
+//! # Fault Code Example
+//!
+//! This example checks the onboard sensors at startup and reports the result
+//! without a serial console: a blink code on the red LED and the beeper for a
+//! fault, or "OK" in Morse code on the green LED.
+//!
+//! ## What This Example Does
+//!
+//! - Checks the gyroscope WHO_AM_I register (fault 12 on mismatch)
+//! - Probes for a known e-compass on I2C1 (fault 13 if none answers)
+//! - Repeats the result every few seconds
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example fault_code
+//! ```
+//!
+//! Fault 12 blinks once, pauses, then blinks twice. Plug headphones into the
+//! audio jack to hear it as well.
+//!
+//! ## Hardware Used
+//!
+//! - Gyroscope (SPI1: PA5/PA6/PA7, CS: PE3)
+//! - E-compass and CS43L22 audio DAC (I2C1: PB6/PB9, DAC reset: PD4)
+//! - I2S3 as the DAC master clock (PC7/PC10/PC12/PA4), DMA1 stream 5
+//! - LD4 (Green) on PD12, LD5 (Red) on PD14
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use stm32f411ve_disco::audio::{AudioClock, CS43L22};
+use stm32f411ve_disco::board::BoardRevision;
+use stm32f411ve_disco::gyro::{GyroModel, L3GD20};
+use stm32f411ve_disco::leds::{Led, Leds};
+use stm32f411ve_disco::morse::{BlinkCode, Morse, Signaller};
+use stm32f411ve_disco::playback::configure_audio_pll;
+use {defmt_rtt as _, panic_probe as _};
+
+/// Gyroscope WHO_AM_I mismatch
+const FAULT_GYRO_WHO_AM_I: u16 = 12;
+/// No e-compass answered on I2C1
+const FAULT_NO_COMPASS: u16 = 13;
+
+/// Main entry point - checks the sensors and signals the result
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = embassy_stm32::Config::default();
+    // The DAC's master clock comes from PLLI2S
+    configure_audio_pll(&mut config);
+    let mut p = embassy_stm32::init(config);
+    info!("Fault code demo");
+
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    let gyro = L3GD20::new(p.SPI1, p.PA5, p.PA6, p.PA7, p.PE3);
+    let revision = BoardRevision::detect(p.I2C1.reborrow(), p.PB6.reborrow(), p.PB9.reborrow());
+    let mut clock_buffer = [0u16; 64];
+    let mut dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+    dac.set_clock(AudioClock::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut clock_buffer));
+    dac.power_on();
+
+    let fault = if let GyroModel::Unknown(id) = gyro.model() {
+        error!("Gyro WHO_AM_I mismatch: {:#x}", id);
+        Some(FAULT_GYRO_WHO_AM_I)
+    } else if revision.is_none() {
+        error!("No e-compass found");
+        Some(FAULT_NO_COMPASS)
+    } else {
+        info!("All sensors found");
+        None
+    };
+
+    let blink = Signaller::default();
+    let morse = Signaller::from_wpm(12);
+    loop {
+        match fault {
+            Some(code) => {
+                let mut output = (&mut leds[Led::Red], &mut dac);
+                blink.play(&mut output, BlinkCode::new(code)).await;
+            }
+            None => morse.play(&mut leds[Led::Green], Morse::new("OK")).await,
+        }
+        Timer::after_secs(3).await;
+    }
+}
@@ -0,0 +1,60 @@
+//! # Button Gesture Example
+//!
+//! This example decodes clicks, double clicks, long presses and holds from the
+//! single user button, in a background task.
+//!
+//! ## What This Example Does
+//!
+//! - **Click**: toggle the green LED
+//! - **Double click**: toggle the blue LED
+//! - **Long press**: turn all LEDs off
+//! - **Hold** (after a long press): step a single light around the LEDs
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example gestures
+//! ```
+//!
+//! ## Hardware Used
+//!
+//! - User button (B1) on pin PA0 (EXTI0)
+//! - All four user LEDs (PD12-PD15)
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use stm32f411ve_disco::button::ExtiButton;
+use stm32f411ve_disco::gesture::{gesture_task, ButtonEvent, ButtonEvents, GestureConfig};
+use stm32f411ve_disco::leds::{Led, Leds};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Events from the gesture task
+static EVENTS: ButtonEvents = ButtonEvents::new();
+
+/// Main entry point - reacts to button gestures
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Button gesture demo");
+
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    let button = ExtiButton::new(p.PA0, p.EXTI0);
+    unwrap!(spawner.spawn(gesture_task(button, GestureConfig::default(), &EVENTS)));
+
+    loop {
+        let event = EVENTS.receive().await;
+        info!("{}", event);
+
+        match event {
+            ButtonEvent::Click => leds.toggle(Led::Green),
+            ButtonEvent::DoubleClick => leds.toggle(Led::Blue),
+            ButtonEvent::LongPress => leds.all_off(),
+            ButtonEvent::Hold(n) => {
+                leds.set_mask(Led::CLOCKWISE[n as usize % Led::CLOCKWISE.len()].mask())
+            }
+        }
+    }
+}
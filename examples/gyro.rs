@@ -103,7 +103,7 @@ async fn main(_spawner: Spawner) {
             rate.x as i32,  // Roll rate
             rate.y as i32,  // Pitch rate
             rate.z as i32,  // Yaw rate
-            temp            // Temperature (raw value, 1 LSB = 1°C)
+            temp.celsius as i32  // Temperature (uncalibrated, relative to 25 °C)
         );
         
         // Update rate: 10 Hz (every 100ms)
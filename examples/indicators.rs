@@ -0,0 +1,78 @@
+//! # Tilt and Heading Indicator Example
+//!
+//! The classic Discovery board demo: the four user LEDs around the MCU show
+//! which way the board is tilted, or which way is north.
+//!
+//! ## What This Example Does
+//!
+//! - **Tilt mode** (default): the LEDs on the lower side of the board light up,
+//!   brighter the further the board is tilted
+//! - **Heading mode**: the LEDs point toward magnetic north
+//! - Press the user button to switch between the two modes
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example indicators
+//! ```
+//!
+//! Hold the board level for heading mode, and keep it away from magnets
+//! and large metal objects.
+//!
+//! ## Hardware Used
+//!
+//! - E-compass (I2C1: PB6/PB9), LSM303DLHC or LSM303AGR
+//! - All four user LEDs on TIM4 PWM (PD12-PD15)
+//! - User button (B1) on pin PA0
+
+#![no_std]
+#![no_main]
+
+// PwmLeds drives the LED pins from TIM4, which this feature hands to embassy-time
+#[cfg(feature = "time-driver-tim4")]
+compile_error!("this example needs `leds::PwmLeds`: build it with a time driver other than `time-driver-tim4`");
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use stm32f411ve_disco::animation::LedOutput;
+use stm32f411ve_disco::board::ECompass;
+use stm32f411ve_disco::button::Button;
+use stm32f411ve_disco::compass::LSM303DLHC;
+use stm32f411ve_disco::indicators;
+use stm32f411ve_disco::leds::PwmLeds;
+use stm32f411ve_disco::sensor::{Accelerometer, Magnetometer};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Main entry point - shows tilt or heading on the LEDs
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Tilt/heading indicator demo - press the button to switch modes");
+
+    let mut leds = PwmLeds::new(p.TIM4, p.PD13, p.PD12, p.PD14, p.PD15);
+    let mut compass = ECompass::new(p.I2C1, p.PB6, p.PB9);
+    let button = Button::new(p.PA0);
+
+    let mut heading_mode = false;
+    let mut last_pressed = false;
+
+    loop {
+        let pressed = button.is_pressed();
+        if pressed && !last_pressed {
+            heading_mode = !heading_mode;
+            info!("Mode: {}", if heading_mode { "heading" } else { "tilt" });
+        }
+        last_pressed = pressed;
+
+        let frame = if heading_mode {
+            let heading = LSM303DLHC::calculate_heading(&compass.read_magnetic_field());
+            indicators::heading(heading)
+        } else {
+            indicators::tilt(&compass.read_acceleration())
+        };
+        leds.show(frame);
+
+        Timer::after_millis(20).await;
+    }
+}
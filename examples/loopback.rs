@@ -0,0 +1,144 @@
+//! # Microphone Loopback Example
+//!
+//! This example plays the MEMS microphone on the headphones in real time, through
+//! a DSP chain that can be switched with the user button.
+//!
+//! ## What This Example Does
+//!
+//! - Runs the core at 96 MHz and enables PLLI2S for the audio clocks
+//! - Captures the microphone with I2S2 and DMA and decimates the PDM stream to 16 kHz
+//! - Processes 1 ms blocks and streams them to the CS43L22 (about 6 ms latency)
+//! - Cycles through DSP presets on every button press:
+//!   1. Flat: DC/rumble high-pass and gain
+//!   2. Voice: adds a presence boost, a 4 kHz low-pass and a noise gate
+//!   3. Muted
+//! - Lights LD4 (Green) while the noise gate is open
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example loopback
+//! ```
+//!
+//! Plug headphones into the audio jack (CN4) and keep the volume low at first:
+//! the microphone can pick up the headphones and howl.
+//!
+//! ## Hardware Used
+//!
+//! - MP45DT02 MEMS Microphone
+//!   - CLK_IN: PB10 (I2S2_CK), PDM_OUT: PC3 (I2S2_SD), DMA1 stream 3
+//! - CS43L22 Audio DAC
+//!   - I2C1: PB6/PB9, RESET: PD4
+//!   - I2S3: MCK PC7, SCK PC10, SD PC12, WS PA4, DMA1 stream 5
+//! - User button B1 on PA0
+//! - LD4 (Green) on PD12
+
+#![no_std]
+#![no_main]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use defmt::*;
+use embassy_executor::Spawner;
+use stm32f411ve_disco::audio::{CS43L22, OutputDevice, Volume};
+use stm32f411ve_disco::button::ExtiButton;
+use stm32f411ve_disco::dsp::{Biquad, Gain, GateSettings, NoiseGate, Processor};
+use stm32f411ve_disco::leds::Leds;
+use stm32f411ve_disco::loopback::{self, run_loopback, MIC_BUFFER_WORDS, OUTPUT_BUFFER_WORDS, SAMPLE_RATE};
+use stm32f411ve_disco::microphone::PdmMicrophone;
+use stm32f411ve_disco::playback::PcmOutput;
+use {defmt_rtt as _, panic_probe as _};
+
+/// Number of presets
+const PRESETS: usize = 3;
+
+/// Preset selected with the button
+static PRESET: AtomicUsize = AtomicUsize::new(0);
+
+/// Microphone level boost (the MP45DT02 peaks around -26 dBFS at 94 dB SPL)
+const MIC_GAIN_DB: f32 = 24.0;
+
+/// The DSP chain of a preset
+type Chain = (Biquad, Option<Biquad>, Option<Biquad>, Option<NoiseGate>, Gain);
+
+/// Build the chain of preset `index`
+fn preset(index: usize) -> Chain {
+    let high_pass = Biquad::high_pass(SAMPLE_RATE, 120.0, 0.707);
+    match index {
+        0 => (high_pass, None, None, None, Gain::from_db(MIC_GAIN_DB)),
+        1 => (
+            high_pass,
+            Some(Biquad::peaking(SAMPLE_RATE, 2_500.0, 1.0, 6.0)),
+            Some(Biquad::low_pass(SAMPLE_RATE, 4_000.0, 0.707)),
+            Some(NoiseGate::new(SAMPLE_RATE, GateSettings { threshold_db: -45.0, ..Default::default() })),
+            Gain::from_db(MIC_GAIN_DB),
+        ),
+        _ => (high_pass, None, None, None, Gain::from_db(-120.0)),
+    }
+}
+
+/// Runs the selected preset, rebuilding the chain when the selection changes
+struct Presets<'a> {
+    index: usize,
+    chain: Chain,
+    led: &'a mut embassy_stm32::gpio::Output<'static>,
+}
+
+impl Processor for Presets<'_> {
+    fn process(&mut self, samples: &mut [f32]) {
+        let index = PRESET.load(Ordering::Relaxed);
+        if index != self.index {
+            self.index = index;
+            self.chain = preset(index);
+        }
+        self.chain.process(samples);
+
+        let gate_open = self.chain.3.as_ref().is_some_and(NoiseGate::is_open);
+        self.led.set_level(gate_open.into());
+    }
+
+    fn reset(&mut self) {
+        self.chain.reset();
+    }
+}
+
+/// Select the next preset on every button press
+#[embassy_executor::task]
+async fn button_task(mut button: ExtiButton<'static>) {
+    loop {
+        button.wait_for_press().await;
+        let index = (PRESET.load(Ordering::Relaxed) + 1) % PRESETS;
+        PRESET.store(index, Ordering::Relaxed);
+        info!("Preset {}", ["flat", "voice", "muted"][index]);
+    }
+}
+
+/// Main entry point - runs the loopback
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let mut config = embassy_stm32::Config::default();
+    loopback::configure_clocks(&mut config);
+    let p = embassy_stm32::init(config);
+    info!("Microphone loopback example");
+
+    let mut dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+    dac.set_output(OutputDevice::Headphone);
+    dac.set_volume(Volume::from_db(-20.0));
+    dac.power_on();
+
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    unwrap!(spawner.spawn(button_task(ExtiButton::new(p.PA0, p.EXTI0))));
+
+    let mut mic_buffer = [0u16; MIC_BUFFER_WORDS];
+    let mut mic = PdmMicrophone::new(p.SPI2, p.PB10, p.PC3, p.DMA1_CH3, &mut mic_buffer);
+    let mut out_buffer = [0u16; OUTPUT_BUFFER_WORDS];
+    let mut output = PcmOutput::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut out_buffer);
+
+    let mut presets = Presets {
+        index: 0,
+        chain: preset(0),
+        led: &mut leds.ld4_green,
+    };
+    info!("Press the user button to change the preset");
+    run_loopback(&mut mic, &mut output, &mut presets).await
+}
@@ -0,0 +1,81 @@
+//! # Low-Power STOP Mode Example
+//!
+//! This example sleeps in STOP mode between short bursts of activity, as a
+//! battery-powered logger would.
+//!
+//! ## What This Example Does
+//!
+//! - Powers down the gyroscope and magnetometer while asleep, and holds the
+//!   audio DAC in reset (its lowest-power state)
+//! - Arms the accelerometer to wake the MCU when the board is moved
+//! - Wakes on the user button, on motion, or every 10 seconds from the RTC
+//! - Flashes an LED for the wake reason: blue = RTC, orange = motion,
+//!   green = button
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example low_power
+//! ```
+//!
+//! RTT logging keeps working while the debugger is attached, but the debug
+//! connection itself keeps some clocks running. Measure current through JP2
+//! with the ST-LINK disconnected to see the real STOP consumption.
+//!
+//! ## Hardware Used
+//!
+//! - User button (B1) on PA0
+//! - LSM303DLHC accelerometer INT1 on PE4 (I2C1: PB6/PB9)
+//! - L3GD20 gyroscope (SPI1), CS43L22 audio DAC reset on PD4
+//! - All four user LEDs (PD12-PD15)
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::gpio::{Level, Output, Speed};
+use embassy_stm32::rtc::{Rtc, RtcConfig};
+use embassy_time::{Duration, Timer};
+use stm32f411ve_disco::compass::LSM303DLHC;
+use stm32f411ve_disco::gyro::L3GD20;
+use stm32f411ve_disco::leds::{Led, Leds};
+use stm32f411ve_disco::power::{LowPower, WakeReason, WakeSources};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Main entry point - sleeps until woken, then shows why
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Low-power STOP mode demo");
+
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    let mut gyro = L3GD20::new(p.SPI1, p.PA5, p.PA6, p.PA7, p.PE3);
+    let mut compass = LSM303DLHC::new(p.I2C1, p.PB6, p.PB9);
+    compass.enable_motion_interrupt(0.25);
+    let _dac_reset = Output::new(p.PD4, Level::Low, Speed::Low);
+
+    let rtc = Rtc::new(p.RTC, RtcConfig::default());
+    let mut power = LowPower::new(rtc, p.PE4, p.EXTI4);
+
+    let wake = WakeSources {
+        button: true,
+        motion: true,
+        rtc: Some(Duration::from_secs(10)),
+    };
+
+    loop {
+        leds.all_off();
+        let reason = power.stop(wake, &mut [&mut gyro, &mut compass]);
+        compass.clear_motion_interrupt();
+
+        let led = match reason {
+            WakeReason::Rtc => Led::Blue,
+            WakeReason::Motion => Led::Orange,
+            WakeReason::Button => Led::Green,
+            WakeReason::Other => Led::Red,
+        };
+        leds[led].set_high();
+        Timer::after_millis(200).await;
+    }
+}
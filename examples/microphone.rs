@@ -1,15 +1,14 @@
 //! # Microphone Example
 //!
-//! This example demonstrates the MP45DT02 MEMS microphone interface.
-//! Due to current limitations, this is a simplified demonstration that shows
-//! the API structure but returns dummy audio data.
+//! This example captures audio from the MP45DT02 MEMS microphone and logs the
+//! sound level ten times per second.
 //!
 //! ## What This Example Does
 //!
-//! - Initializes the MP45DT02 MEMS microphone
-//! - Demonstrates the recording API (simplified)
-//! - Shows how audio samples would be read
-//! - Displays sample data (currently dummy values)
+//! - Runs the core at 96 MHz and enables PLLI2S for the microphone clock
+//! - Captures 16 kHz PCM with [`PdmMicrophone`] (1.024 MHz PDM clock)
+//! - Logs the peak and RMS level of every 100 ms block in dBFS
+//! - Lights the orange LED (LD3) while the level is above -40 dBFS
 //!
 //! ## Running the Example
 //!
@@ -17,23 +16,14 @@
 //! cargo run --example microphone
 //! ```
 //!
-//! **Note:** This is a demonstration of the API. Full PDM audio capture
-//! requires I2S/SPI peripheral configuration with DMA and decimation filtering.
+//! Speak or clap near the microphone (U7, next to the audio jack) and watch the
+//! levels rise from the noise floor.
 //!
 //! ## Hardware Used
 //!
 //! - MP45DT02 MEMS Microphone
-//!   - PDM_OUT: PC3 (PDM data output)
-//!   - CLK_IN: PB10 (Clock input to microphone)
-//!
-//! ## Current Limitations
-//!
-//! Full microphone functionality would require:
-//! - I2S peripheral in PDM receive mode
-//! - DMA for continuous PDM bit stream capture
-//! - CIC (Cascaded Integrator-Comb) decimation filter
-//! - Low-pass filtering for anti-aliasing
-//! - PDM to PCM conversion
+//!   - CLK_IN: PB10 (I2S2_CK), PDM_OUT: PC3 (I2S2_SD), DMA1 stream 3
+//! - Orange LED (LD3): PD13
 //!
 //! ## MP45DT02 Specifications
 //!
@@ -47,15 +37,15 @@
 //! ## Understanding PDM Audio
 //!
 //! PDM (Pulse Density Modulation) is a high-frequency (MHz) 1-bit stream
-//! that encodes audio amplitude in the density of pulses. Converting PDM
-//! to usable PCM audio requires:
-//! 1. Oversampling the PDM stream (typically 64x)
-//! 2. Decimation filtering to reduce sample rate
-//! 3. Low-pass filtering to remove high-frequency noise
+//! that encodes audio amplitude in the density of pulses. [`PdmMicrophone`]
+//! converts it to PCM in software:
+//! 1. I2S2 clocks the microphone and DMA copies the bit stream to RAM
+//! 2. A 4th-order CIC filter decimates it by 64 to 16-bit samples
+//! 3. A DC-blocking filter removes the microphone's offset
 //!
 //! ## Audio Sample Format
 //!
-//! The example uses 16-bit signed integers (i16) for audio samples:
+//! Samples are 16-bit signed integers (i16):
 //! - Range: -32768 to +32767
 //! - 0 = silence
 //! - Positive = compression
@@ -66,64 +56,56 @@
 
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_time::Timer;
-use stm32f411ve_disco::microphone::MP45DT02;
+use embassy_stm32::gpio::{Level, Output, Speed};
+use micromath::F32Ext;
+use stm32f411ve_disco::loopback;
+use stm32f411ve_disco::microphone::PdmMicrophone;
 use {defmt_rtt as _, panic_probe as _};
 
-/// Main entry point - demonstrates microphone API
-///
-/// This example shows the structure of audio capture code,
-/// though actual PDM capture is not implemented.
+/// PCM sample rate
+const SAMPLE_RATE: u32 = 16_000;
+
+/// Samples per level measurement (100 ms)
+const BLOCK: usize = SAMPLE_RATE as usize / 10;
+
+/// Level above which the LED lights
+const THRESHOLD_DB: f32 = -40.0;
+
+/// Level of `amplitude` relative to full scale, in dB
+fn dbfs(amplitude: f32) -> f32 {
+    20.0 * (amplitude.max(1.0) / 32768.0).log10()
+}
+
+/// Main entry point - logs the microphone level
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
-    let p = embassy_stm32::init(Default::default());
+    let mut config = embassy_stm32::Config::default();
+    // The PDM filter needs the faster core clock, and I2S2 runs from PLLI2S
+    loopback::configure_clocks(&mut config);
+    let p = embassy_stm32::init(config);
     info!("Microphone demo - MP45DT02 MEMS microphone");
-    
-    // Initialize microphone
-    let mut mic = MP45DT02::new(p.PC3, p.PB10);
-    
-    info!("Starting audio capture...");
-    
-    // Buffer for audio samples
-    // In a real application, this would hold PCM audio data
-    // converted from the PDM stream
-    let mut audio_buffer = [0i16; 256];
-    
+
+    let mut led = Output::new(p.PD13, Level::Low, Speed::Low);
+
+    let mut buffer = [0u16; 256];
+    let mut mic = PdmMicrophone::new(p.SPI2, p.PB10, p.PC3, p.DMA1_CH3, &mut buffer);
+    mic.start(SAMPLE_RATE).await;
+    info!("Capturing at {} Hz", mic.sample_rate());
+
+    let mut samples = [0i16; BLOCK];
     loop {
-        // Start recording
-        // In a real implementation, this would:
-        // - Enable I2S peripheral
-        // - Start DMA transfer
-        // - Begin generating clock for microphone
-        mic.start_recording().await;
-        
-        // Read audio samples
-        // Currently returns dummy data for demonstration
-        // Real implementation would return decimated PCM samples
-        let samples_read = mic.read_samples(&mut audio_buffer).await;
-        info!("Read {} audio samples (demonstration values)", samples_read);
-        
-        // Display first few samples
-        // In a real application, these would be actual audio amplitude values
-        // You could process these for:
-        // - Volume level detection
-        // - Frequency analysis (FFT)
-        // - Voice activity detection
-        // - Audio recording to storage
-        info!("Sample values [0-4]: {} {} {} {} {}", 
-            audio_buffer[0],   // First sample
-            audio_buffer[1],   // Second sample
-            audio_buffer[2],   // Third sample
-            audio_buffer[3],   // Fourth sample
-            audio_buffer[4]    // Fifth sample
-        );
-        
-        // Stop recording
-        // This would stop the I2S peripheral and DMA transfers
-        mic.stop_recording().await;
-        
-        // Wait before next recording cycle
-        // In a real application, you might process the audio data here
-        Timer::after_millis(1000).await;
+        if let Err(e) = mic.read(&mut samples).await {
+            // Audio was lost: the buffer was emptied, measure the next block
+            warn!("Capture error: {}", e);
+            continue;
+        }
+
+        let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+        let energy: f32 = samples.iter().map(|&s| (s as f32) * (s as f32)).sum();
+        let rms = (energy / BLOCK as f32).sqrt();
+
+        let level = dbfs(rms);
+        led.set_level((level > THRESHOLD_DB).into());
+        info!("Peak {} dBFS, RMS {} dBFS", dbfs(peak as f32), level);
     }
 }
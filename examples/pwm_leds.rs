@@ -0,0 +1,77 @@
+//! # PWM LED Example
+//!
+//! This example drives the four user LEDs from TIM4 to control their brightness.
+//!
+//! ## What This Example Does
+//!
+//! 1. **Breathe**: All LEDs fade up and down together
+//! 2. **Cross-fade**: Brightness moves around the ring of LEDs
+//! 3. **Levels**: Each LED is held at a different fixed brightness
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example pwm_leds
+//! ```
+//!
+//! ## Hardware Used
+//!
+//! All four user LEDs on TIM4:
+//! - LD4 (Green) on pin PD12 (CH1)
+//! - LD3 (Orange) on pin PD13 (CH2)
+//! - LD5 (Red) on pin PD14 (CH3)
+//! - LD6 (Blue) on pin PD15 (CH4)
+//!
+//! ## Brightness
+//!
+//! Brightness is gamma corrected, so a fade looks even to the eye instead of
+//! jumping to near full brightness in the first few percent.
+
+#![no_std]
+#![no_main]
+
+// PwmLeds drives the LED pins from TIM4, which this feature hands to embassy-time
+#[cfg(feature = "time-driver-tim4")]
+compile_error!("this example needs `leds::PwmLeds`: build it with a time driver other than `time-driver-tim4`");
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use stm32f411ve_disco::leds::{Led, PwmLeds};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Main entry point - cycles through brightness patterns
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("PWM LED demo");
+
+    let mut leds = PwmLeds::new(p.TIM4, p.PD13, p.PD12, p.PD14, p.PD15);
+
+    loop {
+        info!("Pattern: Breathe");
+        for _ in 0..3 {
+            leds.fade_all(100, Duration::from_millis(800)).await;
+            leds.fade_all(0, Duration::from_millis(800)).await;
+        }
+
+        // Clockwise around the board: orange (top), blue (right), red (bottom), green (left)
+        info!("Pattern: Cross-fade");
+        for _ in 0..3 {
+            for led in Led::CLOCKWISE {
+                let mut targets = [0; 4];
+                targets[led as usize] = 100;
+                leds.fade_to(targets, Duration::from_millis(300)).await;
+            }
+        }
+        leds.fade_all(0, Duration::from_millis(300)).await;
+
+        info!("Pattern: Levels");
+        for (led, percent) in Led::CLOCKWISE.into_iter().zip([10, 30, 60, 100]) {
+            leds.set_brightness(led, percent);
+        }
+        Timer::after_millis(2000).await;
+        leds.all_off();
+        Timer::after_millis(500).await;
+    }
+}
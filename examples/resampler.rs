@@ -0,0 +1,78 @@
+//! # Resampler Example
+//!
+//! This example plays the MEMS microphone on the headphones at 48 kHz while the
+//! microphone runs from its own 2.4 MHz PDM clock (37.5 kHz PCM), converting
+//! between the two rates on the fly.
+//!
+//! ## What This Example Does
+//!
+//! - Runs the core at 96 MHz and enables PLLI2S for the audio clocks
+//! - Starts the microphone with a 2.4 MHz PDM clock
+//! - Filters the audio at the microphone rate (high-pass, 8 kHz low-pass, gain)
+//! - Resamples it to 48 kHz and streams it to the CS43L22
+//! - Trims the conversion ratio from the microphone buffer level, so the slightly
+//!   different clocks cause no clicks, and logs the measured drift every second
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example resampler
+//! ```
+//!
+//! Plug headphones into the audio jack (CN4) and keep the volume low at first:
+//! the microphone can pick up the headphones and howl.
+//!
+//! ## Hardware Used
+//!
+//! - MP45DT02 MEMS Microphone
+//!   - CLK_IN: PB10 (I2S2_CK), PDM_OUT: PC3 (I2S2_SD), DMA1 stream 3
+//! - CS43L22 Audio DAC
+//!   - I2C1: PB6/PB9, RESET: PD4
+//!   - I2S3: MCK PC7, SCK PC10, SD PC12, WS PA4, DMA1 stream 5
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use stm32f411ve_disco::audio::{CS43L22, OutputDevice, Volume};
+use stm32f411ve_disco::dsp::{Biquad, Gain};
+use stm32f411ve_disco::loopback::{self, run_resampled_loopback, OUTPUT_BUFFER_WORDS, RESAMPLED_MIC_BUFFER_WORDS};
+use stm32f411ve_disco::microphone::{PdmMicrophone, SampleRate};
+use stm32f411ve_disco::playback::PcmOutput;
+use {defmt_rtt as _, panic_probe as _};
+
+/// DAC sample rate
+const OUTPUT_RATE: u32 = 48_000;
+
+/// Main entry point - runs the resampling loopback
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = embassy_stm32::Config::default();
+    loopback::configure_clocks(&mut config);
+    let p = embassy_stm32::init(config);
+    info!("Resampler example");
+
+    let mut dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+    dac.set_output(OutputDevice::Headphone);
+    dac.set_volume(Volume::from_db(-20.0));
+    dac.power_on();
+
+    let mut mic_buffer = [0u16; RESAMPLED_MIC_BUFFER_WORDS];
+    let mut mic = PdmMicrophone::new(p.SPI2, p.PB10, p.PC3, p.DMA1_CH3, &mut mic_buffer);
+    // Twice the usual output buffer: 48 kHz fills it three times as fast as 16 kHz
+    let mut out_buffer = [0u16; 2 * OUTPUT_BUFFER_WORDS];
+    let mut output = PcmOutput::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut out_buffer);
+
+    mic.start_with_clock(SampleRate::MHz2_4).await;
+    let rate = mic.sample_rate();
+    info!("Converting {} Hz to {} Hz", rate, OUTPUT_RATE);
+
+    let mut chain = (
+        Biquad::high_pass(rate, 120.0, 0.707),
+        // Linear interpolation mirrors high frequencies; keep the input band-limited
+        Biquad::low_pass(rate, 8_000.0, 0.707),
+        Gain::from_db(24.0),
+    );
+    run_resampled_loopback(&mut mic, &mut output, OUTPUT_RATE, &mut chain).await
+}
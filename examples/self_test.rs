@@ -0,0 +1,91 @@
+//! # Sensor Self-Test Example
+//!
+//! This example runs the built-in self-test of the L3GD20 gyroscope and the
+//! LSM303DLHC accelerometer, as a quick board bring-up check.
+//!
+//! ## What This Example Does
+//!
+//! - Initializes both motion sensors
+//! - Runs each sensor's self-test (baseline vs. self-test actuation)
+//! - Checks the output change against the default limits, which are not datasheet
+//!   min/max values; a production line should set its own
+//! - Reports the measured output change and a pass/fail result
+//! - Lights the green LED if both sensors pass, the red LED otherwise
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example self_test
+//! ```
+//!
+//! Keep the board still on the bench while the test runs (under a second).
+//!
+//! ## Hardware Used
+//!
+//! - L3GD20 gyroscope (SPI1: PA5/PA6/PA7, CS: PE3)
+//! - LSM303DLHC e-compass (I2C1: PB6/PB9)
+//! - LD4 (Green) on PD12, LD5 (Red) on PD14
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use stm32f411ve_disco::compass::{AccelSelfTestLimits, LSM303DLHC};
+use stm32f411ve_disco::gyro::{SelfTestLimits, L3GD20};
+use stm32f411ve_disco::leds::Leds;
+use {defmt_rtt as _, panic_probe as _};
+
+/// Number of readings averaged with the self-test off and on
+const SAMPLES: u8 = 5;
+
+/// Main entry point - runs both sensor self-tests once
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Sensor self-test");
+
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+    let mut gyro = L3GD20::new(p.SPI1, p.PA5, p.PA6, p.PA7, p.PE3);
+    let mut compass = LSM303DLHC::new(p.I2C1, p.PB6, p.PB9);
+
+    let gyro_passed = match gyro.self_test(SAMPLES, SelfTestLimits::default()).await {
+        Ok(report) => {
+            info!(
+                "Gyro delta - X: {} dps, Y: {} dps, Z: {} dps",
+                report.delta.x as i32,
+                report.delta.y as i32,
+                report.delta.z as i32
+            );
+            report.passed
+        }
+        Err(e) => {
+            error!("Gyro self-test error: {}", e);
+            false
+        }
+    };
+
+    let accel_passed = match compass.accel_self_test(SAMPLES, AccelSelfTestLimits::default()).await {
+        Ok(report) => {
+            info!(
+                "Accel delta - X: {} mg, Y: {} mg, Z: {} mg",
+                (report.delta.x * 1000.0) as i32,
+                (report.delta.y * 1000.0) as i32,
+                (report.delta.z * 1000.0) as i32
+            );
+            report.passed
+        }
+        Err(e) => {
+            error!("Accelerometer self-test error: {}", e);
+            false
+        }
+    };
+
+    if gyro_passed && accel_passed {
+        info!("Self-test PASSED");
+        leds.ld4_green.set_high();
+    } else {
+        error!("Self-test FAILED (gyro: {}, accel: {})", gyro_passed, accel_passed);
+        leds.ld5_red.set_high();
+    }
+}
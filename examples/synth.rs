@@ -0,0 +1,121 @@
+//! # Synthesizer Example
+//!
+//! This example plays synthesized sounds through the CS43L22 audio DAC: a
+//! two-voice RTTTL jingle and an alert made of chords.
+//!
+//! ## What This Example Does
+//!
+//! - Enables PLLI2S for the I2S audio clock
+//! - Parses a melody and a bass line in RTTTL and plays them together
+//! - Starts and releases notes by hand for a three-note alert chord
+//! - Alternates between the two on every button press
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example synth
+//! ```
+//!
+//! Plug headphones into the audio jack (CN4) and press the blue user button.
+//!
+//! ## Hardware Used
+//!
+//! - CS43L22 Audio DAC
+//!   - I2C1: PB6/PB9, RESET: PD4
+//!   - I2S3: MCK PC7, SCK PC10, SD PC12, WS PA4, DMA1 stream 5
+//! - User button B1 on PA0
+//! - LD6 (Blue) on PD15, lit while playing
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use stm32f411ve_disco::audio::{CS43L22, OutputDevice, Volume};
+use stm32f411ve_disco::button::ExtiButton;
+use stm32f411ve_disco::leds::Leds;
+use stm32f411ve_disco::playback::{configure_audio_pll, Error, PcmOutput};
+use stm32f411ve_disco::synth::{midi_to_hz, Instrument, Rtttl, Sequencer, Synth};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Output sample rate
+const RATE: u32 = 16_000;
+
+/// Melody of the jingle
+const MELODY: &str = "Melody:d=8,o=5,b=140:c,e,g,c6,4p,g,4c6,4p,a,f,d,4b4,2c";
+
+/// Bass line of the jingle
+const BASS: &str = "Bass:d=4,o=3,b=140:c,g2,c,e,f,g,2c";
+
+/// Alert chords (MIDI notes), each held for 150 ms
+const ALERT: [[u8; 3]; 3] = [[72, 76, 79], [74, 77, 81], [72, 76, 84]];
+
+/// Main entry point - plays a jingle or an alert on every button press
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = embassy_stm32::Config::default();
+    configure_audio_pll(&mut config);
+    let p = embassy_stm32::init(config);
+    info!("Synthesizer example");
+
+    let melody = unwrap!(Rtttl::parse(MELODY));
+    let bass = unwrap!(Rtttl::parse(BASS));
+    info!("'{}' at {} bpm, {} ms", melody.name(), melody.bpm(), melody.duration().as_millis());
+
+    let mut dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+    dac.set_output(OutputDevice::Headphone);
+    dac.set_volume(Volume::from_db(-10.0));
+    dac.power_on();
+
+    let mut buffer = [0u16; 2048];
+    let mut output = PcmOutput::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut buffer);
+
+    let mut button = ExtiButton::new(p.PA0, p.EXTI0);
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+
+    loop {
+        leds.ld6_blue.set_high();
+        info!("Playing jingle");
+        let song = Sequencer::new(RATE, [(melody.notes(), Instrument::BELL), (bass.notes(), Instrument::BASS)]);
+        if let Err(e) = output.play(RATE, song).await {
+            warn!("Playback error: {}", e);
+        }
+        leds.ld6_blue.set_low();
+
+        button.wait_for_press().await;
+
+        leds.ld6_blue.set_high();
+        info!("Playing alert");
+        if let Err(e) = play_alert(&mut output).await {
+            warn!("Playback error: {}", e);
+        }
+        leds.ld6_blue.set_low();
+
+        button.wait_for_press().await;
+    }
+}
+
+/// Play the alert chords, rendering the synthesizer into the stream block by block
+async fn play_alert(output: &mut PcmOutput<'_>) -> Result<(), Error> {
+    let mut synth: Synth<3> = Synth::new(RATE);
+    let mut frames = [[0i16; 2]; 80];
+    let mut stream = output.open(RATE);
+
+    for chord in ALERT {
+        let voices = chord.map(|note| synth.note_on(midi_to_hz(note), Instrument::BUZZER));
+        // 30 blocks of 5 ms
+        for _ in 0..30 {
+            synth.render(&mut frames);
+            stream.write(&frames).await?;
+        }
+        for voice in voices {
+            synth.note_off(voice);
+        }
+    }
+
+    while !synth.is_idle() {
+        synth.render(&mut frames);
+        stream.write(&frames).await?;
+    }
+    stream.finish().await
+}
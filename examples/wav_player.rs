@@ -0,0 +1,83 @@
+//! # WAV Player Example
+//!
+//! This example plays WAV files embedded in flash through the CS43L22 audio DAC:
+//! the same chime as raw PCM and as IMA ADPCM, which takes a quarter of the flash.
+//!
+//! ## What This Example Does
+//!
+//! - Enables PLLI2S for the I2S audio clock
+//! - Parses WAV files included with `include_bytes!`
+//! - Streams them over I2S3 with DMA at the file's own sample rate
+//! - Decodes the ADPCM file while it plays
+//! - Plays mono files on both channels
+//! - Plays the next sound every time the user button is pressed
+//!
+//! ## Running the Example
+//!
+//! ```bash
+//! cargo run --example wav_player
+//! ```
+//!
+//! Plug headphones into the audio jack (CN4) and press the blue user button.
+//!
+//! ## Hardware Used
+//!
+//! - CS43L22 Audio DAC
+//!   - I2C1: PB6/PB9, RESET: PD4
+//!   - I2S3: MCK PC7, SCK PC10, SD PC12, WS PA4, DMA1 stream 5
+//! - User button B1 on PA0
+//! - LD6 (Blue) on PD15, lit while playing
+
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use stm32f411ve_disco::audio::{CS43L22, OutputDevice, Volume};
+use stm32f411ve_disco::button::ExtiButton;
+use stm32f411ve_disco::leds::Leds;
+use stm32f411ve_disco::playback::{configure_audio_pll, PcmOutput};
+use stm32f411ve_disco::wav::Wav;
+use {defmt_rtt as _, panic_probe as _};
+
+/// 16 kHz, 16-bit mono chime
+static CHIME: &[u8] = include_bytes!("assets/chime.wav");
+
+/// The same chime as 4-bit IMA ADPCM
+static CHIME_IMA: &[u8] = include_bytes!("assets/chime_ima.wav");
+
+/// Main entry point - plays a chime on every button press
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = embassy_stm32::Config::default();
+    configure_audio_pll(&mut config);
+    let p = embassy_stm32::init(config);
+    info!("WAV player example");
+
+    let sounds = [unwrap!(Wav::parse(CHIME)), unwrap!(Wav::parse(CHIME_IMA))];
+    for (wav, file) in sounds.iter().zip([CHIME, CHIME_IMA]) {
+        info!("Loaded {}: {} frames, {} bytes", wav.format(), wav.frame_count(), file.len());
+    }
+
+    let mut dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+    dac.set_output(OutputDevice::Headphone);
+    dac.set_volume(Volume::from_db(-10.0));
+    dac.power_on();
+
+    let mut buffer = [0u16; 2048];
+    let mut output = PcmOutput::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut buffer);
+
+    let mut button = ExtiButton::new(p.PA0, p.EXTI0);
+    let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+
+    for wav in sounds.iter().cycle() {
+        leds.ld6_blue.set_high();
+        if let Err(e) = output.play_wav(wav).await {
+            warn!("Playback error: {}", e);
+        }
+        leds.ld6_blue.set_low();
+
+        info!("Press the user button to play the next sound");
+        button.wait_for_press().await;
+    }
+}
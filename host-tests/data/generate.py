@@ -0,0 +1,187 @@
+#!/usr/bin/env python3
+"""Write the WAV files used by the host tests.
+
+The PCM files come from Python's `wave` module, the other layouts are written
+chunk by chunk. The sample values follow simple formulas that the tests
+recompute (see `tests/wav.rs`).
+
+The IMA ADPCM files are encoded and decoded with `audioop`, which wraps the
+Intel/DVI reference codec also used by ffmpeg's and sox's `adpcm_ima_wav`
+decoders. Blocks are laid out the way ffmpeg writes them: the header holds the
+block's first sample and the step index carried over from the previous block.
+Each `ima_*.wav` comes with the reference decoder's output as `ima_*_ref.wav`.
+The same check works with files from ffmpeg, e.g.
+`ffmpeg -i in.wav -c:a adpcm_ima_wav ima.wav && ffmpeg -i ima.wav ima_ref.wav`.
+
+Needs Python 3.12 or older (`audioop` was removed in 3.13).
+Run from this directory: `python3 generate.py`
+"""
+
+import audioop
+import math
+import struct
+import wave
+
+RATE = 16_000
+
+
+def ramp(i):
+    """Left channel of the 16-bit files"""
+    return (i * 300) % 65_536 - 32_768
+
+
+def chunk(tag, body):
+    """A RIFF chunk, padded to an even size"""
+    pad = b"\0" if len(body) % 2 else b""
+    return tag + struct.pack("<I", len(body)) + body + pad
+
+
+def riff(*chunks, size=None):
+    body = b"WAVE" + b"".join(chunks)
+    return b"RIFF" + struct.pack("<I", len(body) if size is None else size) + body
+
+
+def pcm16(frames, channels):
+    samples = []
+    for i in range(frames):
+        samples.append(ramp(i))
+        if channels == 2:
+            samples.append(-ramp(i) - 1)
+    return struct.pack(f"<{len(samples)}h", *samples)
+
+
+def write_wave(name, channels, width, data):
+    with wave.open(name, "wb") as w:
+        w.setnchannels(channels)
+        w.setsampwidth(width)
+        w.setframerate(RATE)
+        w.writeframes(data)
+
+
+def main():
+    # 16-bit stereo and mono PCM
+    write_wave("pcm16_stereo.wav", 2, 2, pcm16(100, 2))
+    write_wave("pcm16_mono.wav", 1, 2, pcm16(100, 1))
+
+    # 8-bit mono PCM: unsigned samples 0, 1, ..., 255
+    write_wave("pcm8_mono.wav", 1, 1, bytes(range(256)))
+
+    # WAVE_FORMAT_EXTENSIBLE with the KSDATAFORMAT_SUBTYPE_PCM GUID, 16-bit stereo
+    subformat = bytes.fromhex("0100000000001000800000aa00389b71")
+    fmt = struct.pack("<HHIIHHHHI", 0xFFFE, 2, RATE, RATE * 4, 4, 16, 22, 16, 0x3) + subformat
+    with open("pcm16_stereo_extensible.wav", "wb") as f:
+        f.write(riff(chunk(b"fmt ", fmt), chunk(b"data", pcm16(100, 2))))
+
+    # Odd-sized chunks: a LIST chunk before `fmt `, an unknown chunk between
+    # `fmt ` and `data`, and 101 bytes of 8-bit mono data, each with a pad byte
+    fmt = struct.pack("<HHIIHH", 1, 1, RATE, RATE, 1, 8)
+    # 15 bytes: the name is the last thing in the list, so it is not padded
+    info = b"INFO" + b"INAM" + struct.pack("<I", 3) + b"odd"
+    with open("pcm8_odd_chunks.wav", "wb") as f:
+        f.write(
+            riff(
+                chunk(b"LIST", info),
+                chunk(b"fmt ", fmt),
+                chunk(b"junk", b"abc"),
+                chunk(b"data", bytes(range(101))),
+            )
+        )
+
+    # A stream cut off in the middle of a frame: the RIFF and `data` sizes claim
+    # 1000 frames, only 101 bytes (50.5 frames of 16-bit mono) follow
+    fmt = struct.pack("<HHIIHH", 1, 1, RATE, RATE * 2, 2, 16)
+    data = b"data" + struct.pack("<I", 2000) + pcm16(100, 1)[:101]
+    with open("pcm16_truncated_data.wav", "wb") as f:
+        f.write(riff(chunk(b"fmt ", fmt), data, size=2036))
+
+    write_adpcm()
+
+
+def test_signal(frames, channel):
+    """A sweep with a full-scale square burst, exercising every code and the clamping"""
+    samples = []
+    phase = 0.0
+    for i in range(frames):
+        frequency = 200 + 3_000 * i / frames + 500 * channel
+        phase += 2 * math.pi * frequency / RATE
+        x = 20_000 * math.sin(phase)
+        if frames // 2 <= i < frames // 2 + 64:
+            x = 32_767 if (i // 16) % 2 else -32_768
+        samples.append(max(-32_768, min(32_767, round(x))))
+    return samples
+
+
+def nibbles(codes):
+    """4-bit codes from audioop's packing (first code in the high nibble)"""
+    return [n for byte in codes for n in (byte >> 4, byte & 0x0F)]
+
+
+def pack(codes):
+    """WAV packing of 4-bit codes (first code in the low nibble)"""
+    return bytes(codes[i] | codes[i + 1] << 4 for i in range(0, len(codes), 2))
+
+
+def ima_encode(channels, block_align, frames):
+    """Encode whole blocks, returning the data and the reference decoder output"""
+    samples_per_block = 1 + (block_align - 4 * channels) // (4 * channels) * 8
+    indices = [0] * channels
+    data = bytearray()
+    decoded = [[] for _ in range(channels)]
+
+    for start in range(0, len(frames[0]), samples_per_block):
+        headers, codes = b"", []
+        for ch in range(channels):
+            block = frames[ch][start:start + samples_per_block]
+            block += [block[-1]] * (samples_per_block - len(block))
+            first = block[0]
+            headers += struct.pack("<hBB", first, indices[ch], 0)
+
+            rest = struct.pack(f"<{len(block) - 1}h", *block[1:])
+            encoded, (_, indices[ch]) = audioop.lin2adpcm(rest, 2, (first, headers[4 * ch + 2]))
+            codes.append(nibbles(encoded))
+
+            reference, _ = audioop.adpcm2lin(encoded, 2, (first, headers[4 * ch + 2]))
+            decoded[ch] += [first, *struct.unpack(f"<{len(block) - 1}h", reference)]
+
+        data += headers
+        # Groups of 8 codes (4 bytes) per channel, interleaved
+        for group in range(0, samples_per_block - 1, 8):
+            for ch in range(channels):
+                data += pack(codes[ch][group:group + 8])
+
+    return bytes(data), decoded
+
+
+def write_ima(name, channels, block_align, frames, data_len=None):
+    data, decoded = ima_encode(channels, block_align, frames)
+    samples_per_block = 1 + (block_align - 4 * channels) // (4 * channels) * 8
+    if data_len is not None:
+        # Cut the last block short, as a truncated stream would
+        data = data[:data_len]
+        full, partial = divmod(data_len, block_align)
+        count = full * samples_per_block
+        if partial >= 4 * channels:
+            count += 1 + (partial - 4 * channels) // (4 * channels) * 8
+        decoded = [samples[:count] for samples in decoded]
+
+    fmt = struct.pack(
+        "<HHIIHHHH", 0x11, channels, RATE, RATE * block_align // samples_per_block, block_align, 4, 2,
+        samples_per_block,
+    )
+    fact = struct.pack("<I", len(decoded[0]))
+    with open(f"{name}.wav", "wb") as f:
+        f.write(riff(chunk(b"fmt ", fmt), chunk(b"fact", fact), chunk(b"data", data)))
+
+    interleaved = [s for frame in zip(*decoded) for s in frame]
+    write_wave(f"{name}_ref.wav", channels, 2, struct.pack(f"<{len(interleaved)}h", *interleaved))
+
+
+def write_adpcm():
+    # Mono, 256-byte blocks of 505 samples, the last one cut short
+    write_ima("ima_mono", 1, 256, [test_signal(1_700, 0)], data_len=3 * 256 + 4 + 4 * 37 + 3)
+    # Stereo, 512-byte blocks of 505 samples
+    write_ima("ima_stereo", 2, 512, [test_signal(2_020, 0), test_signal(2_020, 1)])
+
+
+if __name__ == "__main__":
+    main()
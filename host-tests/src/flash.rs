@@ -0,0 +1,159 @@
+//! In-memory NOR flash model
+//!
+//! Behaves like the STM32F411's flash as far as [`Storage`](crate::storage::Storage)
+//! can tell: erasing sets whole sectors to `0xFF`, programming can only clear
+//! bits, and writes are made of 4-byte words.
+//!
+//! Power loss is simulated with a write budget: once the given number of bytes
+//! has been programmed, the write in progress stops part-way and every later
+//! write or erase fails until [`RamFlash::power_on`].
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+/// Flash errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum RamFlashError {
+    /// Address not aligned to the read, write or erase size
+    NotAligned,
+    /// Range outside the flash
+    OutOfBounds,
+    /// The write budget ran out
+    PowerLoss,
+}
+
+impl NorFlashError for RamFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::NotAligned => NorFlashErrorKind::NotAligned,
+            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Self::PowerLoss => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// Size of the model's sectors, small enough to fill quickly
+pub const SECTOR_SIZE: usize = 256;
+
+/// RAM-backed flash with uniform [`SECTOR_SIZE`] sectors
+#[derive(Debug, Clone)]
+pub struct RamFlash {
+    data: Vec<u8>,
+    /// Bytes left to program before the power fails, `None` for no limit
+    budget: Option<usize>,
+    erases: Vec<u32>,
+    reads: usize,
+}
+
+impl RamFlash {
+    /// Create an erased flash of `sectors` sectors
+    pub fn new(sectors: usize) -> Self {
+        Self {
+            data: vec![0xFF; sectors * SECTOR_SIZE],
+            budget: None,
+            erases: vec![0; sectors],
+            reads: 0,
+        }
+    }
+
+    /// Fail after `bytes` more bytes have been programmed
+    pub fn power_off_after(&mut self, bytes: usize) {
+        self.budget = Some(bytes);
+    }
+
+    /// Remove the write budget
+    pub fn power_on(&mut self) {
+        self.budget = None;
+    }
+
+    /// Whether the write budget ran out
+    pub fn is_powered_off(&self) -> bool {
+        self.budget == Some(0)
+    }
+
+    /// Number of times each sector was erased
+    pub fn erase_counts(&self) -> &[u32] {
+        &self.erases
+    }
+
+    /// Number of reads so far, to check how much of the flash an operation scans
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+
+    /// Raw flash contents
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Raw flash contents, e.g. to corrupt a record
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Check alignment and bounds of `len` bytes at `offset`
+    fn range(&self, offset: u32, len: usize, align: usize) -> Result<core::ops::Range<usize>, RamFlashError> {
+        let start = offset as usize;
+        if !start.is_multiple_of(align) || !len.is_multiple_of(align) {
+            return Err(RamFlashError::NotAligned);
+        }
+        if start + len > self.data.len() {
+            return Err(RamFlashError::OutOfBounds);
+        }
+        Ok(start..start + len)
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = RamFlashError;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), RamFlashError> {
+        let range = self.range(offset, bytes.len(), Self::READ_SIZE)?;
+        bytes.copy_from_slice(&self.data[range]);
+        self.reads += 1;
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), RamFlashError> {
+        let range = self.range(from, to.saturating_sub(from) as usize, Self::ERASE_SIZE)?;
+        if self.is_powered_off() {
+            return Err(RamFlashError::PowerLoss);
+        }
+        for sector in range.clone().step_by(SECTOR_SIZE) {
+            self.erases[sector / SECTOR_SIZE] += 1;
+        }
+        self.data[range].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), RamFlashError> {
+        let range = self.range(offset, bytes.len(), Self::WRITE_SIZE)?;
+        let len = match self.budget {
+            Some(budget) => bytes.len().min(budget),
+            None => bytes.len(),
+        };
+        // Programming only clears bits
+        for (cell, byte) in self.data[range].iter_mut().zip(&bytes[..len]) {
+            *cell &= byte;
+        }
+        if let Some(budget) = &mut self.budget {
+            *budget -= len;
+            if len < bytes.len() {
+                return Err(RamFlashError::PowerLoss);
+            }
+        }
+        Ok(())
+    }
+}
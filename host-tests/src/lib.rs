@@ -0,0 +1,49 @@
+//! Host tests for the hardware-independent modules
+//!
+//! The board support package always builds for the STM32F411, so its tests can't
+//! run there. This crate compiles the modules that don't touch the hardware for
+//! the host instead, straight from `../src`, and runs the tests in `tests/`
+//! against them:
+//!
+//! ```bash
+//! cargo test-host                # from the repository root
+//! cd host-tests && cargo test    # or from here
+//! ```
+//!
+//! Sample files used by the tests are in `data/`. [`flash::RamFlash`] stands in
+//! for the MCU's flash and [`mock::MockSensor`] for the motion sensors.
+
+#[path = "../../src/adpcm.rs"]
+pub mod adpcm;
+#[path = "../../src/dsp.rs"]
+pub mod dsp;
+pub mod flash;
+#[path = "../../src/gesture.rs"]
+pub mod gesture;
+pub mod mock;
+#[path = "../../src/pdm.rs"]
+pub mod pdm;
+#[path = "../../src/resample.rs"]
+pub mod resample;
+#[path = "../../src/sensor.rs"]
+pub mod sensor;
+#[path = "../../src/storage.rs"]
+pub mod storage;
+#[path = "../../src/synth.rs"]
+pub mod synth;
+#[path = "../../src/wav.rs"]
+pub mod wav;
+
+/// Discards the modules' log messages
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {}
+    unsafe fn flush() {}
+    unsafe fn release() {}
+    unsafe fn write(_bytes: &[u8]) {}
+}
+
+// Provided by the linker script on the MCU
+defmt::timestamp!("");
@@ -0,0 +1,125 @@
+//! Mock motion sensor
+//!
+//! [`MockSensor`] implements all three sensor traits by replaying queued
+//! readings, so code written against the traits can be tested without a board.
+
+use std::collections::VecDeque;
+
+use crate::sensor::{Accelerometer, Gyroscope, Magnetometer, Vector3};
+
+/// Readings queued for one axis set
+#[derive(Debug, Default, Clone)]
+struct Channel {
+    readings: VecDeque<Vector3>,
+    /// Last reading, repeated once the queue is empty
+    last: Vector3,
+    reads: usize,
+}
+
+impl Channel {
+    fn ready(&self) -> bool {
+        !self.readings.is_empty()
+    }
+
+    fn read(&mut self) -> Vector3 {
+        if let Some(reading) = self.readings.pop_front() {
+            self.last = reading;
+        }
+        self.reads += 1;
+        self.last
+    }
+}
+
+/// A gyroscope, accelerometer and magnetometer replaying queued readings
+///
+/// Data is ready while readings are queued. Reading with an empty queue returns
+/// the last reading again, as a real sensor's output registers would.
+/// Configurations are recorded as a free-form value of type `C`.
+#[derive(Debug, Default, Clone)]
+pub struct MockSensor<C = u8> {
+    gyro: Channel,
+    accel: Channel,
+    mag: Channel,
+    /// Configurations applied, in order
+    pub configs: Vec<C>,
+}
+
+impl<C> MockSensor<C> {
+    /// Create a sensor with nothing queued
+    pub fn new() -> Self {
+        Self {
+            gyro: Channel::default(),
+            accel: Channel::default(),
+            mag: Channel::default(),
+            configs: Vec::new(),
+        }
+    }
+
+    /// Queue angular rate readings in dps
+    pub fn queue_angular_rate(&mut self, readings: impl IntoIterator<Item = Vector3>) {
+        self.gyro.readings.extend(readings);
+    }
+
+    /// Queue acceleration readings in g
+    pub fn queue_acceleration(&mut self, readings: impl IntoIterator<Item = Vector3>) {
+        self.accel.readings.extend(readings);
+    }
+
+    /// Queue magnetic field readings in gauss
+    pub fn queue_magnetic_field(&mut self, readings: impl IntoIterator<Item = Vector3>) {
+        self.mag.readings.extend(readings);
+    }
+
+    /// Number of angular rate, acceleration and magnetic field reads so far
+    pub fn reads(&self) -> [usize; 3] {
+        [self.gyro.reads, self.accel.reads, self.mag.reads]
+    }
+}
+
+impl<C> Gyroscope for MockSensor<C> {
+    type GyroConfig = C;
+
+    fn configure_gyro(&mut self, config: C) {
+        self.configs.push(config);
+    }
+
+    fn gyro_data_ready(&mut self) -> bool {
+        self.gyro.ready()
+    }
+
+    fn read_angular_rate(&mut self) -> Vector3 {
+        self.gyro.read()
+    }
+}
+
+impl<C> Accelerometer for MockSensor<C> {
+    type AccelConfig = C;
+
+    fn configure_accel(&mut self, config: C) {
+        self.configs.push(config);
+    }
+
+    fn accel_data_ready(&mut self) -> bool {
+        self.accel.ready()
+    }
+
+    fn read_acceleration(&mut self) -> Vector3 {
+        self.accel.read()
+    }
+}
+
+impl<C> Magnetometer for MockSensor<C> {
+    type MagConfig = C;
+
+    fn configure_mag(&mut self, config: C) {
+        self.configs.push(config);
+    }
+
+    fn mag_data_ready(&mut self) -> bool {
+        self.mag.ready()
+    }
+
+    fn read_magnetic_field(&mut self) -> Vector3 {
+        self.mag.read()
+    }
+}
@@ -0,0 +1,146 @@
+//! IMA ADPCM tests against the reference decoder's output (see `data/generate.py`)
+
+use host_tests::adpcm::{samples_per_block, BlockDecoder, ImaState};
+use host_tests::wav::{Wav, WavEncoding};
+
+/// Decode `file` and compare every frame with `reference`
+fn assert_matches_reference(file: &[u8], reference: &[u8]) {
+    let wav = Wav::parse(file).unwrap();
+    assert_eq!(wav.format().encoding, WavEncoding::ImaAdpcm);
+    let reference = Wav::parse(reference).unwrap();
+    assert_eq!(reference.format().channels, wav.format().channels);
+
+    let frames: Vec<_> = wav.frames().collect();
+    let expected: Vec<_> = reference.frames().collect();
+    assert_eq!(frames.len(), expected.len());
+    assert_eq!(wav.frame_count(), expected.len());
+    for (i, (frame, expected)) in frames.iter().zip(&expected).enumerate() {
+        assert_eq!(frame, expected, "frame {i}");
+    }
+}
+
+#[test]
+fn mono_matches_reference_decoder() {
+    // 3 full blocks and a last block cut short in the middle of a group
+    assert_matches_reference(
+        include_bytes!("../data/ima_mono.wav"),
+        include_bytes!("../data/ima_mono_ref.wav"),
+    );
+}
+
+#[test]
+fn stereo_matches_reference_decoder() {
+    assert_matches_reference(
+        include_bytes!("../data/ima_stereo.wav"),
+        include_bytes!("../data/ima_stereo_ref.wav"),
+    );
+}
+
+/// A 500 Hz sine at 16 kHz, amplitude 12000
+fn sine(len: usize) -> Vec<i16> {
+    (0..len)
+        .map(|i| (12_000.0 * (2.0 * core::f64::consts::PI * 500.0 * i as f64 / 16_000.0).sin()) as i16)
+        .collect()
+}
+
+#[test]
+fn encoder_and_decoder_stay_in_step() {
+    let mut encoder = ImaState::new(0, 0);
+    let mut decoder = ImaState::new(0, 0);
+    for sample in sine(2_000) {
+        let code = encoder.encode(sample);
+        assert!(code < 16);
+        assert_eq!(decoder.decode(code), encoder.predictor);
+        assert_eq!(decoder, encoder);
+    }
+}
+
+/// Smallest step size (step index 0), for which step / 8 rounds to 0
+const STEP_MIN: i32 = 7;
+
+#[test]
+fn encoding_error_is_bounded_by_the_step() {
+    let signal = sine(4_000);
+    let mut state = ImaState::new(0, 0);
+    let mut in_range = 0;
+    let mut error_energy = 0.0;
+    let mut signal_energy = 0.0;
+
+    for &sample in &signal {
+        // Decoding code 0 adds step / 8, which recovers the step for this sample
+        let eighth = ImaState::new(0, state.step_index).decode(0) as i32;
+        let step = STEP_MIN.max(eighth * 8);
+        let diff = (sample as i32 - state.predictor as i32).abs();
+
+        state.encode(sample);
+        let error = (state.predictor as i32 - sample as i32).abs();
+
+        // Unless the difference overflows the largest code (7/4 of the step),
+        // the quantization error is at most step / 8, plus rounding
+        if diff < step + step / 2 + step / 4 {
+            in_range += 1;
+            assert!(error <= eighth + 1, "error {error} with step {step}");
+        }
+        error_energy += (error as f64).powi(2);
+        signal_energy += (sample as f64).powi(2);
+    }
+
+    // The step adapts to the signal, so it rarely overflows
+    assert!(in_range > signal.len() * 95 / 100, "{in_range} of {} samples in range", signal.len());
+    let snr = 10.0 * (signal_energy / error_energy).log10();
+    assert!(snr > 25.0, "SNR {snr:.1} dB");
+}
+
+#[test]
+fn remaining_counts_a_short_last_block() {
+    for channels in [1, 2] {
+        let block_align = 256 * channels;
+        let full = samples_per_block(block_align, channels);
+        // A full block, then a header, 5 groups and 3 stray bytes
+        let len = block_align + (4 + 5 * 4) * channels + 3;
+        let data: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+
+        let mut decoder = BlockDecoder::new(&data, channels, block_align).unwrap();
+        let expected = full + 1 + 5 * 8;
+        assert_eq!(decoder.remaining(), expected, "{channels} channels");
+        assert_eq!(decoder.len(), expected);
+
+        let mut produced = 0;
+        while decoder.next().is_some() {
+            produced += 1;
+            assert_eq!(decoder.remaining(), expected - produced, "after {produced} frames");
+        }
+        assert_eq!(produced, expected, "{channels} channels");
+        assert_eq!(decoder.remaining(), 0);
+    }
+}
+
+#[test]
+fn remaining_counts_a_header_only_block() {
+    // A block with a header but no group still yields its first sample
+    let data = [0x34, 0x12, 0, 0, 1, 2];
+    let decoder = BlockDecoder::new(&data, 1, 256).unwrap();
+    assert_eq!(decoder.remaining(), 1);
+    assert_eq!(decoder.collect::<Vec<_>>(), [[0x1234; 2]]);
+
+    // Less than a header decodes nothing
+    let decoder = BlockDecoder::new(&data[..3], 1, 256).unwrap();
+    assert_eq!(decoder.remaining(), 0);
+    assert_eq!(decoder.count(), 0);
+}
+
+#[test]
+fn decoder_rejects_invalid_layouts() {
+    let data = [0u8; 64];
+    for channels in [0, 3, 8] {
+        assert!(BlockDecoder::new(&data, channels, 256).is_none(), "{channels} channels");
+    }
+    // Blocks must hold at least the 4-byte header of each channel
+    assert!(BlockDecoder::new(&data, 1, 0).is_none());
+    assert!(BlockDecoder::new(&data, 1, 3).is_none());
+    assert!(BlockDecoder::new(&data, 2, 7).is_none());
+    assert_eq!(BlockDecoder::new(&data, 1, 4).unwrap().remaining(), 16);
+    assert_eq!(BlockDecoder::new(&data, 2, 8).unwrap().remaining(), 8);
+
+    assert_eq!(samples_per_block(256, 0), 0);
+}
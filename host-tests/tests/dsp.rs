@@ -0,0 +1,202 @@
+//! Audio processing block tests: biquad responses, noise gate timing and chains
+
+use core::f64::consts::PI;
+
+use embassy_time::Duration;
+use host_tests::dsp::{Biquad, Gain, GateSettings, NoiseGate, Processor};
+
+const RATE: u32 = 16_000;
+const NYQUIST: f32 = RATE as f32 / 2.0;
+
+/// Marks a frequency the filter should block
+const BLOCKED: f32 = f32::NEG_INFINITY;
+
+/// Gain of `filter` at `frequency` Hz in dB, in the steady state
+///
+/// Runs two seconds of a cosine (a constant at 0 Hz, ±1 at Nyquist) through the
+/// filter and compares the RMS of the second second.
+fn response_db(mut filter: Biquad, frequency: f32) -> f32 {
+    let mut samples: Vec<f32> = (0..2 * RATE)
+        .map(|n| (0.5 * (2.0 * PI * frequency as f64 * n as f64 / RATE as f64).cos()) as f32)
+        .collect();
+    let rms = |samples: &[f32]| (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    let input = rms(&samples[RATE as usize..]);
+    filter.process(&mut samples);
+    20.0 * (rms(&samples[RATE as usize..]) / input).log10()
+}
+
+#[test]
+fn biquad_responses() {
+    // Filter, corner or center frequency, and gain in dB at 0 Hz, there and at Nyquist
+    let filters = [
+        ("low-pass", Biquad::low_pass(RATE, 1_000.0, 0.707), 1_000.0, [0.0, -3.01, BLOCKED]),
+        ("high-pass", Biquad::high_pass(RATE, 1_000.0, 0.707), 1_000.0, [BLOCKED, -3.01, 0.0]),
+        ("band-pass", Biquad::band_pass(RATE, 1_000.0, 2.0), 1_000.0, [BLOCKED, 0.0, BLOCKED]),
+        ("notch", Biquad::notch(RATE, 1_000.0, 2.0), 1_000.0, [0.0, BLOCKED, 0.0]),
+        ("peaking", Biquad::peaking(RATE, 2_000.0, 1.0, 6.0), 2_000.0, [0.0, 6.0, 0.0]),
+        ("low shelf", Biquad::low_shelf(RATE, 500.0, 6.0), 500.0, [6.0, 3.0, 0.0]),
+        ("high shelf", Biquad::high_shelf(RATE, 2_000.0, -6.0), 2_000.0, [0.0, -3.0, -6.0]),
+    ];
+
+    for (name, filter, corner, expected) in filters {
+        for (frequency, expected) in [0.0, corner, NYQUIST].into_iter().zip(expected) {
+            let db = response_db(filter, frequency);
+            if expected == BLOCKED {
+                assert!(db < -60.0, "{name} at {frequency} Hz: {db:.2} dB, expected blocked");
+            } else {
+                assert!((db - expected).abs() < 0.05, "{name} at {frequency} Hz: {db:.2} dB, expected {expected}");
+            }
+        }
+    }
+}
+
+#[test]
+fn biquad_reset_clears_the_state() {
+    let mut filter = Biquad::low_pass(RATE, 1_000.0, 0.707);
+    let fresh = filter;
+    filter.process(&mut [1.0; 64]);
+    assert_ne!(filter, fresh);
+    filter.reset();
+    assert_eq!(filter, fresh);
+}
+
+#[test]
+fn gain() {
+    let gain = Gain::from_db(-20.0);
+    assert!((gain.factor() - 0.1).abs() < 1e-6);
+    assert!((gain.db() + 20.0).abs() < 1e-4);
+    assert_eq!(Gain::UNITY.factor(), 1.0);
+
+    let mut samples = [0.5, -1.0];
+    Gain::from_db(20.0).process(&mut samples);
+    assert!((samples[0] - 5.0).abs() < 1e-5 && (samples[1] + 10.0).abs() < 1e-5);
+}
+
+/// -40 dB threshold, 1 ms attack, 100 ms hold, 150 ms release
+fn gate() -> NoiseGate {
+    NoiseGate::new(
+        RATE,
+        GateSettings {
+            threshold_db: -40.0,
+            attack: Duration::from_millis(1),
+            hold: Duration::from_millis(100),
+            release: Duration::from_millis(150),
+        },
+    )
+}
+
+/// Samples in `ms` milliseconds
+fn ms(ms: usize) -> usize {
+    ms * RATE as usize / 1_000
+}
+
+#[test]
+fn gate_stays_closed_below_the_threshold() {
+    let mut gate = gate();
+    // -46 dB
+    let mut samples = vec![0.005; ms(500)];
+    gate.process(&mut samples);
+    assert!(samples.iter().all(|&s| s == 0.0));
+    assert!(!gate.is_open());
+}
+
+#[test]
+fn gate_timing() {
+    const LOUD: f32 = 0.5;
+    const QUIET: f32 = 0.001;
+
+    // 50 ms above the threshold, then 450 ms at -60 dB
+    let mut gate = gate();
+    let mut samples = vec![LOUD; ms(50)];
+    samples.resize(ms(500), QUIET);
+    let input = samples.clone();
+    gate.process(&mut samples);
+    let gains: Vec<f32> = samples.iter().zip(&input).map(|(out, x)| out / x).collect();
+
+    // Attack: opens over 1 ms
+    assert!((gains[0] - 1.0 / ms(1) as f32).abs() < 1e-6);
+    assert!(gains[ms(1) - 2] < 1.0);
+    assert!(gains[ms(1) - 1..ms(50)].iter().all(|&g| g == 1.0));
+
+    // The level detector falls from -6 dB to the -40 dB threshold with a 10 ms time
+    // constant, then the gate holds for 100 ms before it starts to close (give or
+    // take a sample)
+    let below = ms(50) + (ms(10) as f32 * (LOUD / 0.01).ln()) as usize;
+    let release = gains[ms(50)..].iter().position(|&g| g < 1.0).unwrap() + ms(50);
+    assert!(release.abs_diff(below + ms(100)) <= 2, "release starts at {release}, expected {}", below + ms(100));
+
+    // Release: closes over 150 ms
+    let closed = gains[release..].iter().position(|&g| g == 0.0).unwrap() + release;
+    assert!(closed.abs_diff(release + ms(150)) <= 1, "closed at {closed}, expected {}", release + ms(150));
+    assert!(gains[release..closed].windows(2).all(|w| w[1] < w[0]));
+    assert!(gains[closed..].iter().all(|&g| g == 0.0));
+    assert!(!gate.is_open());
+}
+
+#[test]
+fn gate_reset_closes_it() {
+    let mut gate = gate();
+    gate.process(&mut [0.5; 64]);
+    assert!(gate.is_open());
+    gate.reset();
+    assert!(!gate.is_open());
+    let mut quiet = [0.005; 16];
+    gate.process(&mut quiet);
+    assert_eq!(quiet, [0.0; 16]);
+}
+
+/// `x · scale + offset`, counting resets; order matters when chained
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Affine {
+    scale: f32,
+    offset: f32,
+    resets: u32,
+}
+
+fn affine(scale: f32, offset: f32) -> Affine {
+    Affine { scale, offset, resets: 0 }
+}
+
+impl Processor for Affine {
+    fn process(&mut self, samples: &mut [f32]) {
+        for s in samples {
+            *s = *s * self.scale + self.offset;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.resets += 1;
+    }
+}
+
+fn run(processor: &mut impl Processor, x: f32) -> f32 {
+    let mut samples = [x];
+    processor.process(&mut samples);
+    samples[0]
+}
+
+#[test]
+fn chains_run_in_order() {
+    // ((1 · 2 + 1) · 3) - 1
+    assert_eq!(run(&mut (affine(2.0, 1.0), affine(3.0, 0.0), affine(1.0, -1.0)), 1.0), 8.0);
+    assert_eq!(run(&mut (affine(1.0, -1.0), affine(3.0, 0.0), affine(2.0, 1.0)), 1.0), 1.0);
+    assert_eq!(run(&mut [affine(2.0, 1.0), affine(3.0, 0.0), affine(1.0, -1.0)], 1.0), 8.0);
+    assert_eq!(run(&mut [affine(1.0, -1.0), affine(3.0, 0.0), affine(2.0, 1.0)], 1.0), 1.0);
+
+    // Nested chains, references and bypassed blocks
+    let mut inner = (affine(3.0, 0.0), None::<Affine>);
+    let mut chain = (Some(affine(2.0, 1.0)), &mut inner, [affine(1.0, -1.0)]);
+    assert_eq!(run(&mut chain, 1.0), 8.0);
+    chain.0 = None;
+    assert_eq!(run(&mut chain, 1.0), 2.0);
+}
+
+#[test]
+fn chains_reset_every_block() {
+    let mut chain = (affine(1.0, 0.0), [affine(1.0, 0.0); 2], Some(affine(1.0, 0.0)), None::<Affine>);
+    chain.reset();
+    chain.reset();
+    assert_eq!(chain.0.resets, 2);
+    assert!(chain.1.iter().all(|a| a.resets == 2));
+    assert_eq!(chain.2.map(|a| a.resets), Some(2));
+}
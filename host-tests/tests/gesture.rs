@@ -0,0 +1,213 @@
+//! Gesture decoder tests on simulated button timelines
+//!
+//! Times use the firmware's 32.768 kHz tick, so the boundaries land on the same
+//! ticks as on the board.
+
+use embassy_time::{Duration, Instant};
+use host_tests::gesture::{ButtonEvent, GestureConfig, GestureDecoder};
+
+/// One tick, the smallest step in a timeline
+const TICK: Duration = Duration::from_ticks(1);
+
+fn ms(ms: u64) -> Instant {
+    Instant::from_millis(ms)
+}
+
+/// Feed `changes` (time, pressed) to a decoder with the default timings the way
+/// the button task does, polling at every deadline until `until`
+///
+/// Returns the events with the time they were reported.
+fn run(changes: &[(Instant, bool)], until: Instant) -> Vec<(Instant, ButtonEvent)> {
+    let mut decoder = GestureDecoder::new(GestureConfig::default());
+    let mut events = Vec::new();
+
+    for &(at, pressed) in changes {
+        while let Some(deadline) = decoder.deadline().filter(|&deadline| deadline < at) {
+            events.extend(decoder.poll(deadline).map(|event| (deadline, event)));
+        }
+        events.extend(decoder.on_change(pressed, at).map(|event| (at, event)));
+    }
+    while let Some(deadline) = decoder.deadline().filter(|&deadline| deadline <= until) {
+        events.extend(decoder.poll(deadline).map(|event| (deadline, event)));
+    }
+    events
+}
+
+fn config() -> GestureConfig {
+    GestureConfig::default()
+}
+
+#[test]
+fn default_timings() {
+    let config = config();
+    assert_eq!(config.double_click, Duration::from_millis(300));
+    assert_eq!(config.long_press, Duration::from_millis(800));
+    assert_eq!(config.hold_repeat, Duration::from_millis(250));
+}
+
+#[test]
+fn click_is_reported_when_the_double_click_window_ends() {
+    let release = ms(100);
+    let window_end = release + config().double_click;
+    assert_eq!(
+        run(&[(ms(0), true), (release, false)], ms(2_000)),
+        [(window_end, ButtonEvent::Click)]
+    );
+
+    // Not a tick earlier
+    let mut decoder = GestureDecoder::new(config());
+    assert_eq!(decoder.on_change(true, ms(0)), None);
+    assert_eq!(decoder.on_change(false, release), None);
+    assert_eq!(decoder.deadline(), Some(window_end));
+    assert_eq!(decoder.poll(window_end - TICK), None);
+    assert_eq!(decoder.poll(window_end), Some(ButtonEvent::Click));
+    assert_eq!(decoder.deadline(), None);
+    assert_eq!(decoder.poll(ms(5_000)), None);
+}
+
+#[test]
+fn double_click() {
+    let release = ms(100);
+    let last_chance = release + config().double_click - TICK;
+    assert_eq!(
+        run(
+            &[(ms(0), true), (release, false), (last_chance, true), (last_chance + Duration::from_millis(50), false)],
+            ms(2_000)
+        ),
+        [(last_chance + Duration::from_millis(50), ButtonEvent::DoubleClick)]
+    );
+}
+
+#[test]
+fn second_press_after_the_window_is_another_click() {
+    let release = ms(100);
+    let window_end = release + config().double_click;
+
+    // The click is reported by the change itself, even without a poll at the deadline
+    let mut decoder = GestureDecoder::new(config());
+    decoder.on_change(true, ms(0));
+    decoder.on_change(false, release);
+    assert_eq!(decoder.on_change(true, window_end), Some(ButtonEvent::Click));
+    assert_eq!(decoder.on_change(false, window_end + Duration::from_millis(100)), None);
+    assert_eq!(decoder.poll(window_end + Duration::from_millis(400)), Some(ButtonEvent::Click));
+
+    assert_eq!(
+        run(
+            &[(ms(0), true), (release, false), (window_end, true), (window_end + Duration::from_millis(100), false)],
+            ms(2_000)
+        ),
+        [
+            (window_end, ButtonEvent::Click),
+            (window_end + Duration::from_millis(400), ButtonEvent::Click),
+        ]
+    );
+}
+
+#[test]
+fn long_press_threshold() {
+    let long_press = ms(0) + config().long_press;
+
+    // Released a tick before the threshold: a click
+    assert_eq!(
+        run(&[(ms(0), true), (long_press - TICK, false)], ms(5_000)),
+        [(long_press - TICK + config().double_click, ButtonEvent::Click)]
+    );
+
+    // Released at the threshold: the long press wins, and no click follows
+    assert_eq!(
+        run(&[(ms(0), true), (long_press, false)], ms(5_000)),
+        [(long_press, ButtonEvent::LongPress)]
+    );
+
+    let mut decoder = GestureDecoder::new(config());
+    decoder.on_change(true, ms(0));
+    assert_eq!(decoder.deadline(), Some(long_press));
+    assert_eq!(decoder.poll(long_press - TICK), None);
+    assert_eq!(decoder.poll(long_press), Some(ButtonEvent::LongPress));
+    assert_eq!(decoder.on_change(false, long_press + TICK), None);
+    assert_eq!(decoder.deadline(), None);
+}
+
+#[test]
+fn hold_repeats_until_release() {
+    let long_press = ms(0) + config().long_press;
+    let repeat = config().hold_repeat;
+
+    assert_eq!(
+        run(&[(ms(0), true), (long_press + repeat * 3 + TICK, false)], ms(5_000)),
+        [
+            (long_press, ButtonEvent::LongPress),
+            (long_press + repeat, ButtonEvent::Hold(1)),
+            (long_press + repeat * 2, ButtonEvent::Hold(2)),
+            (long_press + repeat * 3, ButtonEvent::Hold(3)),
+        ]
+    );
+
+    // Released a tick before the first repeat: only the long press
+    assert_eq!(
+        run(&[(ms(0), true), (long_press + repeat - TICK, false)], ms(5_000)),
+        [(long_press, ButtonEvent::LongPress)]
+    );
+}
+
+#[test]
+fn late_polls_catch_up_one_hold_at_a_time() {
+    let long_press = ms(0) + config().long_press;
+    let repeat = config().hold_repeat;
+    let mut decoder = GestureDecoder::new(config());
+    decoder.on_change(true, ms(0));
+
+    // Polled well after three repeats were due
+    let late = long_press + repeat * 3 + Duration::from_millis(10);
+    assert_eq!(decoder.poll(late), Some(ButtonEvent::LongPress));
+    for count in 1..=3 {
+        assert_eq!(decoder.poll(late), Some(ButtonEvent::Hold(count)));
+    }
+    assert_eq!(decoder.poll(late), None);
+    // The schedule is kept, not shifted by the late poll
+    assert_eq!(decoder.deadline(), Some(long_press + repeat * 4));
+}
+
+#[test]
+fn second_press_turning_into_a_long_press() {
+    let second = ms(300);
+    assert_eq!(
+        run(
+            &[(ms(0), true), (ms(100), false), (second, true), (second + config().long_press, false)],
+            ms(5_000)
+        ),
+        [(second + config().long_press, ButtonEvent::LongPress)]
+    );
+}
+
+#[test]
+fn repeated_changes_are_ignored() {
+    let mut decoder = GestureDecoder::new(config());
+    assert_eq!(decoder.on_change(false, ms(0)), None);
+    assert_eq!(decoder.deadline(), None);
+
+    decoder.on_change(true, ms(10));
+    // A second press report does not restart the long-press timer
+    assert_eq!(decoder.on_change(true, ms(500)), None);
+    assert_eq!(decoder.deadline(), Some(ms(10) + config().long_press));
+}
+
+#[test]
+fn custom_timings() {
+    let config = GestureConfig {
+        double_click: Duration::from_millis(150),
+        long_press: Duration::from_millis(2_000),
+        hold_repeat: Duration::from_millis(1_000),
+    };
+    let mut decoder = GestureDecoder::new(config);
+    decoder.on_change(true, ms(0));
+    assert_eq!(decoder.poll(ms(1_999)), None);
+    assert_eq!(decoder.poll(ms(2_000)), Some(ButtonEvent::LongPress));
+    assert_eq!(decoder.deadline(), Some(ms(3_000)));
+    decoder.on_change(false, ms(2_500));
+
+    decoder.on_change(true, ms(3_000));
+    decoder.on_change(false, ms(3_100));
+    assert_eq!(decoder.deadline(), Some(ms(3_250)));
+    assert_eq!(decoder.poll(ms(3_250)), Some(ButtonEvent::Click));
+}
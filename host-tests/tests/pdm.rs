@@ -0,0 +1,100 @@
+//! PDM decimator tests: CIC gain and shift, DC removal and a modulated tone
+
+use core::f64::consts::PI;
+
+use host_tests::pdm::{PdmDecimator, DECIMATION};
+
+/// PCM rate from a 1.024 MHz PDM clock
+const RATE: usize = 16_000;
+
+/// PDM words per PCM sample
+const WORDS: usize = DECIMATION as usize / 16;
+
+/// Decimate `samples` PCM samples worth of `words`
+fn decimate(words: &[u16], samples: usize) -> Vec<i16> {
+    let mut decimator = PdmDecimator::new();
+    let mut pcm = vec![0; samples];
+    assert_eq!(decimator.process(words, &mut pcm), samples);
+    pcm
+}
+
+/// Decimate one second of the same PDM word
+fn constant(word: u16) -> Vec<i16> {
+    decimate(&vec![word; RATE * WORDS], RATE)
+}
+
+#[test]
+fn bit_density_sets_the_level() {
+    // All ones, all zeros and 75 % / 25 % ones
+    for (word, level) in [(0xFFFF, 1.0), (0x0000, -1.0), (0xEEEE, 0.5), (0x1111, -0.5)] {
+        let pcm = constant(word);
+
+        // The CIC filter settles after 4 samples at a gain of 64^4, which the shift
+        // brings to 16-bit full scale. The DC blocker has only just started to act.
+        let expected = level * 32_768.0;
+        let peak = pcm[..8].iter().map(|&s| s as f64).max_by(|a, b| a.abs().total_cmp(&b.abs())).unwrap();
+        assert!((peak / expected - 1.0).abs() < 0.01, "{word:#06x}: peak {peak}, expected {expected}");
+        // No wrap-around on the way there
+        assert!(pcm.iter().all(|&s| s == 0 || (s > 0) == (level > 0.0)), "{word:#06x}: sign flipped");
+
+        // Then the DC blocker removes the constant offset
+        assert!(pcm[RATE / 2..].iter().all(|&s| s.abs() <= 1), "{word:#06x}: offset left");
+    }
+}
+
+#[test]
+fn half_density_is_silence() {
+    for word in [0xAAAA, 0x5555] {
+        let pcm = constant(word);
+        // Only the start-up of the CIC filter shows
+        assert!(pcm[..4].iter().all(|&s| s.abs() < 256), "{word:#06x}: {:?}", &pcm[..4]);
+        assert!(pcm[4..].iter().all(|&s| s.abs() <= 1), "{word:#06x}: not silent");
+    }
+}
+
+/// PDM words of a sine at `frequency` Hz and `amplitude` (full scale 1.0), from a
+/// first-order sigma-delta modulator
+fn pdm_sine(frequency: f64, amplitude: f64, samples: usize) -> Vec<u16> {
+    let clock = (RATE * DECIMATION as usize) as f64;
+    let mut error = 0.0;
+    (0..samples * WORDS)
+        .map(|w| {
+            let mut word = 0u16;
+            for b in 0..16 {
+                let x = amplitude * (2.0 * PI * frequency * (w * 16 + b) as f64 / clock).sin();
+                let bit = error + x >= 0.0;
+                error += x - if bit { 1.0 } else { -1.0 };
+                word = word << 1 | bit as u16;
+            }
+            word
+        })
+        .collect()
+}
+
+#[test]
+fn decodes_a_modulated_sine() {
+    let pcm = decimate(&pdm_sine(1_000.0, 0.5, RATE), RATE);
+    // Skip the DC blocker's start
+    let pcm = &pcm[RATE / 10..];
+
+    let energy: f64 = pcm.iter().map(|&s| (s as f64).powi(2)).sum();
+    let amplitude = (energy / pcm.len() as f64).sqrt() * 2f64.sqrt();
+    // Half scale, less the CIC filter's 0.22 dB droop at 1 kHz
+    let expected = 16_384.0 * 0.975;
+    assert!((amplitude / expected - 1.0).abs() < 0.02, "amplitude {amplitude:.0}, expected {expected:.0}");
+
+    // 1 kHz: 2 zero crossings per millisecond
+    let crossings = pcm.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count();
+    let expected = 2 * pcm.len() / 16;
+    assert!(crossings.abs_diff(expected) <= 2, "{crossings} zero crossings, expected {expected}");
+}
+
+#[test]
+fn stops_when_the_output_is_full() {
+    let mut decimator = PdmDecimator::new();
+    let mut pcm = [0; 4];
+    assert_eq!(decimator.process(&[0xFFFF; 10 * WORDS], &mut pcm), 4);
+    // A partial sample is kept for the next call
+    assert_eq!(decimator.process(&[0xFFFF; WORDS - 1], &mut pcm), 0);
+    assert_eq!(decimator.process(&[0xFFFF; 1], &mut pcm[..1]), 1);
+}
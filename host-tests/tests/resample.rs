@@ -0,0 +1,231 @@
+//! Resampler and drift control tests
+
+use core::f64::consts::PI;
+
+use host_tests::resample::{DriftControl, Resampler, MAX_TRIM_PPM};
+
+/// `len` frames of a sine at `frequency` Hz sampled at `rate` Hz, amplitude 16000
+fn sine(frequency: f64, rate: u32, len: usize) -> Vec<[i16; 2]> {
+    (0..len)
+        .map(|i| {
+            let x = (16_000.0 * (2.0 * PI * frequency * i as f64 / rate as f64).sin()) as i16;
+            [x, -x]
+        })
+        .collect()
+}
+
+/// Run all of `input` through `resampler` in blocks of `block` frames
+fn run(resampler: &mut Resampler, input: &[[i16; 2]], block: usize) -> Vec<[i16; 2]> {
+    let mut output = Vec::new();
+    let mut buffer = [[0; 2]; 256];
+    let mut pending = input;
+    while !pending.is_empty() {
+        let chunk = &pending[..block.min(pending.len())];
+        let (consumed, produced) = resampler.process(chunk, &mut buffer);
+        output.extend_from_slice(&buffer[..produced]);
+        pending = &pending[consumed..];
+        if consumed == 0 && produced == 0 {
+            // Only the frame needed to interpolate past the end is left
+            break;
+        }
+    }
+    output
+}
+
+#[test]
+fn sine_37k5_to_48k() {
+    let input = sine(1_000.0, 37_500, 37_500);
+    let mut resampler = Resampler::new(37_500, 48_000);
+    assert!((resampler.ratio() - 0.781_25).abs() < 1e-6);
+    // Odd block sizes, so the position carries over between calls
+    let output = run(&mut resampler, &input, 77);
+
+    // One second of input makes one second of output
+    let ratio = output.len() as f64 / input.len() as f64;
+    assert!((ratio - 1.28).abs() < 1e-4, "length ratio {ratio}");
+    let expected = resampler.output_len(input.len());
+    assert!(output.len().abs_diff(expected) <= 1, "{} frames, expected {expected}", output.len());
+
+    // 1 kHz: 2000 zero crossings in one second
+    let crossings = output.windows(2).filter(|w| (w[0][0] < 0) != (w[1][0] < 0)).count();
+    assert!(crossings.abs_diff(2_000) <= 2, "{crossings} zero crossings");
+
+    // Each output frame follows the sine at 48 kHz, one input frame late
+    for (k, frame) in output.iter().enumerate().skip(2) {
+        let t = k as f64 / 48_000.0 - 1.0 / 37_500.0;
+        let ideal = 16_000.0 * (2.0 * PI * 1_000.0 * t).sin();
+        // Linear interpolation error at 1 kHz is under 0.4 % of the amplitude
+        assert!((frame[0] as f64 - ideal).abs() < 80.0, "frame {k}: {} vs {ideal:.0}", frame[0]);
+        // The right channel is the left one negated, give or take the rounding
+        assert!((frame[0] as i32 + frame[1] as i32).abs() <= 1, "frame {k}: {frame:?}");
+    }
+}
+
+/// Test tone in Hz, not a divisor of any of the rates
+const TONE: f64 = 997.0;
+
+/// Frequency of the left channel in Hz, from its rising zero crossings
+///
+/// The crossings are interpolated between frames. The first and the last one are
+/// thousands of periods apart, so their sub-frame error hardly shows.
+fn frequency(frames: &[[i16; 2]], rate: u32) -> f64 {
+    let crossings: Vec<f64> = frames
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| w[0][0] < 0 && w[1][0] >= 0)
+        .map(|(k, w)| {
+            let (a, b) = (w[0][0] as f64, w[1][0] as f64);
+            k as f64 + a / (a - b)
+        })
+        .collect();
+    let span = crossings[crossings.len() - 1] - crossings[0];
+    (crossings.len() - 1) as f64 * rate as f64 / span
+}
+
+#[test]
+fn frequency_is_kept() {
+    // The microphone's 16 kHz and 37.5 kHz presets to the DAC, converting down,
+    // and a ratio that is not a simple fraction
+    for (input_rate, output_rate) in [(16_000, 48_000), (37_500, 48_000), (48_000, 16_000), (50_000, 44_100)] {
+        let input = sine(TONE, input_rate, 10 * input_rate as usize);
+        // The measurement itself is good to a few hundredths of a ppm
+        assert!((frequency(&input, input_rate) / TONE - 1.0).abs() < 0.05e-6);
+
+        let mut resampler = Resampler::new(input_rate, output_rate);
+        let output = run(&mut resampler, &input, 77);
+        let error = (frequency(&output, output_rate) / TONE - 1.0) * 1e6;
+        assert!(error.abs() < 0.1, "{input_rate} -> {output_rate} Hz: {error:.3} ppm");
+    }
+}
+
+#[test]
+fn trim_shifts_the_frequency() {
+    for ppm in [2_500, -2_500, 100] {
+        let input = sine(TONE, 16_000, 160_000);
+        let mut resampler = Resampler::new(16_000, 48_000);
+        resampler.set_trim_ppm(ppm);
+        let output = run(&mut resampler, &input, 77);
+
+        // Consuming the input faster raises the pitch by the same amount
+        let shift = (frequency(&output, 48_000) / TONE - 1.0) * 1e6;
+        assert!((shift - ppm as f64).abs() < 0.1, "{ppm} ppm trim: {shift:.3} ppm shift");
+    }
+}
+
+#[test]
+fn full_scale_steps_stay_between_neighbours() {
+    for (low, high) in [([-20_000, 0], [20_000, 0]), ([i16::MIN, i16::MAX], [i16::MAX, i16::MIN])] {
+        let input: Vec<[i16; 2]> = (0..3_750).map(|i| if i % 2 == 0 { low } else { high }).collect();
+        let mut resampler = Resampler::new(37_500, 48_000);
+        let output = run(&mut resampler, &input, 77);
+        assert!(output.len() > 4_700, "{} frames", output.len());
+
+        // Output frame k lies between input frames k·r - 1 and k·r, one frame late
+        for (k, frame) in output.iter().enumerate() {
+            let i = k * 37_500 / 48_000;
+            let before = if i == 0 { [0; 2] } else { input[i - 1] };
+            let after = input[i];
+            for ch in 0..2 {
+                let (min, max) = (before[ch].min(after[ch]), before[ch].max(after[ch]));
+                assert!((min..=max).contains(&frame[ch]), "frame {k} channel {ch}: {} not in {min}..={max}", frame[ch]);
+            }
+        }
+    }
+}
+
+/// Input frames consumed to produce `len` output frames
+fn consumed_for(resampler: &mut Resampler, len: usize) -> usize {
+    let input = vec![[0; 2]; 2 * len];
+    let mut output = vec![[0; 2]; len];
+    let (consumed, produced) = resampler.process(&input, &mut output);
+    assert_eq!(produced, len);
+    consumed
+}
+
+#[test]
+fn trim_changes_the_consumption_rate() {
+    const OUTPUT: usize = 960_000;
+    let nominal = consumed_for(&mut Resampler::new(37_500, 48_000), OUTPUT);
+    // 0.78125 input frames per output frame, less the frame held back for the lag
+    assert!(nominal.abs_diff(750_000) <= 1, "{nominal} frames");
+
+    for ppm in [1_000, -1_000, 250, -4_000] {
+        let mut resampler = Resampler::new(37_500, 48_000);
+        resampler.set_trim_ppm(ppm);
+        assert_eq!(resampler.trim_ppm(), ppm);
+
+        let consumed = consumed_for(&mut resampler, OUTPUT);
+        let expected = nominal as f64 * (1.0 + ppm as f64 / 1e6);
+        assert!((consumed as f64 - expected).abs() <= 2.0, "{ppm} ppm: {consumed} frames, expected {expected}");
+    }
+}
+
+#[test]
+fn trim_is_clamped() {
+    let mut resampler = Resampler::new(48_000, 48_000);
+    resampler.set_trim_ppm(20_000);
+    assert_eq!(resampler.trim_ppm(), MAX_TRIM_PPM);
+    assert!((resampler.ratio() - 1.005).abs() < 1e-6);
+
+    resampler.set_trim_ppm(-20_000);
+    assert_eq!(resampler.trim_ppm(), -MAX_TRIM_PPM);
+    assert!((resampler.ratio() - 0.995).abs() < 1e-6);
+}
+
+/// Frames per block read by the consumer
+const BLOCK: f64 = 16.0;
+
+/// Buffer level the controller holds
+const TARGET: usize = 24;
+
+/// Simulate a buffer filled by a clock `offset_ppm` faster than nominal and
+/// drained by a resampler trimmed by `drift`, for `updates` blocks
+///
+/// Returns the buffer level after each update.
+fn simulate(drift: &mut DriftControl, offset_ppm: f64, updates: usize) -> Vec<f64> {
+    let mut level = TARGET as f64;
+    let mut levels = Vec::with_capacity(updates);
+    for _ in 0..updates {
+        let trim = drift.update(level.round() as usize);
+        level += BLOCK * (1.0 + offset_ppm / 1e6);
+        level -= BLOCK * (1.0 + trim as f64 / 1e6);
+        level = level.max(0.0);
+        levels.push(level);
+    }
+    levels
+}
+
+#[test]
+fn drift_control_converges_to_the_clock_offset() {
+    for offset in [300.0, -300.0, 1_500.0, -800.0] {
+        let mut drift = DriftControl::new(TARGET);
+        // 20 minutes of 16-frame blocks at 48 kHz
+        let levels = simulate(&mut drift, offset, 3_600_000);
+
+        let reported = drift.drift_ppm() as f64;
+        assert!((reported - offset).abs() <= offset.abs() * 0.05 + 10.0, "{offset} ppm reported as {reported}");
+
+        // The level stays near the target once settled
+        for level in &levels[levels.len() / 2..] {
+            assert!((level - TARGET as f64).abs() <= 2.0, "{offset} ppm: level {level}");
+        }
+        assert!((drift.level() - TARGET as f32).abs() <= 1.5, "{offset} ppm: smoothed {}", drift.level());
+    }
+}
+
+#[test]
+fn drift_control_without_offset_stays_at_zero() {
+    let mut drift = DriftControl::new(TARGET);
+    simulate(&mut drift, 0.0, 100_000);
+    assert_eq!(drift.drift_ppm(), 0);
+    assert_eq!(drift.trim_ppm(), 0);
+}
+
+#[test]
+fn drift_control_reset() {
+    let mut drift = DriftControl::new(TARGET);
+    simulate(&mut drift, 1_000.0, 10_000);
+    assert_ne!(drift.drift_ppm(), 0);
+    drift.reset();
+    assert_eq!(drift, DriftControl::new(TARGET));
+}
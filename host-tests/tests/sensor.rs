@@ -0,0 +1,200 @@
+//! Vector math and sensor trait tests
+//!
+//! The host build uses std's `sqrt`, `sin` and `cos` where the firmware uses
+//! micromath's approximations; the tolerances leave room for both.
+
+use core::f32::consts::{FRAC_PI_2, PI};
+
+use host_tests::mock::MockSensor;
+use host_tests::sensor::{Accelerometer, Gyroscope, Magnetometer, Vector3};
+
+const TOLERANCE: f32 = 1e-3;
+
+fn assert_close(actual: Vector3, expected: Vector3) {
+    assert!(
+        (actual - expected).norm() < TOLERANCE * expected.norm().max(1.0),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+const X: Vector3 = Vector3::new(1.0, 0.0, 0.0);
+const Y: Vector3 = Vector3::new(0.0, 1.0, 0.0);
+const Z: Vector3 = Vector3::new(0.0, 0.0, 1.0);
+
+#[test]
+fn operators() {
+    let a = Vector3::new(1.0, -2.0, 3.0);
+    let b = Vector3::new(0.5, 4.0, -1.5);
+
+    assert_eq!(a + b, Vector3::new(1.5, 2.0, 1.5));
+    assert_eq!(a - b, Vector3::new(0.5, -6.0, 4.5));
+    assert_eq!(a * 2.0, Vector3::new(2.0, -4.0, 6.0));
+    assert_eq!(a / 2.0, Vector3::new(0.5, -1.0, 1.5));
+    assert_eq!(-a, Vector3::new(-1.0, 2.0, -3.0));
+    assert_eq!(a + Vector3::ZERO, a);
+    assert_eq!(Vector3::default(), Vector3::ZERO);
+
+    let mut c = a;
+    c += b;
+    assert_eq!(c, a + b);
+    c -= b;
+    assert_eq!(c, a);
+    c *= 4.0;
+    assert_eq!(c, a * 4.0);
+    c /= 4.0;
+    assert_eq!(c, a);
+
+    assert_eq!(a.map(f32::abs), Vector3::new(1.0, 2.0, 3.0));
+}
+
+#[test]
+fn dot_and_cross() {
+    let a = Vector3::new(1.0, -2.0, 3.0);
+    let b = Vector3::new(0.5, 4.0, -1.5);
+    assert_eq!(a.dot(b), 0.5 - 8.0 - 4.5);
+    assert_eq!(X.dot(Y), 0.0);
+
+    // Right-handed axes
+    assert_eq!(X.cross(Y), Z);
+    assert_eq!(Y.cross(Z), X);
+    assert_eq!(Z.cross(X), Y);
+    assert_eq!(Y.cross(X), -Z);
+
+    // The cross product is perpendicular to both operands
+    let c = a.cross(b);
+    assert_eq!(c.dot(a), 0.0);
+    assert_eq!(c.dot(b), 0.0);
+    assert_eq!(a.cross(a), Vector3::ZERO);
+}
+
+#[test]
+fn norm() {
+    assert!((Vector3::new(3.0, 4.0, 12.0).norm() - 13.0).abs() < TOLERANCE);
+    assert!((Vector3::new(-1.0, -1.0, -1.0).norm() - 3f32.sqrt()).abs() < TOLERANCE);
+    assert_eq!(Vector3::ZERO.norm(), 0.0);
+
+    let unit = Vector3::new(0.0, -3.0, 4.0).normalized();
+    assert_close(unit, Vector3::new(0.0, -0.6, 0.8));
+    assert!((unit.norm() - 1.0).abs() < TOLERANCE);
+    assert_eq!(Vector3::ZERO.normalized(), Vector3::ZERO);
+}
+
+#[test]
+fn rotate_by_matrix() {
+    // 90° about Z
+    let matrix = [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+    assert_eq!(X.rotate(&matrix), Y);
+    assert_eq!(Y.rotate(&matrix), -X);
+    assert_eq!(Z.rotate(&matrix), Z);
+}
+
+#[test]
+fn rotate_about_follows_the_right_hand_rule() {
+    assert_close(X.rotate_about(Z, FRAC_PI_2), Y);
+    assert_close(Y.rotate_about(X, FRAC_PI_2), Z);
+    assert_close(Z.rotate_about(Y, FRAC_PI_2), X);
+    assert_close(X.rotate_about(Z, -FRAC_PI_2), -Y);
+    assert_close(X.rotate_about(Z, PI), -X);
+}
+
+#[test]
+fn rotate_about_any_axis() {
+    let v = Vector3::new(0.3, -1.2, 2.5);
+    // The axis does not need to be a unit vector
+    let axis = Vector3::new(1.0, 2.0, -2.0);
+
+    // A third of a turn about (1, 1, 1) cycles the axes
+    assert_close(v.rotate_about(Vector3::new(1.0, 1.0, 1.0), 2.0 * PI / 3.0), Vector3::new(v.z, v.x, v.y));
+
+    for angle in [0.0, 0.1, 1.0, 2.5, -1.7] {
+        let rotated = v.rotate_about(axis, angle);
+        // Length and the component along the axis are kept
+        assert!((rotated.norm() - v.norm()).abs() < TOLERANCE, "angle {angle}");
+        let k = axis.normalized();
+        assert!((rotated.dot(k) - v.dot(k)).abs() < TOLERANCE, "angle {angle}");
+        // Rotating back undoes it
+        assert_close(rotated.rotate_about(axis, -angle), v);
+        // Rotations about one axis add up
+        assert_close(rotated.rotate_about(axis * 3.0, 0.5), v.rotate_about(axis, angle + 0.5));
+    }
+
+    // A vector along the axis does not move
+    assert_close((axis * 2.0).rotate_about(axis, 1.0), axis * 2.0);
+    // A full turn comes back
+    assert_close(v.rotate_about(axis, 2.0 * PI), v);
+}
+
+#[test]
+fn rotate_about_matches_the_rotation_matrix() {
+    // Rotation matrix for an angle about a unit axis
+    let k = Vector3::new(2.0, -1.0, 2.0).normalized();
+    let angle: f32 = 0.8;
+    let (s, c) = angle.sin_cos();
+    let t = 1.0 - c;
+    let matrix = [
+        [t * k.x * k.x + c, t * k.x * k.y - s * k.z, t * k.x * k.z + s * k.y],
+        [t * k.x * k.y + s * k.z, t * k.y * k.y + c, t * k.y * k.z - s * k.x],
+        [t * k.x * k.z - s * k.y, t * k.y * k.z + s * k.x, t * k.z * k.z + c],
+    ];
+
+    for v in [X, Y, Z, Vector3::new(-0.7, 0.2, 1.9)] {
+        assert_close(v.rotate_about(k, angle), v.rotate(&matrix));
+    }
+}
+
+/// Average `count` angular rate readings, as a bias calibration would
+fn gyro_bias<G: Gyroscope>(gyro: &mut G, count: usize) -> Vector3 {
+    let mut sum = Vector3::ZERO;
+    for _ in 0..count {
+        sum += gyro.read_angular_rate();
+    }
+    sum / count as f32
+}
+
+/// Tilt from vertical in radians, as in the module example
+fn tilt<A: Accelerometer>(accel: &mut A) -> f32 {
+    accel.read_acceleration().normalized().z.acos()
+}
+
+/// Heading in radians from the magnetometer, with the board lying flat
+fn heading<M: Magnetometer>(mag: &mut M) -> f32 {
+    let field = mag.read_magnetic_field();
+    field.y.atan2(field.x)
+}
+
+#[test]
+fn mock_replays_readings() {
+    let mut sensor = MockSensor::<u8>::new();
+    assert!(!sensor.gyro_data_ready());
+    assert_eq!(sensor.read_angular_rate(), Vector3::ZERO);
+
+    sensor.queue_angular_rate([Vector3::new(1.0, 2.0, 3.0), Vector3::new(3.0, 2.0, 1.0)]);
+    assert!(sensor.gyro_data_ready());
+    assert!(!sensor.accel_data_ready());
+    assert!(!sensor.mag_data_ready());
+    assert_eq!(sensor.read_angular_rate(), Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(sensor.read_angular_rate(), Vector3::new(3.0, 2.0, 1.0));
+    assert!(!sensor.gyro_data_ready());
+    // The output registers keep the last sample
+    assert_eq!(sensor.read_angular_rate(), Vector3::new(3.0, 2.0, 1.0));
+    assert_eq!(sensor.reads(), [4, 0, 0]);
+
+    sensor.configure_gyro(1);
+    sensor.configure_accel(2);
+    sensor.configure_mag(3);
+    assert_eq!(sensor.configs, [1, 2, 3]);
+}
+
+#[test]
+fn generic_code_runs_against_the_mock() {
+    let mut sensor = MockSensor::<()>::new();
+    sensor.queue_angular_rate([Vector3::new(0.5, -0.25, 1.0), Vector3::new(1.5, 0.25, 1.0)]);
+    sensor.queue_acceleration([Z, Vector3::new(0.0, 1.0, 1.0)]);
+    sensor.queue_magnetic_field([Vector3::new(0.0, 0.3, -0.4)]);
+
+    assert_close(gyro_bias(&mut sensor, 2), Vector3::new(1.0, 0.0, 1.0));
+    assert!(tilt(&mut sensor).abs() < TOLERANCE);
+    assert!((tilt(&mut sensor) - PI / 4.0).abs() < TOLERANCE);
+    assert!((heading(&mut sensor) - FRAC_PI_2).abs() < TOLERANCE);
+    assert_eq!(sensor.reads(), [2, 2, 1]);
+}
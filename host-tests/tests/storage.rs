@@ -0,0 +1,369 @@
+//! Key-value store tests against the RAM flash model, including power loss
+
+use host_tests::flash::{RamFlash, RamFlashError, SECTOR_SIZE};
+use host_tests::sensor::Vector3;
+use host_tests::storage::{Error, Key, Storage, MAX_KEYS};
+
+/// The store uses sectors 1 and 2; sector 0 stands for the firmware
+const SECTORS: [u32; 2] = [SECTOR_SIZE as u32, 2 * SECTOR_SIZE as u32];
+
+/// Space for records in a sector, after the sector header
+const LOG_SIZE: u32 = SECTOR_SIZE as u32 - 8;
+
+/// Size of a record holding a `u32`
+const U32_RECORD: u32 = 12;
+
+fn flash() -> RamFlash {
+    let mut flash = RamFlash::new(3);
+    flash.data_mut()[..SECTOR_SIZE].fill(0x5A);
+    flash
+}
+
+fn mount(flash: &mut RamFlash) -> Storage<&mut RamFlash> {
+    Storage::from_flash(flash, SECTORS, SECTOR_SIZE as u32).unwrap()
+}
+
+fn read_u32(storage: &mut Storage<&mut RamFlash>, key: u16) -> Option<u32> {
+    let mut buf = [0; 4];
+    let len = storage.read(key, &mut buf).unwrap()?;
+    assert_eq!(len, 4);
+    Some(u32::from_le_bytes(buf))
+}
+
+fn write_u32(storage: &mut Storage<&mut RamFlash>, key: u16, value: u32) -> Result<(), Error<RamFlashError>> {
+    storage.write(key, &value.to_le_bytes())
+}
+
+#[test]
+fn put_get_remove() {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.free(), LOG_SIZE);
+
+    let mut buf = [0; 16];
+    assert_eq!(storage.read(1, &mut buf), Ok(None));
+
+    storage.write(1, b"hello").unwrap();
+    storage.write(2, b"world!").unwrap();
+    assert_eq!(storage.read(1, &mut buf), Ok(Some(5)));
+    assert_eq!(&buf[..5], b"hello");
+    assert_eq!(storage.read(2, &mut buf), Ok(Some(6)));
+    assert_eq!(&buf[..6], b"world!");
+
+    // A short buffer gets the start of the value
+    let mut short = [0; 3];
+    assert_eq!(storage.read(2, &mut short), Ok(Some(6)));
+    assert_eq!(&short, b"wor");
+
+    storage.remove(1).unwrap();
+    assert_eq!(storage.read(1, &mut buf), Ok(None));
+    assert_eq!(storage.read(2, &mut buf), Ok(Some(6)));
+    // Removing a missing key writes nothing
+    let free = storage.free();
+    storage.remove(1).unwrap();
+    storage.remove(7).unwrap();
+    assert_eq!(storage.free(), free);
+
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.read(1, &mut buf), Ok(None));
+    assert_eq!(storage.read(2, &mut buf), Ok(Some(6)));
+
+    // The sector before the store is left alone
+    assert!(flash.data()[..SECTOR_SIZE].iter().all(|&byte| byte == 0x5A));
+}
+
+#[test]
+fn unchanged_value_is_not_rewritten() {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    write_u32(&mut storage, 1, 42).unwrap();
+    let free = storage.free();
+    write_u32(&mut storage, 1, 42).unwrap();
+    assert_eq!(storage.free(), free);
+    write_u32(&mut storage, 1, 43).unwrap();
+    assert_eq!(storage.free(), free - U32_RECORD);
+}
+
+#[test]
+fn typed_values() {
+    const COUNT: Key<u32> = Key::new(0x1000);
+    const BIAS: Key<Vector3> = Key::new(0x1001);
+    const MATRIX: Key<[[f32; 3]; 3]> = Key::new(0x1002);
+    const ENABLED: Key<bool> = Key::new(0x1003);
+    const COUNT_AS_U16: Key<u16> = Key::new(0x1000);
+
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    let bias = Vector3::new(0.5, -1.25, 3.0);
+    let matrix = [[1.0, 0.1, 0.0], [0.1, 0.9, 0.0], [0.0, 0.0, 1.1]];
+
+    assert_eq!(storage.load(COUNT), Ok(None));
+    storage.save(COUNT, &7).unwrap();
+    storage.save(BIAS, &bias).unwrap();
+    storage.save(MATRIX, &matrix).unwrap();
+    storage.save(ENABLED, &true).unwrap();
+
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.load(COUNT), Ok(Some(7)));
+    assert_eq!(storage.load(BIAS), Ok(Some(bias)));
+    assert_eq!(storage.load(MATRIX), Ok(Some(matrix)));
+    assert_eq!(storage.load(ENABLED), Ok(Some(true)));
+    // A value of another size reads as missing
+    assert_eq!(storage.load(COUNT_AS_U16), Ok(None));
+}
+
+#[test]
+fn invalid_keys_and_lengths() {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.write(0xFFFF, b"x"), Err(Error::InvalidKey));
+    assert_eq!(storage.remove(0xFFFF), Err(Error::InvalidKey));
+    assert_eq!(storage.write(1, b""), Err(Error::InvalidLength));
+    assert_eq!(storage.write(1, &[0; SECTOR_SIZE]), Err(Error::InvalidLength));
+    assert_eq!(storage.free(), LOG_SIZE);
+}
+
+#[test]
+fn overwrite_then_compact() {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    write_u32(&mut storage, 2, 200).unwrap();
+    write_u32(&mut storage, 3, 300).unwrap();
+    storage.remove(3).unwrap();
+
+    // Each sector holds 20 u32 records, so this compacts several times
+    for value in 0..100 {
+        write_u32(&mut storage, 1, value).unwrap();
+        assert_eq!(read_u32(&mut storage, 1), Some(value));
+        assert_eq!(read_u32(&mut storage, 2), Some(200));
+        assert_eq!(read_u32(&mut storage, 3), None);
+    }
+    // After the last compaction only keys 1 and 2 were left
+    assert_eq!((LOG_SIZE - storage.free()) % U32_RECORD, 0);
+    assert!(storage.free() <= LOG_SIZE - 2 * U32_RECORD);
+
+    // Both sectors take turns, one erase per compaction
+    let [firmware, a, b] = flash.erase_counts() else { unreachable!() };
+    assert_eq!(*firmware, 0);
+    assert!(*a >= 3 && a.abs_diff(*b) <= 1, "erase counts {a} and {b}");
+
+    let mut storage = mount(&mut flash);
+    assert_eq!(read_u32(&mut storage, 1), Some(99));
+    assert_eq!(read_u32(&mut storage, 2), Some(200));
+    assert_eq!(read_u32(&mut storage, 3), None);
+}
+
+#[test]
+fn full() {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    // 5 records of 48 bytes fit in a sector
+    for key in 1..=5 {
+        storage.write(key, &[key as u8; 40]).unwrap();
+    }
+    assert_eq!(storage.write(6, &[6; 40]), Err(Error::Full));
+
+    // Compaction makes room once a key is removed
+    storage.remove(1).unwrap();
+    storage.write(6, &[6; 40]).unwrap();
+    let mut buf = [0; 40];
+    for key in 2..=6 {
+        assert_eq!(storage.read(key, &mut buf), Ok(Some(40)));
+        assert_eq!(buf, [key as u8; 40]);
+    }
+}
+
+/// Log sectors made of 4 model sectors, with room for more than `MAX_KEYS` records
+const BIG_SECTOR: u32 = 4 * SECTOR_SIZE as u32;
+
+fn mount_big(flash: &mut RamFlash) -> Storage<&mut RamFlash> {
+    Storage::from_flash(flash, [0, BIG_SECTOR], BIG_SECTOR).unwrap()
+}
+
+#[test]
+fn compact_a_sector_of_small_records() {
+    let mut flash = RamFlash::new(8);
+    let mut storage = mount_big(&mut flash);
+    // 16 keys overwritten in turn, until 84 records fill the sector
+    let records = (BIG_SECTOR - 8) / U32_RECORD;
+    for value in 0..records {
+        write_u32(&mut storage, (value % 16) as u16, value).unwrap();
+    }
+    assert!(storage.free() < U32_RECORD);
+
+    // Mounting, looking up the key and compacting each take one pass over the log,
+    // plus a CRC check and a copy per key. Checking every record against the later
+    // ones took thousands of reads.
+    let before = flash.reads();
+    let mut storage = mount_big(&mut flash);
+    write_u32(&mut storage, 0, 1_000).unwrap();
+    let reads = flash.reads() - before;
+    assert!(reads < 4 * records as usize, "{reads} reads");
+
+    let mut storage = mount_big(&mut flash);
+    assert_eq!(storage.free(), BIG_SECTOR - 8 - 17 * U32_RECORD);
+    assert_eq!(read_u32(&mut storage, 0), Some(1_000));
+    for key in 1..16 {
+        let latest = (0..records).filter(|value| value % 16 == key).max();
+        assert_eq!(read_u32(&mut storage, key as u16), latest, "key {key}");
+    }
+}
+
+#[test]
+fn too_many_keys() {
+    let mut flash = RamFlash::new(8);
+    let mut storage = mount_big(&mut flash);
+    for key in 0..MAX_KEYS as u16 {
+        write_u32(&mut storage, key, key as u32).unwrap();
+    }
+    assert_eq!(write_u32(&mut storage, 100, 100), Err(Error::TooManyKeys));
+    // Known keys can still be updated
+    write_u32(&mut storage, 1, 10).unwrap();
+
+    // Compaction drops a removed key, which makes room for another
+    storage.remove(0).unwrap();
+    write_u32(&mut storage, 100, 100).unwrap();
+
+    let mut storage = mount_big(&mut flash);
+    assert_eq!(read_u32(&mut storage, 0), None);
+    assert_eq!(read_u32(&mut storage, 1), Some(10));
+    assert_eq!(read_u32(&mut storage, 100), Some(100));
+    assert_eq!(write_u32(&mut storage, 101, 101), Err(Error::TooManyKeys));
+}
+
+/// Set keys 1 and 2, then update key 1 with `new` and cut the power after `cut` bytes
+///
+/// Returns the flash after the power loss.
+fn interrupted_update(new: &[u8], cut: usize) -> RamFlash {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    storage.write(1, b"old value").unwrap();
+    storage.write(2, b"other").unwrap();
+
+    flash.power_off_after(cut);
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.write(1, new), Err(Error::Flash(RamFlashError::PowerLoss)));
+    flash.power_on();
+    flash
+}
+
+/// Check that the store still holds the values from before an interrupted update
+/// of key 1 to `new`, and can still be written
+fn assert_recovers(mut flash: RamFlash, new: &[u8], cut: usize) {
+    let mut buf = [0; 32];
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.read(1, &mut buf), Ok(Some(9)), "cut after {cut} bytes");
+    assert_eq!(&buf[..9], b"old value", "cut after {cut} bytes");
+    assert_eq!(storage.read(2, &mut buf), Ok(Some(5)), "cut after {cut} bytes");
+    assert_eq!(&buf[..5], b"other", "cut after {cut} bytes");
+
+    storage.write(1, new).unwrap();
+    let mut storage = mount(&mut flash);
+    assert_eq!(storage.read(1, &mut buf), Ok(Some(new.len())), "cut after {cut} bytes");
+    assert_eq!(&buf[..new.len()], new, "cut after {cut} bytes");
+    assert_eq!(storage.read(2, &mut buf), Ok(Some(5)), "cut after {cut} bytes");
+}
+
+#[test]
+fn torn_record_header_keeps_previous_value() {
+    let new = b"a new value, 24 bytes ok";
+    for cut in 0..8 {
+        assert_recovers(interrupted_update(new, cut), new, cut);
+    }
+}
+
+#[test]
+fn torn_value_keeps_previous_value() {
+    let new = b"a new value, 24 bytes ok";
+    for cut in 8..8 + new.len() {
+        assert_recovers(interrupted_update(new, cut), new, cut);
+    }
+}
+
+#[test]
+fn corrupt_value_keeps_previous_value() {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    write_u32(&mut storage, 1, 1).unwrap();
+    write_u32(&mut storage, 1, 2).unwrap();
+
+    // Clear the bits of the second record's value, as a stray write would
+    let value = SECTORS[0] as usize + 8 + U32_RECORD as usize + 8;
+    flash.data_mut()[value] = 0;
+    let mut storage = mount(&mut flash);
+    assert_eq!(read_u32(&mut storage, 1), Some(1));
+}
+
+/// Keys 2 and 3 set and key 1 overwritten until the active sector is full
+fn full_log() -> RamFlash {
+    let mut flash = flash();
+    let mut storage = mount(&mut flash);
+    write_u32(&mut storage, 2, 200).unwrap();
+    write_u32(&mut storage, 3, 300).unwrap();
+    for value in 0..(LOG_SIZE / U32_RECORD) - 2 {
+        write_u32(&mut storage, 1, value).unwrap();
+    }
+    assert!(storage.free() < U32_RECORD);
+    flash
+}
+
+#[test]
+fn power_loss_during_compaction() {
+    let last = LOG_SIZE / U32_RECORD - 3;
+    let mut completed = false;
+
+    // Cut the power after every byte of the compaction and the write that follows
+    for cut in 0.. {
+        let mut flash = full_log();
+        flash.power_off_after(cut);
+        let mut storage = mount(&mut flash);
+        let result = write_u32(&mut storage, 1, 1_000);
+        flash.power_on();
+
+        let mut storage = mount(&mut flash);
+        if result.is_ok() {
+            // 3 records copied, a sector header and the new record
+            assert_eq!(cut, 4 * U32_RECORD as usize + 8);
+            assert_eq!(read_u32(&mut storage, 1), Some(1_000));
+            completed = true;
+            break;
+        }
+
+        // Until the new sector's header is written, the old sector stays active;
+        // after that, the new one holds the same values
+        assert_eq!(read_u32(&mut storage, 1), Some(last), "cut after {cut} bytes");
+        assert_eq!(read_u32(&mut storage, 2), Some(200), "cut after {cut} bytes");
+        assert_eq!(read_u32(&mut storage, 3), Some(300), "cut after {cut} bytes");
+
+        write_u32(&mut storage, 1, 2_000).unwrap();
+        let mut storage = mount(&mut flash);
+        assert_eq!(read_u32(&mut storage, 1), Some(2_000), "cut after {cut} bytes");
+        assert_eq!(read_u32(&mut storage, 2), Some(200), "cut after {cut} bytes");
+    }
+    assert!(completed);
+}
+
+#[test]
+fn older_generation_wins_over_an_unfinished_compaction() {
+    let mut flash = full_log();
+    let active = flash.data()[SECTORS[0] as usize..SECTORS[1] as usize].to_vec();
+
+    // Stop right before the new sector's header: 3 records copied, nothing more
+    flash.power_off_after(3 * U32_RECORD as usize);
+    let mut storage = mount(&mut flash);
+    assert!(write_u32(&mut storage, 1, 1_000).is_err());
+    flash.power_on();
+
+    // The copies are in place, but without a header the new sector is ignored
+    let target = &flash.data()[SECTORS[1] as usize..];
+    assert!(target[..8].iter().all(|&byte| byte == 0xFF));
+    assert!(target[8..8 + 3 * U32_RECORD as usize].iter().any(|&byte| byte != 0xFF));
+    assert_eq!(&flash.data()[SECTORS[0] as usize..SECTORS[1] as usize], active);
+
+    let mut storage = mount(&mut flash);
+    assert_eq!(read_u32(&mut storage, 1), Some(LOG_SIZE / U32_RECORD - 3));
+    // Still full: the next write redoes the compaction
+    assert!(storage.free() < U32_RECORD);
+    write_u32(&mut storage, 1, 1_000).unwrap();
+    assert_eq!(storage.free(), LOG_SIZE - 4 * U32_RECORD);
+}
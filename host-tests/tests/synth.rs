@@ -0,0 +1,184 @@
+//! RTTTL parser, note timing and sequencer tests
+
+use embassy_time::Duration;
+use host_tests::synth::{midi_to_hz, Instrument, Note, Rtttl, RtttlError, Sequencer};
+
+/// Notes of `text`, which must parse
+fn notes(text: &str) -> Vec<Note> {
+    Rtttl::parse(text).unwrap().notes().collect()
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn midi_notes() {
+    assert_eq!(midi_to_hz(69), 440.0);
+    assert_eq!(midi_to_hz(81), 880.0);
+    assert_eq!(midi_to_hz(57), 220.0);
+    assert!((midi_to_hz(60) - 261.626).abs() < 0.01);
+    assert!((midi_to_hz(12) - 16.352).abs() < 0.01);
+}
+
+#[test]
+fn defaults() {
+    // Without defaults: quarter notes, octave 6, 63 bpm
+    let tune = Rtttl::parse(" Tune :: c, a").unwrap();
+    assert_eq!(tune.name(), "Tune");
+    assert_eq!(tune.bpm(), 63);
+    let quarter = Duration::from_micros(60_000_000 / 63);
+    assert_eq!(tune.notes().collect::<Vec<_>>(), [Note::new(midi_to_hz(84), quarter), Note::new(1760.0, quarter)]);
+
+    // Given defaults apply to every note without its own values
+    let tune = Rtttl::parse("x:d=8, o=4 ,b=120:a,2a5").unwrap();
+    assert_eq!(tune.bpm(), 120);
+    assert_eq!(tune.notes().collect::<Vec<_>>(), [Note::new(440.0, ms(250)), Note::new(880.0, ms(1_000))]);
+}
+
+#[test]
+fn note_names() {
+    let notes = notes("x:d=4,o=4,b=120:c,c#,d,d#,e,f,f#,g,g#,a,a#,b,h,C,A");
+    let expected = [60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 71, 60, 69].map(|midi| Some(midi_to_hz(midi)));
+    assert_eq!(notes.iter().map(|n| n.frequency).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn durations_at_a_given_bpm() {
+    // 120 bpm: a quarter note is half a second
+    let notes = notes("x:d=4,o=5,b=120:1c,2c,4c,8c,16c,32c,c,p,8p");
+    let durations: Vec<_> = notes.iter().map(|n| n.duration).collect();
+    assert_eq!(
+        durations,
+        [ms(2_000), ms(1_000), ms(500), ms(250), ms(125), Duration::from_micros(62_500), ms(500), ms(500), ms(250)]
+    );
+    assert_eq!(notes[7], Note::rest(ms(500)));
+
+    // The default duration follows the tempo too
+    assert_eq!(notes_duration("x:d=16,b=200:c"), Duration::from_micros(75_000));
+    assert_eq!(notes_duration("x:b=900:1c"), Duration::from_micros(266_666));
+    assert_eq!(Rtttl::parse("x:d=4,b=120:c,8p,2c").unwrap().duration(), ms(1_750));
+}
+
+fn notes_duration(text: &str) -> Duration {
+    notes(text)[0].duration
+}
+
+#[test]
+fn dotted_notes_before_and_after_the_octave() {
+    let a5 = midi_to_hz(81);
+    // The dot is accepted on either side of the octave, and makes the note 1.5 times as long
+    assert_eq!(notes("x:d=4,o=6,b=120:a.5"), [Note::new(a5, ms(750))]);
+    assert_eq!(notes("x:d=4,o=6,b=120:a5."), [Note::new(a5, ms(750))]);
+    assert_eq!(notes("x:d=4,o=6,b=120:8a#.5"), [Note::new(midi_to_hz(82), Duration::from_micros(375_000))]);
+    // Without an octave, the default one
+    assert_eq!(notes("x:d=4,o=5,b=120:a."), [Note::new(a5, ms(750))]);
+    assert_eq!(notes("x:d=4,o=5,b=120:p."), [Note::rest(ms(750))]);
+}
+
+#[test]
+fn invalid_notes_report_their_index() {
+    // Empty tokens are skipped and not counted
+    for (text, index) in [
+        ("x:d=4,o=5,b=120:q", 0),
+        ("x:d=4,o=5,b=120:c,d,x,e", 2),
+        ("x:d=4,o=5,b=120:c,,d, ,3c", 2),
+        ("x:d=4,o=5,b=120:c,64c", 1),
+        ("x:d=4,o=5,b=120:c,c9", 1),
+        ("x:d=4,o=5,b=120:c5x", 0),
+        ("x:d=4,o=5,b=120:c,c#5..", 1),
+        ("x:d=4,o=5,b=120:c,c##", 1),
+        ("x:d=4,o=5,b=120:c,4", 1),
+        ("x:d=4,o=5,b=120:c,99999999c", 1),
+    ] {
+        assert_eq!(Rtttl::parse(text), Err(RtttlError::InvalidNote(index)), "{text}");
+    }
+}
+
+#[test]
+fn out_of_range_defaults() {
+    for defaults in ["d=0", "d=3", "d=64", "o=9", "b=0", "b=901", "x=1", "d4", "d=a", "d=", "b=-1"] {
+        let text = format!("x:{defaults}:c");
+        assert_eq!(Rtttl::parse(&text), Err(RtttlError::InvalidDefault), "{defaults}");
+    }
+    for defaults in ["d=1", "d=32", "o=0", "o=8", "b=1", "b=900", ""] {
+        let text = format!("x:{defaults}:c");
+        assert!(Rtttl::parse(&text).is_ok(), "{defaults}");
+    }
+}
+
+#[test]
+fn missing_sections() {
+    for text in ["", "x", "x:d=4,o=5,b=120"] {
+        assert_eq!(Rtttl::parse(text), Err(RtttlError::MissingSection), "{text:?}");
+    }
+    // An empty melody is allowed
+    assert_eq!(Rtttl::parse("x::").unwrap().notes().count(), 0);
+}
+
+const RATE: u32 = 8_000;
+
+/// Samples in a note of `duration` at [`RATE`]
+fn samples(duration: Duration) -> usize {
+    (duration.as_micros() * RATE as u64 / 1_000_000) as usize
+}
+
+/// Index of the first silent frame at or after `from`, and of the first sounding frame after that
+fn next_silence_and_sound(frames: &[[i16; 2]], from: usize) -> (usize, usize) {
+    let silence = from + frames[from..].iter().position(|f| f[0] == 0 && f[1] == 0).unwrap();
+    let sound = silence + frames[silence..].iter().position(|f| f[0] != 0).unwrap_or(frames.len() - silence);
+    (silence, sound)
+}
+
+#[test]
+fn sequencer_follows_the_note_timing() {
+    // 250 ms notes at 120 bpm: 2000 samples each
+    let tune = Rtttl::parse("x:d=8,o=5,b=120:a,p,a").unwrap();
+    let note = samples(ms(250));
+    let frames: Vec<_> = Sequencer::new(RATE, [(tune.notes(), Instrument::BUZZER)]).collect();
+
+    // The melody ends with the last note's length, after its release faded out
+    assert_eq!(frames.len(), 3 * note);
+
+    // The first note sounds from the start, is released after 90 % of its length
+    // and fades out over the instrument's 20 ms release
+    assert_ne!(frames[1], [0, 0]);
+    let release = samples(Instrument::BUZZER.envelope.release);
+    let (silence, sound) = next_silence_and_sound(&frames, 0);
+    assert!(silence.abs_diff(note * 9 / 10 + release) <= 2, "silent from {silence}");
+    // The rest keeps it silent until the third note
+    assert_eq!(sound, 2 * note);
+    let (silence, _) = next_silence_and_sound(&frames, sound);
+    assert!(silence.abs_diff(2 * note + note * 9 / 10 + release) <= 2, "silent from {silence}");
+}
+
+#[test]
+fn sequencer_plays_one_track_per_voice() {
+    let melody = Rtttl::parse("m:d=4,o=5,b=120:c,e").unwrap();
+    let bass = Rtttl::parse("b:d=2,o=3,b=120:c,c").unwrap();
+    let mut sequencer = Sequencer::new(RATE, [(melody.notes(), Instrument::BASS), (bass.notes(), Instrument::BASS)]);
+
+    let half = samples(ms(1_000));
+    assert!(sequencer.by_ref().take(10).all(|f| f != [0, 0]));
+    assert!(sequencer.synth().is_playing(0) && sequencer.synth().is_playing(1));
+
+    // The melody (two quarter notes) is over after one second, the bass plays on
+    let rest: Vec<_> = sequencer.by_ref().take(half + half / 2 - 10).collect();
+    assert_eq!(rest.len(), half + half / 2 - 10);
+    assert!(!sequencer.synth().is_playing(0));
+    assert!(sequencer.synth().is_playing(1));
+
+    // Two half notes
+    assert_eq!(sequencer.count(), 2 * half - (half + half / 2));
+}
+
+#[test]
+fn sequencer_skips_zero_length_notes() {
+    // Too short to last a sample at this rate
+    let notes = [Note::new(440.0, Duration::from_ticks(0)), Note::new(440.0, ms(10))];
+    let frames: Vec<_> = Sequencer::new(RATE, [(notes.into_iter(), Instrument::BUZZER)]).collect();
+    let expected: Vec<_> = Sequencer::new(RATE, [(notes[1..].iter().copied(), Instrument::BUZZER)]).collect();
+    assert_eq!(frames, expected);
+    // The release outlasts the short note
+    assert!(frames.len() > samples(ms(10)));
+}
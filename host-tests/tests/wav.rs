@@ -0,0 +1,226 @@
+//! WAV parser tests against the files in `data/` (see `data/generate.py`)
+
+use host_tests::wav::{Wav, WavEncoding, WavError, WavFormat};
+
+/// Left channel of the 16-bit sample files
+fn ramp(i: usize) -> i16 {
+    (((i * 300) % 65_536) as i32 - 32_768) as i16
+}
+
+/// A `fmt ` chunk body
+fn fmt(tag: u16, channels: u16, rate: u32, block_align: u16, bits: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&tag.to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&rate.to_le_bytes());
+    body.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+    body.extend_from_slice(&block_align.to_le_bytes());
+    body.extend_from_slice(&bits.to_le_bytes());
+    body
+}
+
+/// A WAV file made of `chunks`
+fn wav(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let mut body = b"WAVE".to_vec();
+    for (id, data) in chunks {
+        body.extend_from_slice(*id);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    let mut file = b"RIFF".to_vec();
+    file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    file.extend(body);
+    file
+}
+
+/// A 16-bit mono PCM file with `frames` silent frames
+fn pcm16_mono(frames: usize) -> Vec<u8> {
+    wav(&[(b"fmt ", &fmt(1, 1, 16_000, 2, 16)), (b"data", &vec![0; 2 * frames])])
+}
+
+#[test]
+fn pcm16_stereo() {
+    let wav = Wav::parse(include_bytes!("../data/pcm16_stereo.wav")).unwrap();
+    assert_eq!(
+        wav.format(),
+        WavFormat {
+            encoding: WavEncoding::Pcm,
+            channels: 2,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            block_align: 4,
+        }
+    );
+    assert_eq!(wav.frame_count(), 100);
+    assert_eq!(wav.duration_ms(), 6);
+
+    let frames: Vec<_> = wav.frames().collect();
+    assert_eq!(frames.len(), 100);
+    for (i, frame) in frames.iter().enumerate() {
+        // The right channel is -ramp - 1
+        assert_eq!(*frame, [ramp(i), !ramp(i)], "frame {i}");
+    }
+}
+
+#[test]
+fn pcm16_mono_is_played_on_both_channels() {
+    let wav = Wav::parse(include_bytes!("../data/pcm16_mono.wav")).unwrap();
+    assert_eq!(wav.format().channels, 1);
+    assert_eq!(wav.format().block_align, 2);
+
+    let frames: Vec<_> = wav.frames().collect();
+    assert_eq!(frames.len(), 100);
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(*frame, [ramp(i); 2], "frame {i}");
+    }
+}
+
+#[test]
+fn pcm8_mono_is_scaled_to_16_bits() {
+    let wav = Wav::parse(include_bytes!("../data/pcm8_mono.wav")).unwrap();
+    assert_eq!(wav.format().bits_per_sample, 8);
+    assert_eq!(wav.format().block_align, 1);
+
+    let frames: Vec<_> = wav.frames().collect();
+    assert_eq!(frames.len(), 256);
+    assert_eq!(frames[0], [i16::MIN; 2]);
+    assert_eq!(frames[128], [0; 2]);
+    assert_eq!(frames[255], [127 << 8; 2]);
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame[0], (i as i16 - 128) * 256, "frame {i}");
+    }
+}
+
+#[test]
+fn extensible_pcm() {
+    let wav = Wav::parse(include_bytes!("../data/pcm16_stereo_extensible.wav")).unwrap();
+    assert_eq!(wav.format().encoding, WavEncoding::Pcm);
+    assert_eq!(wav.format().channels, 2);
+    assert_eq!(wav.format().bits_per_sample, 16);
+
+    let expected = Wav::parse(include_bytes!("../data/pcm16_stereo.wav")).unwrap();
+    assert!(wav.frames().eq(expected.frames()));
+}
+
+#[test]
+fn extensible_with_another_subformat_is_rejected() {
+    let mut body = fmt(0xFFFE, 1, 16_000, 2, 16);
+    body.extend_from_slice(&[22, 0, 16, 0, 0, 0, 0, 0]);
+    // KSDATAFORMAT_SUBTYPE_IEEE_FLOAT
+    body.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xAA, 0, 0x38, 0x9B, 0x71]);
+    let file = wav(&[(b"fmt ", &body), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::UnsupportedEncoding(3));
+}
+
+#[test]
+fn odd_sized_chunks_are_padded() {
+    let wav = Wav::parse(include_bytes!("../data/pcm8_odd_chunks.wav")).unwrap();
+    assert_eq!(wav.format().sample_rate, 16_000);
+    // The pad byte after the data is not a sample
+    assert_eq!(wav.data().len(), 101);
+    assert_eq!(wav.frame_count(), 101);
+    assert_eq!(wav.frames().last(), Some([(100 - 128) << 8; 2]));
+}
+
+#[test]
+fn truncated_data_chunk_is_cut_to_whole_frames() {
+    let wav = Wav::parse(include_bytes!("../data/pcm16_truncated_data.wav")).unwrap();
+    assert_eq!(wav.data().len(), 100);
+    assert_eq!(wav.frame_count(), 50);
+    let frames: Vec<_> = wav.frames().collect();
+    assert_eq!(frames.len(), 50);
+    assert_eq!(frames[49], [ramp(49); 2]);
+}
+
+#[test]
+fn frames_are_an_exact_size_iterator() {
+    let wav = Wav::parse(include_bytes!("../data/pcm16_stereo.wav")).unwrap();
+    let mut frames = wav.frames();
+    assert_eq!(frames.len(), 100);
+    frames.nth(9);
+    assert_eq!(frames.len(), 90);
+}
+
+#[test]
+fn not_riff() {
+    assert_eq!(Wav::parse(b"").unwrap_err(), WavError::NotRiff);
+    assert_eq!(Wav::parse(b"RIFX\0\0\0\0WAVE").unwrap_err(), WavError::NotRiff);
+}
+
+#[test]
+fn not_wave() {
+    let mut file = pcm16_mono(4);
+    file[8..12].copy_from_slice(b"AVI ");
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::NotWave);
+}
+
+#[test]
+fn missing_format() {
+    let file = wav(&[(b"data", &[0; 4]), (b"fmt ", &fmt(1, 1, 16_000, 2, 16))]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::MissingFormat);
+}
+
+#[test]
+fn missing_data() {
+    let file = wav(&[(b"fmt ", &fmt(1, 1, 16_000, 2, 16))]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::MissingData);
+}
+
+#[test]
+fn truncated() {
+    // A chunk before `data` running past the end of the file
+    let mut file = pcm16_mono(4);
+    file.truncate(30);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::Truncated);
+
+    // A `fmt ` chunk too short for the fields
+    let file = wav(&[(b"fmt ", &fmt(1, 1, 16_000, 2, 16)[..14]), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::Truncated);
+
+    // An extensible `fmt ` chunk without the sub-format
+    let file = wav(&[(b"fmt ", &fmt(0xFFFE, 1, 16_000, 2, 16)), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::Truncated);
+}
+
+#[test]
+fn unsupported_encoding() {
+    // IEEE float
+    let file = wav(&[(b"fmt ", &fmt(3, 1, 16_000, 4, 32)), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::UnsupportedEncoding(3));
+}
+
+#[test]
+fn unsupported_channels() {
+    for channels in [0, 3, 6] {
+        let file = wav(&[(b"fmt ", &fmt(1, channels, 16_000, 2 * channels, 16)), (b"data", &[0; 12])]);
+        assert_eq!(Wav::parse(&file).unwrap_err(), WavError::UnsupportedChannels(channels));
+    }
+}
+
+#[test]
+fn unsupported_bit_depth() {
+    for (tag, bits) in [(1, 24), (1, 4), (0x11, 16)] {
+        let file = wav(&[(b"fmt ", &fmt(tag, 1, 16_000, 3, bits)), (b"data", &[0; 3])]);
+        assert_eq!(Wav::parse(&file).unwrap_err(), WavError::UnsupportedBitDepth(bits));
+    }
+}
+
+#[test]
+fn invalid_block_align() {
+    // 16-bit stereo PCM frames are 4 bytes
+    let file = wav(&[(b"fmt ", &fmt(1, 2, 16_000, 2, 16)), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::InvalidBlockAlign);
+
+    // Stereo ADPCM blocks start with a header per channel
+    let file = wav(&[(b"fmt ", &fmt(0x11, 2, 16_000, 4, 4)), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::InvalidBlockAlign);
+}
+
+#[test]
+fn invalid_sample_rate() {
+    let file = wav(&[(b"fmt ", &fmt(1, 1, 0, 2, 16)), (b"data", &[0; 4])]);
+    assert_eq!(Wav::parse(&file).unwrap_err(), WavError::InvalidSampleRate);
+}
@@ -0,0 +1,247 @@
+//! IMA/DVI ADPCM codec
+//!
+//! IMA ADPCM stores each 16-bit sample as a 4-bit code, a quarter of the size of
+//! raw PCM, at a quality good enough for voice prompts and UI sounds. Decoding
+//! takes a few instructions per sample, so prompts are decoded while they play.
+//!
+//! WAV files in this format (format tag `0x11`) are handled by [`crate::wav`];
+//! create them with e.g. `ffmpeg -i prompt.wav -ac 1 -ar 16000 -c:a adpcm_ima_wav out.wav`
+//! or `sox prompt.wav -e ima-adpcm out.wav`.
+//!
+//! ## Block Layout
+//! WAV files split the data into blocks that can be decoded independently. Each
+//! block starts with a 4-byte header per channel (first sample as `i16`, step
+//! index, reserved byte), followed by groups of 4 bytes (8 samples) per channel,
+//! low nibble first.
+//!
+//! # Example
+//! ```ignore
+//! let mut state = ImaState::default();
+//! let sample = state.decode(code);
+//! ```
+
+/// Step sizes for the 89 step indices
+const STEP_TABLE: [i16; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408,
+    449, 494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066,
+    2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630,
+    9493, 10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794,
+    32767,
+];
+
+/// Step index change for each code magnitude
+const INDEX_TABLE: [i8; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Highest step index
+const MAX_INDEX: u8 = 88;
+
+/// Bytes per channel in a block header
+pub const BLOCK_HEADER_SIZE: usize = 4;
+
+/// Bytes per channel in a group of 8 samples
+const GROUP_SIZE: usize = 4;
+
+/// Codec state of one channel
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct ImaState {
+    /// Last sample
+    pub predictor: i16,
+    /// Index into the step table (0-88)
+    pub step_index: u8,
+}
+
+impl ImaState {
+    /// Create a state from a block header
+    pub fn new(predictor: i16, step_index: u8) -> Self {
+        Self {
+            predictor,
+            step_index: step_index.min(MAX_INDEX),
+        }
+    }
+
+    /// Decode a 4-bit code into the next sample
+    pub fn decode(&mut self, code: u8) -> i16 {
+        let step = STEP_TABLE[self.step_index as usize] as i32;
+
+        // diff = (magnitude + 0.5) * step / 4, computed the way the reference does
+        let mut diff = step >> 3;
+        if code & 4 != 0 {
+            diff += step;
+        }
+        if code & 2 != 0 {
+            diff += step >> 1;
+        }
+        if code & 1 != 0 {
+            diff += step >> 2;
+        }
+
+        let predictor = if code & 8 != 0 {
+            self.predictor as i32 - diff
+        } else {
+            self.predictor as i32 + diff
+        };
+        self.predictor = predictor.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+
+        let index = self.step_index as i8 + INDEX_TABLE[(code & 7) as usize];
+        self.step_index = index.clamp(0, MAX_INDEX as i8) as u8;
+
+        self.predictor
+    }
+
+    /// Encode a sample into a 4-bit code
+    ///
+    /// The state follows the decoder, so decoding the codes from the same
+    /// starting state reproduces the encoder's predictions exactly.
+    pub fn encode(&mut self, sample: i16) -> u8 {
+        let step = STEP_TABLE[self.step_index as usize] as i32;
+        let mut diff = sample as i32 - self.predictor as i32;
+
+        let mut code = 0;
+        if diff < 0 {
+            code = 8;
+            diff = -diff;
+        }
+        if diff >= step {
+            code |= 4;
+            diff -= step;
+        }
+        if diff >= step >> 1 {
+            code |= 2;
+            diff -= step >> 1;
+        }
+        if diff >= step >> 2 {
+            code |= 1;
+        }
+
+        self.decode(code);
+        code
+    }
+}
+
+/// Samples per channel in a block of `block_align` bytes
+pub fn samples_per_block(block_align: usize, channels: usize) -> usize {
+    let header = BLOCK_HEADER_SIZE * channels;
+    if channels == 0 || block_align < header {
+        return 0;
+    }
+    1 + (block_align - header) / (GROUP_SIZE * channels) * 8
+}
+
+/// Iterator decoding WAV-style IMA ADPCM blocks into `[left, right]` frames
+///
+/// Mono data is played on both channels. A short last block is decoded as far as
+/// it goes.
+#[derive(Debug, Clone)]
+pub struct BlockDecoder<'a> {
+    data: &'a [u8],
+    channels: usize,
+    block_align: usize,
+    /// Remainder of the current block
+    block: &'a [u8],
+    states: [ImaState; 2],
+    /// Decoded frames of the current group
+    frames: [[i16; 2]; 8],
+    pending: usize,
+    next: usize,
+}
+
+impl<'a> BlockDecoder<'a> {
+    /// Decode `data`, made of blocks of `block_align` bytes with `channels` (1 or 2) channels
+    ///
+    /// Returns `None` for other channel counts, or blocks too short for their header.
+    pub fn new(data: &'a [u8], channels: usize, block_align: usize) -> Option<Self> {
+        if !matches!(channels, 1 | 2) || block_align < BLOCK_HEADER_SIZE * channels {
+            return None;
+        }
+        Some(Self {
+            data,
+            channels,
+            block_align,
+            block: &[],
+            states: [ImaState::default(); 2],
+            frames: [[0; 2]; 8],
+            pending: 0,
+            next: 0,
+        })
+    }
+
+    /// Number of frames left
+    pub fn remaining(&self) -> usize {
+        let full = self.data.len() / self.block_align;
+        let partial = samples_per_block(self.data.len() % self.block_align, self.channels);
+        let group = self.block.len() / (GROUP_SIZE * self.channels) * 8;
+        full * samples_per_block(self.block_align, self.channels) + partial + group + self.pending
+    }
+
+    /// Start the next block, returning its first frame
+    fn start_block(&mut self) -> Option<[i16; 2]> {
+        let header = BLOCK_HEADER_SIZE * self.channels;
+        if self.data.len() < header {
+            return None;
+        }
+        let (block, rest) = self.data.split_at(self.block_align.min(self.data.len()));
+        self.data = rest;
+
+        for (ch, state) in self.states.iter_mut().take(self.channels).enumerate() {
+            let h = &block[BLOCK_HEADER_SIZE * ch..];
+            *state = ImaState::new(i16::from_le_bytes([h[0], h[1]]), h[2]);
+        }
+        self.block = &block[header..];
+        Some(self.frame(|ch| self.states[ch].predictor))
+    }
+
+    /// Decode the next group of 8 frames from the current block
+    fn decode_group(&mut self) -> bool {
+        let size = GROUP_SIZE * self.channels;
+        if self.block.len() < size {
+            return false;
+        }
+        let (group, rest) = self.block.split_at(size);
+        self.block = rest;
+
+        for ch in 0..self.channels {
+            let bytes = &group[GROUP_SIZE * ch..GROUP_SIZE * (ch + 1)];
+            for (i, byte) in bytes.iter().enumerate() {
+                self.frames[2 * i][ch] = self.states[ch].decode(byte & 0x0F);
+                self.frames[2 * i + 1][ch] = self.states[ch].decode(byte >> 4);
+            }
+        }
+        if self.channels == 1 {
+            for frame in &mut self.frames {
+                frame[1] = frame[0];
+            }
+        }
+        self.pending = 8;
+        self.next = 0;
+        true
+    }
+
+    /// Build a frame from per-channel samples
+    fn frame(&self, sample: impl Fn(usize) -> i16) -> [i16; 2] {
+        let left = sample(0);
+        let right = if self.channels == 2 { sample(1) } else { left };
+        [left, right]
+    }
+}
+
+impl Iterator for BlockDecoder<'_> {
+    type Item = [i16; 2];
+
+    fn next(&mut self) -> Option<[i16; 2]> {
+        if self.pending == 0 && !self.decode_group() {
+            return self.start_block();
+        }
+        let frame = self.frames[self.next];
+        self.next += 1;
+        self.pending -= 1;
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.remaining();
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for BlockDecoder<'_> {}
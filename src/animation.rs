@@ -0,0 +1,283 @@
+//! Non-blocking LED animations
+//!
+//! A [`LedAnimator`] holds one [`Pattern`] per [`Priority`] and a task renders the
+//! highest-priority active one. Application code only tells the animator what to
+//! show, so status indication doesn't need its own `Timer::after` loop.
+//!
+//! A higher-priority pattern temporarily overrides the lower ones: when it is stopped
+//! or its timeout expires, the background pattern resumes.
+//!
+//! LED masks use one bit per [`Led`] (see [`Led::mask`]).
+//!
+//! # Example
+//! ```ignore
+//! static ANIMATOR: LedAnimator = LedAnimator::new();
+//!
+//! let leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+//! spawner.spawn(led_task(&ANIMATOR, leds)).unwrap();
+//!
+//! ANIMATOR.play(Priority::Background, Pattern::Heartbeat { leds: Led::Green.mask() });
+//! ANIMATOR.play_for(Priority::Alert, Pattern::ErrorCode { leds: Led::Red.mask(), code: 3 }, Duration::from_secs(5));
+//! ```
+
+use core::cell::RefCell;
+use core::f32::consts::PI;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{with_timeout, Duration, Instant};
+use micromath::F32Ext;
+
+#[cfg(not(feature = "time-driver-tim4"))]
+use crate::leds::PwmLeds;
+use crate::leds::{Led, Leds};
+
+/// Interval between rendered frames
+const FRAME: Duration = Duration::from_millis(10);
+
+/// Blink length used by [`Pattern::ErrorCode`]
+const CODE_BLINK: Duration = Duration::from_millis(250);
+
+/// Pause between repetitions of [`Pattern::ErrorCode`]
+const CODE_PAUSE: Duration = Duration::from_millis(1500);
+
+/// A declarative LED pattern
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum Pattern {
+    /// All LEDs off
+    Off,
+    /// LEDs held on
+    Solid {
+        /// LED mask
+        leds: u8,
+    },
+    /// LEDs blink together
+    Blink {
+        /// LED mask
+        leds: u8,
+        /// Time on per cycle
+        on: Duration,
+        /// Time off per cycle
+        off: Duration,
+    },
+    /// LEDs fade smoothly up and down
+    ///
+    /// Only PWM outputs show intermediate brightness; plain [`Leds`] see a slow blink.
+    Breathe {
+        /// LED mask
+        leds: u8,
+        /// Length of one full breath
+        period: Duration,
+    },
+    /// A single light rotating around the compass-rose layout
+    Chase {
+        /// Time each LED stays lit
+        step: Duration,
+        /// Rotate clockwise (orange, blue, red, green) or counter-clockwise
+        clockwise: bool,
+    },
+    /// Double pulse once per second
+    Heartbeat {
+        /// LED mask
+        leds: u8,
+    },
+    /// `code` short blinks followed by a pause, repeated
+    ErrorCode {
+        /// LED mask
+        leds: u8,
+        /// Number of blinks (1-255)
+        code: u8,
+    },
+}
+
+impl Pattern {
+    /// Brightness of each LED (0-100, indexed like [`Led`]) at time `t` into the pattern
+    pub fn frame(&self, t: Duration) -> [u8; 4] {
+        let ms = t.as_millis();
+        match *self {
+            Pattern::Off => [0; 4],
+            Pattern::Solid { leds } => from_mask(leds, 100),
+            Pattern::Blink { leds, on, off } => {
+                let period = (on + off).as_millis().max(1);
+                let lit = ms % period < on.as_millis();
+                from_mask(leds, if lit { 100 } else { 0 })
+            }
+            Pattern::Breathe { leds, period } => {
+                let period = period.as_millis().max(1);
+                let phase = (ms % period) as f32 / period as f32;
+                let level = (1.0 - (2.0 * PI * phase).cos()) / 2.0;
+                from_mask(leds, (level * 100.0).round() as u8)
+            }
+            Pattern::Chase { step, clockwise } => {
+                let index = (ms / step.as_millis().max(1)) as usize % Led::CLOCKWISE.len();
+                let index = if clockwise {
+                    index
+                } else {
+                    (Led::CLOCKWISE.len() - index) % Led::CLOCKWISE.len()
+                };
+                from_mask(Led::CLOCKWISE[index].mask(), 100)
+            }
+            Pattern::Heartbeat { leds } => {
+                let lit = matches!(ms % 1000, 0..100 | 200..300);
+                from_mask(leds, if lit { 100 } else { 0 })
+            }
+            Pattern::ErrorCode { leds, code } => {
+                let blink = CODE_BLINK.as_millis();
+                let blinks = 2 * blink * code.max(1) as u64;
+                let pos = ms % (blinks + CODE_PAUSE.as_millis());
+                let lit = pos < blinks && (pos / blink).is_multiple_of(2);
+                from_mask(leds, if lit { 100 } else { 0 })
+            }
+        }
+    }
+}
+
+/// Set `brightness` on every LED in `mask`
+fn from_mask(mask: u8, brightness: u8) -> [u8; 4] {
+    Led::ALL.map(|led| if mask & led.mask() != 0 { brightness } else { 0 })
+}
+
+/// Pattern priority; higher priorities override lower ones while active
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub enum Priority {
+    /// Idle or ambient pattern
+    Background = 0,
+    /// Application status (connecting, recording, ...)
+    Status = 1,
+    /// Errors and user alerts
+    Alert = 2,
+}
+
+/// LEDs that can display an animation frame
+pub trait LedOutput {
+    /// Show a frame (brightness 0-100 per LED, indexed like [`Led`])
+    fn show(&mut self, frame: [u8; 4]);
+}
+
+/// On/off LEDs light at 50% brightness or above
+impl LedOutput for Leds<'_> {
+    fn show(&mut self, frame: [u8; 4]) {
+        for led in Led::ALL {
+            self[led].set_level((frame[led as usize] >= 50).into());
+        }
+    }
+}
+
+#[cfg(not(feature = "time-driver-tim4"))]
+impl LedOutput for PwmLeds<'_> {
+    fn show(&mut self, frame: [u8; 4]) {
+        for led in Led::ALL {
+            if self.brightness(led) != frame[led as usize] {
+                self.set_brightness(led, frame[led as usize]);
+            }
+        }
+    }
+}
+
+/// A pattern playing on one priority layer
+#[derive(Clone, Copy)]
+struct Layer {
+    pattern: Pattern,
+    started: Instant,
+    until: Option<Instant>,
+}
+
+/// Pattern state shared between the application and the LED task
+pub struct LedAnimator {
+    layers: Mutex<CriticalSectionRawMutex, RefCell<[Option<Layer>; 3]>>,
+    changed: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Default for LedAnimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedAnimator {
+    /// Create an animator with no patterns (LEDs off)
+    pub const fn new() -> Self {
+        Self {
+            layers: Mutex::new(RefCell::new([None; 3])),
+            changed: Signal::new(),
+        }
+    }
+
+    /// Play a pattern on a priority layer until it is stopped or replaced
+    pub fn play(&self, priority: Priority, pattern: Pattern) {
+        self.set(priority, pattern, None);
+    }
+
+    /// Play a pattern on a priority layer for a limited time
+    ///
+    /// # Arguments
+    /// * `priority` - Layer to play on
+    /// * `pattern` - Pattern to play
+    /// * `duration` - How long the pattern overrides lower layers
+    pub fn play_for(&self, priority: Priority, pattern: Pattern, duration: Duration) {
+        self.set(priority, pattern, Some(Instant::now() + duration));
+    }
+
+    /// Stop the pattern on a priority layer
+    pub fn stop(&self, priority: Priority) {
+        self.layers.lock(|layers| layers.borrow_mut()[priority as usize] = None);
+        self.changed.signal(());
+    }
+
+    /// Pattern currently shown, if any
+    pub fn current(&self) -> Option<Pattern> {
+        self.top(Instant::now()).map(|layer| layer.pattern)
+    }
+
+    /// Render patterns onto `leds` forever
+    ///
+    /// Use this from your own task for outputs other than [`Leds`], e.g. [`PwmLeds`].
+    pub async fn run(&self, leds: &mut impl LedOutput) -> ! {
+        loop {
+            let now = Instant::now();
+            let frame = match self.top(now) {
+                Some(layer) => layer.pattern.frame(now - layer.started),
+                None => [0; 4],
+            };
+            leds.show(frame);
+
+            // Wake early when a pattern changes
+            let _ = with_timeout(FRAME, self.changed.wait()).await;
+        }
+    }
+
+    fn set(&self, priority: Priority, pattern: Pattern, until: Option<Instant>) {
+        let layer = Layer {
+            pattern,
+            started: Instant::now(),
+            until,
+        };
+        self.layers.lock(|layers| layers.borrow_mut()[priority as usize] = Some(layer));
+        self.changed.signal(());
+    }
+
+    /// Highest-priority active layer, dropping expired ones
+    fn top(&self, now: Instant) -> Option<Layer> {
+        self.layers.lock(|layers| {
+            let mut layers = layers.borrow_mut();
+            for slot in layers.iter_mut() {
+                if matches!(slot, Some(Layer { until: Some(until), .. }) if *until <= now) {
+                    *slot = None;
+                }
+            }
+            layers.iter().rev().flatten().next().copied()
+        })
+    }
+}
+
+/// Embassy task rendering `animator` onto the on/off user LEDs
+///
+/// # Example
+/// ```ignore
+/// spawner.spawn(led_task(&ANIMATOR, leds)).unwrap();
+/// ```
+#[embassy_executor::task]
+pub async fn led_task(animator: &'static LedAnimator, mut leds: Leds<'static>) {
+    animator.run(&mut leds).await
+}
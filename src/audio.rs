@@ -19,13 +19,81 @@
 //! - I2S_WS: PA4
 //! - RESET: PD4
 //!
-//! Note: Full implementation requires complex I2S setup and audio processing
+//! The DAC needs a master clock (MCLK) for anything but register access, including
+//! the beep generator. [`AudioClock`] provides it from I2S3 when no audio is streamed,
+//! and [`PcmOutput`](crate::playback::PcmOutput) streams audio.
 
-use defmt::{debug, info};
+use defmt::{debug, info, warn};
 use embassy_stm32::gpio::{Level, Output, Speed};
 use embassy_stm32::i2c::{Config as I2cConfig, I2c};
+use embassy_stm32::i2s::{self, I2S};
+use embassy_stm32::pac::{self, spi::vals as spi_vals};
+use embassy_stm32::peripherals::{DMA1_CH5, PA4, PC10, PC12, PC7, SPI3};
+use embassy_stm32::time::Hertz;
 use embassy_stm32::{i2c, Peri};
-use embassy_time::Duration;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, RawMutex};
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Timer};
+use micromath::F32Ext;
+
+use crate::playback::i2s_clock;
+
+/// BEEP_TONE_CFG beep mode bits
+const BEEP_MODE_MASK: u8 = 0xC0;
+
+/// PLAYBACK_CTL2 headphone and speaker mute bits
+const PLAYBACK_MUTE_MASK: u8 = 0xF0;
+
+/// MISC_CTL digital soft ramp and zero cross bits
+const MISC_DIGSFT: u8 = 0x02;
+const MISC_DIGZC: u8 = 0x01;
+
+/// BEEP_TONE_CFG tone control bits: treble corner, bass corner, enable
+const TONE_CFG_MASK: u8 = 0x1F;
+const TONE_CFG_TCEN: u8 = 0x01;
+
+/// PLAYBACK_CTL1 PCM polarity inversion bits
+const PLAYBACK_INV_PCMB: u8 = 0x08;
+const PLAYBACK_INV_PCMA: u8 = 0x04;
+
+/// MISC_CTL de-emphasis bit
+const MISC_DEEMPHASIS: u8 = 0x04;
+
+/// LIMIT_CTL1 soft ramp and zero cross disable bits
+const LIMIT_SR_DIS: u8 = 0x02;
+const LIMIT_ZC_DIS: u8 = 0x01;
+
+/// LIMIT_CTL2 enable bits
+const LIMIT_ENABLE: u8 = 0x80;
+const LIMIT_ALL: u8 = 0x40;
+
+/// MISC_CTL passthrough enable and mute bits
+const MISC_PASSTHRU_MASK: u8 = 0xC0;
+const MISC_PASSMUTE_MASK: u8 = 0x30;
+
+/// STATUS error flags
+const STATUS_SPCLKERR: u8 = 0x40;
+const STATUS_DSPAOVFL: u8 = 0x20;
+const STATUS_DSPBOVFL: u8 = 0x10;
+const STATUS_PCMAOVFL: u8 = 0x08;
+const STATUS_PCMBOVFL: u8 = 0x04;
+
+/// SPEAKER_STATUS short circuit and SPKR/HP pin bits
+const SPEAKER_STATUS_SPKASHRT: u8 = 0x20;
+const SPEAKER_STATUS_SPKBSHRT: u8 = 0x10;
+const SPEAKER_STATUS_SPKR_HP: u8 = 0x08;
+
+/// Polling interval of [`output_monitor_task`]
+const OUTPUT_MONITOR_INTERVAL: Duration = Duration::from_millis(250);
+
+/// PCM_VOL mute bit
+const PCM_MUTE: u8 = 0x80;
+
+/// HEADPHONE_VOL/SPEAKER_VOL value that mutes the channel
+const OUTPUT_VOL_MUTE: u8 = 0x01;
+
+/// Sample rate of [`AudioClock`]
+const SAMPLE_RATE: u32 = 48_000;
 
 /// CS43L22 I2C address
 const CS43L22_ADDR: u8 = 0x4A; // 0x94 >> 1
@@ -74,7 +142,7 @@ mod regs {
 ///
 /// The CS43L22 can drive both headphones and speakers.
 /// Auto mode will detect which output is connected.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
 pub enum OutputDevice {
     /// Auto-detect (default) - Automatically selects based on jack detection
     Auto = 0,
@@ -86,33 +154,675 @@ pub enum OutputDevice {
     Both = 3,
 }
 
-/// Volume level (0-100)
+/// Master volume, -102 to +12 dB in 0.5 dB steps
 ///
-/// Represents the audio output volume as a percentage.
-/// The value is automatically clamped to the valid range.
-#[derive(Debug, Clone, Copy)]
-pub struct Volume(pub u8);
+/// The MASTER_VOL registers are two's complement half-dB steps, wrapping around
+/// from +12 dB (0x18) to -102 dB (0x34).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Volume(i16);
 
 impl Volume {
-    /// Create a new volume level (clamped to 0-100)
+    /// Lowest volume (-102 dB)
+    pub const MIN: Self = Self(-204);
+    /// Highest volume (+12 dB)
+    pub const MAX: Self = Self(24);
+
+    /// Create a volume from a percentage (clamped to 0-100)
+    ///
+    /// 100% is 0 dB and 0% is [`Volume::MIN`]. In between, the level follows
+    /// 40·log10(level / 100), so 50% sounds about half as loud as 100%.
     ///
     /// # Arguments
     /// * `level` - Volume level as percentage (0-100)
     ///
     /// # Example
-    /// ```no_run
-    /// let vol = Volume::new(75); // 75% volume
+    /// ```ignore
+    /// let vol = Volume::new(75); // 75% volume, -5 dB
     /// ```
     pub fn new(level: u8) -> Self {
-        Self(level.min(100))
+        match level.min(100) {
+            0 => Self::MIN,
+            level => Self::from_db(40.0 * (level as f32 / 100.0).log10()),
+        }
     }
     
-    /// Convert to DAC register value
+    /// Create a volume in dB, clamped to -102..=+12 and rounded to 0.5 dB
     ///
-    /// Internal method to convert percentage to CS43L22 register format
-    fn to_dac_value(&self) -> u8 {
-        // Convert 0-100 to DAC range (0x00-0xFF)
-        ((self.0 as u16 * 255) / 100) as u8
+    /// # Example
+    /// ```ignore
+    /// let vol = Volume::from_db(-20.0);
+    /// ```
+    pub fn from_db(db: f32) -> Self {
+        Self(half_db_steps(db, -102.0, 12.0))
+    }
+    
+    /// Volume in dB
+    pub fn db(&self) -> f32 {
+        self.0 as f32 / 2.0
+    }
+    
+    /// This volume lowered by `db` (clamped at [`Volume::MIN`])
+    fn attenuated(self, db: f32) -> Self {
+        Self::from_db(self.db() - db)
+    }
+    
+    /// MASTER_VOL register value
+    fn register_value(self) -> u8 {
+        self.0 as u8
+    }
+}
+
+/// Left/right balance
+///
+/// Attenuates the master volume of one channel; the other keeps the set volume.
+#[derive(Debug, Default, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Balance(f32);
+
+impl Balance {
+    /// Both channels at the master volume
+    pub const CENTER: Self = Self(0.0);
+    
+    /// Create a balance from -1.0 (left only) to 1.0 (right only)
+    ///
+    /// The quieter channel's amplitude is scaled by 1 - |balance|.
+    ///
+    /// # Example
+    /// ```ignore
+    /// dac.set_balance(Balance::new(0.5)); // left channel at half amplitude
+    /// ```
+    pub fn new(balance: f32) -> Self {
+        Self(balance.clamp(-1.0, 1.0))
+    }
+    
+    /// Balance from -1.0 (left) to 1.0 (right)
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+    
+    /// Attenuation of the left and right channel in dB
+    fn attenuation(&self) -> (f32, f32) {
+        let db = |gain: f32| if gain > 0.0 { -20.0 * gain.log10() } else { f32::INFINITY };
+        if self.0 > 0.0 {
+            (db(1.0 - self.0), 0.0)
+        } else {
+            (0.0, db(1.0 + self.0))
+        }
+    }
+}
+
+/// How volume changes take effect
+///
+/// Soft ramp steps the volume in 0.125 dB increments, zero cross waits for a zero
+/// crossing of the signal. Both avoid audible clicks. Applies to the digital
+/// volumes (MISC_CTL) and to the analog passthrough volume (ANALOG_ZC_SR).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct VolumeRamp {
+    /// Ramp volume changes gradually
+    pub soft_ramp: bool,
+    /// Apply volume changes on a signal zero crossing
+    pub zero_cross: bool,
+}
+
+impl Default for VolumeRamp {
+    /// Soft ramp only
+    fn default() -> Self {
+        Self {
+            soft_ramp: true,
+            zero_cross: false,
+        }
+    }
+}
+
+/// Bass shelving filter corner frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BassCorner {
+    /// 50 Hz
+    Hz50 = 0,
+    /// 100 Hz
+    Hz100 = 1,
+    /// 200 Hz
+    Hz200 = 2,
+    /// 250 Hz
+    Hz250 = 3,
+}
+
+/// Treble shelving filter corner frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum TrebleCorner {
+    /// 5 kHz
+    Khz5 = 0,
+    /// 7 kHz
+    Khz7 = 1,
+    /// 10 kHz
+    Khz10 = 2,
+    /// 15 kHz
+    Khz15 = 3,
+}
+
+/// Bass and treble tone control
+///
+/// Gains range from -10.5 to +12 dB in 1.5 dB steps.
+///
+/// # Example
+/// ```ignore
+/// let tone = ToneControl { bass_db: 6.0, ..Default::default() };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct ToneControl {
+    /// Bass gain in dB
+    pub bass_db: f32,
+    /// Treble gain in dB
+    pub treble_db: f32,
+    /// Bass corner frequency
+    pub bass_corner: BassCorner,
+    /// Treble corner frequency
+    pub treble_corner: TrebleCorner,
+}
+
+impl Default for ToneControl {
+    /// Flat response, 100 Hz and 7 kHz corners
+    fn default() -> Self {
+        Self {
+            bass_db: 0.0,
+            treble_db: 0.0,
+            bass_corner: BassCorner::Hz100,
+            treble_corner: TrebleCorner::Khz7,
+        }
+    }
+}
+
+impl ToneControl {
+    /// TONE_CTL gain field: 0 is +12 dB, counting down in 1.5 dB steps
+    fn gain_value(db: f32) -> u8 {
+        (8.0 - (db.clamp(-10.5, 12.0) / 1.5).round()) as u8
+    }
+}
+
+/// Limiter threshold below full scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum LimiterThreshold {
+    /// 0 dB
+    Db0 = 0,
+    /// -3 dB
+    DbMinus3 = 1,
+    /// -6 dB
+    DbMinus6 = 2,
+    /// -9 dB
+    DbMinus9 = 3,
+    /// -12 dB
+    DbMinus12 = 4,
+    /// -18 dB
+    DbMinus18 = 5,
+    /// -24 dB
+    DbMinus24 = 6,
+    /// -30 dB
+    DbMinus30 = 7,
+}
+
+/// Peak limiter
+///
+/// The limiter lowers the volume when the signal exceeds `max` and raises it
+/// again once the signal falls below `cushion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Limiter {
+    /// Threshold at which the volume is lowered
+    pub max: LimiterThreshold,
+    /// Threshold below which the volume is restored (at or below `max`)
+    pub cushion: LimiterThreshold,
+    /// Attack rate, 0 (fastest) to 63 (slowest)
+    pub attack_rate: u8,
+    /// Release rate, 0 (fastest) to 63 (slowest)
+    pub release_rate: u8,
+    /// Limit both channels when either one exceeds the threshold
+    pub both_channels: bool,
+    /// Apply the limiter's volume changes with soft ramp
+    pub soft_ramp: bool,
+    /// Apply the limiter's volume changes on zero crossings
+    pub zero_cross: bool,
+}
+
+impl Default for Limiter {
+    /// Limit at 0 dB, release below -3 dB, fastest attack and slowest release
+    fn default() -> Self {
+        Self {
+            max: LimiterThreshold::Db0,
+            cushion: LimiterThreshold::DbMinus3,
+            attack_rate: 0,
+            release_rate: 63,
+            both_channels: true,
+            soft_ramp: true,
+            zero_cross: true,
+        }
+    }
+}
+
+/// Routing of the PCM channels to the outputs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ChannelMix {
+    /// Left to left, right to right
+    #[default]
+    Stereo,
+    /// Left to right, right to left
+    Swap,
+    /// (L+R)/2 on both channels
+    Mono,
+    /// Left on both channels
+    Left,
+    /// Right on both channels
+    Right,
+}
+
+impl ChannelMix {
+    /// CHANNEL_MIXER value: PCMASWP in bits 7:6, PCMBSWP in bits 5:4
+    fn register_value(self) -> u8 {
+        // 00 = own channel, 01 = (A+B)/2, 11 = other channel
+        let (a, b) = match self {
+            ChannelMix::Stereo => (0b00, 0b00),
+            ChannelMix::Swap => (0b11, 0b11),
+            ChannelMix::Mono => (0b01, 0b01),
+            ChannelMix::Left => (0b00, 0b11),
+            ChannelMix::Right => (0b11, 0b00),
+        };
+        a << 6 | b << 4
+    }
+}
+
+/// Digital signal processing in the DAC
+///
+/// Applied with [`CS43L22::set_audio_processing`]. The default bypasses everything.
+///
+/// # Example
+/// ```ignore
+/// dac.set_audio_processing(AudioProcessing {
+///     tone: Some(ToneControl { bass_db: 6.0, treble_db: -3.0, ..Default::default() }),
+///     limiter: Some(Limiter::default()),
+///     mix: ChannelMix::Mono,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, defmt::Format)]
+pub struct AudioProcessing {
+    /// Bass and treble tone control (`None` bypasses it)
+    pub tone: Option<ToneControl>,
+    /// Peak limiter (`None` disables it)
+    pub limiter: Option<Limiter>,
+    /// Channel routing
+    pub mix: ChannelMix,
+    /// 44.1 kHz de-emphasis filter
+    pub de_emphasis: bool,
+    /// Invert the polarity of the left channel
+    pub invert_left: bool,
+    /// Invert the polarity of the right channel
+    pub invert_right: bool,
+}
+
+/// Error flags from the STATUS register
+///
+/// The flags latch until read; reading clears them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct DacStatus {
+    /// MCLK, SCLK and LRCK are not in a valid ratio
+    pub serial_clock_error: bool,
+    /// Overflow in the left channel's DSP engine
+    pub dsp_overflow_left: bool,
+    /// Overflow in the right channel's DSP engine
+    pub dsp_overflow_right: bool,
+    /// Overflow in the left channel's PCM data path
+    pub pcm_overflow_left: bool,
+    /// Overflow in the right channel's PCM data path
+    pub pcm_overflow_right: bool,
+}
+
+impl DacStatus {
+    /// Check if any flag is set
+    pub fn any(&self) -> bool {
+        self.serial_clock_error
+            || self.dsp_overflow_left
+            || self.dsp_overflow_right
+            || self.pcm_overflow_left
+            || self.pcm_overflow_right
+    }
+}
+
+/// Speaker amplifier and headphone-detect state from the SPEAKER_STATUS register
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct SpeakerStatus {
+    /// Short circuit on the left speaker output
+    pub short_left: bool,
+    /// Short circuit on the right speaker output
+    pub short_right: bool,
+    /// Headphones plugged in (SPKR/HP pin low)
+    pub headphone_detected: bool,
+}
+
+/// Analog input of the CS43L22
+///
+/// On the Discovery board, AIN1x shares the I2S3 WS line (PA4) and AIN4x carries
+/// the microphone's PDM output through a low-pass filter (PC3). AIN2x and AIN3x are
+/// not connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum AnalogInput {
+    /// AIN1A/AIN1B
+    Ain1 = 0x01,
+    /// AIN2A/AIN2B
+    Ain2 = 0x02,
+    /// AIN3A/AIN3B
+    Ain3 = 0x04,
+    /// AIN4A/AIN4B, the filtered microphone signal
+    Ain4 = 0x08,
+}
+
+/// Analog passthrough settings
+///
+/// Routes the analog inputs straight to the headphone and speaker amplifiers,
+/// bypassing the DAC and its digital processing.
+///
+/// # Example
+/// ```ignore
+/// // Listen to the microphone
+/// dac.enable_passthrough(Passthrough::new(AnalogInput::Ain4));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Passthrough {
+    /// Input for the left channel
+    pub left: AnalogInput,
+    /// Input for the right channel
+    pub right: AnalogInput,
+    /// Passthrough gain in dB, -60 to +12 in 0.5 dB steps
+    pub gain_db: f32,
+    /// Keep the PCM (I2S) audio and beeps mixed in; otherwise the PCM input is muted
+    pub mix_pcm: bool,
+}
+
+impl Passthrough {
+    /// The same input on both channels at 0 dB, PCM audio muted
+    pub const fn new(input: AnalogInput) -> Self {
+        Self {
+            left: input,
+            right: input,
+            gain_db: 0.0,
+            mix_pcm: false,
+        }
+    }
+}
+
+/// HEADPHONE_VOL/SPEAKER_VOL register value, -96 to 0 dB
+fn output_volume_register(db: f32) -> u8 {
+    if db < -96.0 {
+        OUTPUT_VOL_MUTE
+    } else {
+        half_db_steps(db, -96.0, 0.0) as u8
+    }
+}
+
+/// Half-dB steps of `db`, clamped to `min..=max`
+fn half_db_steps(db: f32, min: f32, max: f32) -> i16 {
+    (db.clamp(min, max) * 2.0).round() as i16
+}
+
+/// Beep generator pitch
+///
+/// The 16 notes of the CS43L22 beep generator, at a 48 kHz sample rate. The pitch
+/// scales with the actual sample rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BeepFrequency {
+    /// 260.87 Hz
+    C4 = 0,
+    /// 521.74 Hz
+    C5 = 1,
+    /// 585.37 Hz
+    D5 = 2,
+    /// 666.67 Hz
+    E5 = 3,
+    /// 705.88 Hz
+    F5 = 4,
+    /// 774.19 Hz
+    G5 = 5,
+    /// 888.89 Hz
+    A5 = 6,
+    /// 1000.00 Hz
+    B5 = 7,
+    /// 1043.48 Hz
+    C6 = 8,
+    /// 1200.00 Hz
+    D6 = 9,
+    /// 1333.33 Hz
+    E6 = 10,
+    /// 1411.76 Hz
+    F6 = 11,
+    /// 1600.00 Hz
+    G6 = 12,
+    /// 1714.29 Hz
+    A6 = 13,
+    /// 2000.00 Hz
+    B6 = 14,
+    /// 2181.82 Hz
+    C7 = 15,
+}
+
+impl BeepFrequency {
+    /// All pitches, from lowest to highest
+    pub const ALL: [BeepFrequency; 16] = [
+        BeepFrequency::C4,
+        BeepFrequency::C5,
+        BeepFrequency::D5,
+        BeepFrequency::E5,
+        BeepFrequency::F5,
+        BeepFrequency::G5,
+        BeepFrequency::A5,
+        BeepFrequency::B5,
+        BeepFrequency::C6,
+        BeepFrequency::D6,
+        BeepFrequency::E6,
+        BeepFrequency::F6,
+        BeepFrequency::G6,
+        BeepFrequency::A6,
+        BeepFrequency::B6,
+        BeepFrequency::C7,
+    ];
+
+    /// Pitch in Hz at a 48 kHz sample rate
+    pub fn hz(&self) -> f32 {
+        match self {
+            BeepFrequency::C4 => 260.87,
+            BeepFrequency::C5 => 521.74,
+            BeepFrequency::D5 => 585.37,
+            BeepFrequency::E5 => 666.67,
+            BeepFrequency::F5 => 705.88,
+            BeepFrequency::G5 => 774.19,
+            BeepFrequency::A5 => 888.89,
+            BeepFrequency::B5 => 1000.0,
+            BeepFrequency::C6 => 1043.48,
+            BeepFrequency::D6 => 1200.0,
+            BeepFrequency::E6 => 1333.33,
+            BeepFrequency::F6 => 1411.76,
+            BeepFrequency::G6 => 1600.0,
+            BeepFrequency::A6 => 1714.29,
+            BeepFrequency::B6 => 2000.0,
+            BeepFrequency::C7 => 2181.82,
+        }
+    }
+
+    /// The available pitch closest to `hz`
+    pub fn nearest(hz: f32) -> Self {
+        let mut best = BeepFrequency::C4;
+        for frequency in Self::ALL {
+            if (frequency.hz() - hz).abs() < (best.hz() - hz).abs() {
+                best = frequency;
+            }
+        }
+        best
+    }
+}
+
+/// Beep duration in single and multiple mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BeepOnTime {
+    /// About 86 ms
+    Ms86 = 0,
+    /// About 430 ms
+    Ms430 = 1,
+    /// About 780 ms
+    Ms780 = 2,
+    /// About 1.20 s
+    Ms1200 = 3,
+    /// About 1.50 s
+    Ms1500 = 4,
+    /// About 1.80 s
+    Ms1800 = 5,
+    /// About 2.20 s
+    Ms2200 = 6,
+    /// About 2.50 s
+    Ms2500 = 7,
+    /// About 2.80 s
+    Ms2800 = 8,
+    /// About 3.20 s
+    Ms3200 = 9,
+    /// About 3.50 s
+    Ms3500 = 10,
+    /// About 3.80 s
+    Ms3800 = 11,
+    /// About 4.20 s
+    Ms4200 = 12,
+    /// About 4.50 s
+    Ms4500 = 13,
+    /// About 4.80 s
+    Ms4800 = 14,
+    /// About 5.20 s
+    Ms5200 = 15,
+}
+
+impl BeepOnTime {
+    /// Approximate duration at a 48 kHz sample rate
+    pub fn duration(&self) -> Duration {
+        const MS: [u64; 16] = [
+            86, 430, 780, 1200, 1500, 1800, 2200, 2500, 2800, 3200, 3500, 3800, 4200, 4500, 4800, 5200,
+        ];
+        Duration::from_millis(MS[*self as usize])
+    }
+}
+
+/// Pause between beeps in multiple mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BeepOffTime {
+    /// About 1.23 s
+    Ms1230 = 0,
+    /// About 2.58 s
+    Ms2580 = 1,
+    /// About 3.90 s
+    Ms3900 = 2,
+    /// About 5.20 s
+    Ms5200 = 3,
+    /// About 6.60 s
+    Ms6600 = 4,
+    /// About 8.05 s
+    Ms8050 = 5,
+    /// About 9.35 s
+    Ms9350 = 6,
+    /// About 10.80 s
+    Ms10800 = 7,
+}
+
+impl BeepOffTime {
+    /// Approximate duration at a 48 kHz sample rate
+    pub fn duration(&self) -> Duration {
+        const MS: [u64; 8] = [1230, 2580, 3900, 5200, 6600, 8050, 9350, 10800];
+        Duration::from_millis(MS[*self as usize])
+    }
+}
+
+/// Beep generator volume, -56 to +6 dB in 2 dB steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct BeepVolume(i8);
+
+impl BeepVolume {
+    /// Lowest volume (-56 dB)
+    pub const MIN: Self = Self(-56);
+    /// Highest volume (+6 dB)
+    pub const MAX: Self = Self(6);
+
+    /// Volume in dB, clamped to -56..=+6 and rounded down to a 2 dB step
+    pub fn from_db(db: i8) -> Self {
+        let db = db.clamp(-56, 6);
+        Self(db - (db + 56) % 2)
+    }
+
+    /// Volume in dB
+    pub fn db(&self) -> i8 {
+        self.0
+    }
+
+    /// BEEPVOL field value: 0 is -6 dB, counting up in 2 dB steps and wrapping at +6 dB
+    fn register_value(self) -> u8 {
+        ((((self.0 + 56) / 2) as u8) + 7) % 32
+    }
+}
+
+impl Default for BeepVolume {
+    /// -6 dB
+    fn default() -> Self {
+        Self(-6)
+    }
+}
+
+/// Beep generator mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BeepMode {
+    /// One beep of the on time
+    Single = 1,
+    /// Beeps of the on time, separated by the off time, until stopped
+    Multiple = 2,
+    /// A continuous tone until stopped
+    Continuous = 3,
+}
+
+/// Beep generator settings
+///
+/// # Example
+/// ```ignore
+/// dac.start_beep(Beep::multiple(BeepFrequency::A5, BeepOnTime::Ms86, BeepOffTime::Ms1230));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Beep {
+    /// Pitch
+    pub frequency: BeepFrequency,
+    /// Beep duration (single and multiple mode)
+    pub on_time: BeepOnTime,
+    /// Pause between beeps (multiple mode)
+    pub off_time: BeepOffTime,
+    /// Mode
+    pub mode: BeepMode,
+}
+
+impl Beep {
+    /// One beep
+    pub const fn single(frequency: BeepFrequency, on_time: BeepOnTime) -> Self {
+        Self {
+            frequency,
+            on_time,
+            off_time: BeepOffTime::Ms1230,
+            mode: BeepMode::Single,
+        }
+    }
+
+    /// Repeated beeps
+    pub const fn multiple(frequency: BeepFrequency, on_time: BeepOnTime, off_time: BeepOffTime) -> Self {
+        Self {
+            frequency,
+            on_time,
+            off_time,
+            mode: BeepMode::Multiple,
+        }
+    }
+
+    /// A continuous tone
+    pub const fn continuous(frequency: BeepFrequency) -> Self {
+        Self {
+            frequency,
+            on_time: BeepOnTime::Ms86,
+            off_time: BeepOffTime::Ms1230,
+            mode: BeepMode::Continuous,
+        }
     }
 }
 
@@ -122,18 +832,14 @@ impl Volume {
 /// 
 /// ## Current Implementation
 /// - I2C control interface for configuration
-/// - Volume control and muting
+/// - Master volume in dB with balance, headphone, speaker and PCM volumes
+/// - Muting
+/// - Tone control, limiter and channel mixer ([`AudioProcessing`])
+/// - Fault flags and headphone detection ([`output_monitor_task`])
+/// - Analog passthrough from the AIN inputs ([`Passthrough`])
 /// - Output device selection (speaker/headphone)
-/// - Basic beep tone generation
+/// - Beep generator (needs an [`AudioClock`] or a running I2S stream)
 /// 
-/// ## Limitations
-/// This driver currently only provides I2C control functionality.
-/// Full audio playback would require:
-/// - I2S peripheral configuration for audio data streaming
-/// - DMA setup for continuous audio transfer
-/// - Audio PLL configuration for precise timing
-/// - Sample rate and format configuration
-///
 /// ## Shared I2C Bus
 /// Note that this device shares the I2C bus with the LSM303DLHC compass.
 /// Ensure proper coordination when using both devices.
@@ -141,8 +847,18 @@ pub struct CS43L22<'a> {
     i2c: I2c<'a, embassy_stm32::mode::Blocking, i2c::Master>,
     #[allow(dead_code)]
     reset: Output<'a>,
+    /// Output device asked for with [`set_output`](Self::set_output)
+    requested_output: OutputDevice,
+    /// Output device currently routed, differs from the request in `Auto`
     output: OutputDevice,
     volume: Volume,
+    balance: Balance,
+    beep_volume: BeepVolume,
+    clock: Option<AudioClock<'a>>,
+    beeping: bool,
+    passthrough: bool,
+    /// PCM mute bits of both channels from before passthrough was enabled
+    pcm_mute_before_passthrough: [bool; 2],
 }
 
 impl<'a> CS43L22<'a> {
@@ -168,8 +884,15 @@ impl<'a> CS43L22<'a> {
         let mut dac = Self {
             i2c,
             reset,
+            requested_output: OutputDevice::Auto,
             output: OutputDevice::Auto,
             volume: Volume::new(70),
+            balance: Balance::CENTER,
+            beep_volume: BeepVolume::default(),
+            clock: None,
+            beeping: false,
+            passthrough: false,
+            pcm_mute_before_passthrough: [false; 2],
         };
         
         // Initialize the DAC
@@ -194,12 +917,8 @@ impl<'a> CS43L22<'a> {
         self.write_register(regs::INTERFACE_CTL1, 0x04);
         
         // Set initial volume
-        let vol = self.volume.to_dac_value();
-        self.write_register(regs::MASTER_VOL_A, vol);
-        self.write_register(regs::MASTER_VOL_B, vol);
-        
-        // Configure output path
-        self.write_register(regs::ANALOG_ZC_SR, 0x00);
+        self.write_master_volume();
+        self.set_volume_ramp(VolumeRamp::default());
         info!("CS43L22 initialized");
     }
     
@@ -227,91 +946,465 @@ impl<'a> CS43L22<'a> {
     
     /// Set the output device
     ///
-    /// Configures which audio output(s) are active.
+    /// Configures which audio output(s) are active. With [`OutputDevice::Auto`],
+    /// the DAC itself switches between headphone and speaker on the jack detect
+    /// pin, and [`update_output`](Self::update_output) reports the changes; other
+    /// selections are kept as they are.
     ///
     /// # Arguments
     /// * `output` - The desired output configuration
     ///
     /// # Example
-    /// ```no_run
+    /// ```ignore
     /// dac.set_output(OutputDevice::Headphone);
     /// ```
     pub fn set_output(&mut self, output: OutputDevice) {
-        self.output = output;
-        
+        self.requested_output = output;
+        self.route_output(output);
+    }
+    
+    /// Route the audio to `output` without changing the requested device
+    fn route_output(&mut self, output: OutputDevice) {
         let val = match output {
-            OutputDevice::Auto => 0x00,
+            // Headphone on while SPK/HP_SW is LO, speaker while it is HI
+            OutputDevice::Auto => 0x05,
             OutputDevice::Speaker => 0xFA,
             OutputDevice::Headphone => 0xAF,
             OutputDevice::Both => 0xAA,
         };
         
         self.write_register(regs::POWER_CTL2, val);
+        self.output = match output {
+            OutputDevice::Auto => self.jack_output(),
+            output => output,
+        };
         debug!("Output device set to {:?}", output);
     }
     
+    /// Output device the jack detect pin selects in [`OutputDevice::Auto`]
+    fn jack_output(&mut self) -> OutputDevice {
+        if self.headphone_detected() {
+            OutputDevice::Headphone
+        } else {
+            OutputDevice::Speaker
+        }
+    }
+    
     /// Set the master volume
     ///
-    /// Sets the output volume for both left and right channels.
+    /// Sets the output volume for both left and right channels, taking the
+    /// [`Balance`] into account.
     ///
     /// # Arguments
-    /// * `volume` - Volume level (0-100%)
+    /// * `volume` - Volume level, from a percentage or in dB
     ///
     /// # Example
-    /// ```no_run
+    /// ```ignore
     /// dac.set_volume(Volume::new(80)); // 80% volume
+    /// dac.set_volume(Volume::from_db(-12.0));
     /// ```
     pub fn set_volume(&mut self, volume: Volume) {
         self.volume = volume;
-        let val = volume.to_dac_value();
+        self.write_master_volume();
+        debug!("Volume set to {} dB", volume.db());
+    }
+    
+    /// Set the left/right balance
+    ///
+    /// # Example
+    /// ```ignore
+    /// dac.set_balance(Balance::new(-0.25)); // slightly to the left
+    /// ```
+    pub fn set_balance(&mut self, balance: Balance) {
+        self.balance = balance;
+        self.write_master_volume();
+        debug!("Balance set to {}", balance.value());
+    }
+    
+    /// Set the headphone amplifier volume
+    ///
+    /// Applied after the master volume, from -96 to 0 dB in 0.5 dB steps.
+    /// Values below -96 dB mute the headphone output.
+    pub fn set_headphone_volume(&mut self, db: f32) {
+        let val = output_volume_register(db);
+        self.write_register(regs::HEADPHONE_VOL_A, val);
+        self.write_register(regs::HEADPHONE_VOL_B, val);
+        debug!("Headphone volume set to {} dB", db);
+    }
+    
+    /// Set the speaker amplifier volume
+    ///
+    /// Applied after the master volume, from -96 to 0 dB in 0.5 dB steps.
+    /// Values below -96 dB mute the speaker output.
+    pub fn set_speaker_volume(&mut self, db: f32) {
+        let val = output_volume_register(db);
+        self.write_register(regs::SPEAKER_VOL_A, val);
+        self.write_register(regs::SPEAKER_VOL_B, val);
+        debug!("Speaker volume set to {} dB", db);
+    }
+    
+    /// Set the volume of the PCM input
+    ///
+    /// Scales the I2S data before it is mixed with the beep generator, from
+    /// -51.5 to +12 dB in 0.5 dB steps. Values below -51.5 dB mute the PCM input.
+    /// This also clears the PCM mute set by [`enable_passthrough`](Self::enable_passthrough).
+    pub fn set_pcm_volume(&mut self, db: f32) {
+        let val = if db < -51.5 {
+            PCM_MUTE
+        } else {
+            (half_db_steps(db, -51.5, 12.0) as u8) & !PCM_MUTE
+        };
+        self.write_register(regs::PCM_VOL_A, val);
+        self.write_register(regs::PCM_VOL_B, val);
+        debug!("PCM volume set to {} dB", db);
+    }
+    
+    /// Configure how volume changes take effect
+    ///
+    /// # Example
+    /// ```ignore
+    /// dac.set_volume_ramp(VolumeRamp { soft_ramp: true, zero_cross: true });
+    /// ```
+    pub fn set_volume_ramp(&mut self, ramp: VolumeRamp) {
+        let mut misc = self.read_register(regs::MISC_CTL) & !(MISC_DIGSFT | MISC_DIGZC);
+        let mut analog = 0;
+        if ramp.soft_ramp {
+            misc |= MISC_DIGSFT;
+            analog |= 0x0A; // ANLGSFTB | ANLGSFTA
+        }
+        if ramp.zero_cross {
+            misc |= MISC_DIGZC;
+            analog |= 0x05; // ANLGZCB | ANLGZCA
+        }
+        self.write_register(regs::MISC_CTL, misc);
+        self.write_register(regs::ANALOG_ZC_SR, analog);
+        debug!("Volume ramp set to {}", ramp);
+    }
+    
+    /// Configure tone control, limiter, channel routing and filters
+    ///
+    /// # Example
+    /// ```ignore
+    /// dac.set_audio_processing(AudioProcessing {
+    ///     limiter: Some(Limiter { max: LimiterThreshold::DbMinus6, ..Default::default() }),
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_audio_processing(&mut self, processing: AudioProcessing) {
+        // Tone control; the beep settings share BEEP_TONE_CFG
+        let mut tone_cfg = self.read_register(regs::BEEP_TONE_CFG) & !TONE_CFG_MASK;
+        if let Some(tone) = processing.tone {
+            tone_cfg |= (tone.treble_corner as u8) << 3 | (tone.bass_corner as u8) << 1 | TONE_CFG_TCEN;
+            self.write_register(
+                regs::TONE_CTL,
+                ToneControl::gain_value(tone.treble_db) << 4 | ToneControl::gain_value(tone.bass_db),
+            );
+        }
+        self.write_register(regs::BEEP_TONE_CFG, tone_cfg);
+        
+        // Limiter
+        match processing.limiter {
+            Some(limiter) => {
+                let mut ctl1 = (limiter.max as u8) << 5 | (limiter.cushion as u8) << 2;
+                if !limiter.soft_ramp {
+                    ctl1 |= LIMIT_SR_DIS;
+                }
+                if !limiter.zero_cross {
+                    ctl1 |= LIMIT_ZC_DIS;
+                }
+                let mut ctl2 = LIMIT_ENABLE | limiter.release_rate.min(63);
+                if limiter.both_channels {
+                    ctl2 |= LIMIT_ALL;
+                }
+                self.write_register(regs::LIMIT_CTL1, ctl1);
+                self.write_register(regs::LIMIT_ATTACK, limiter.attack_rate.min(63));
+                self.write_register(regs::LIMIT_CTL2, ctl2);
+            }
+            None => {
+                let ctl2 = self.read_register(regs::LIMIT_CTL2);
+                self.write_register(regs::LIMIT_CTL2, ctl2 & !LIMIT_ENABLE);
+            }
+        }
+        
+        // Channel routing
+        self.write_register(regs::CHANNEL_MIXER, processing.mix.register_value());
+        
+        // De-emphasis
+        let mut misc = self.read_register(regs::MISC_CTL) & !MISC_DEEMPHASIS;
+        if processing.de_emphasis {
+            misc |= MISC_DEEMPHASIS;
+        }
+        self.write_register(regs::MISC_CTL, misc);
+        
+        // Polarity
+        let mut playback = self.read_register(regs::PLAYBACK_CTL1) & !(PLAYBACK_INV_PCMA | PLAYBACK_INV_PCMB);
+        if processing.invert_left {
+            playback |= PLAYBACK_INV_PCMA;
+        }
+        if processing.invert_right {
+            playback |= PLAYBACK_INV_PCMB;
+        }
+        self.write_register(regs::PLAYBACK_CTL1, playback);
+        
+        debug!("Audio processing set to {}", processing);
+    }
+    
+    /// Route analog inputs to the outputs
+    ///
+    /// The output amplifiers need MCLK for their charge pump, so the [`AudioClock`]
+    /// runs while passthrough is enabled. The DAC must be powered on.
+    pub fn enable_passthrough(&mut self, passthrough: Passthrough) {
+        if !self.passthrough {
+            self.pcm_mute_before_passthrough = self.pcm_mute();
+        }
+        self.passthrough = true;
+        self.update_clock();
+        
+        self.write_register(regs::PASSTHROUGH_A, passthrough.left as u8);
+        self.write_register(regs::PASSTHROUGH_B, passthrough.right as u8);
+        self.set_passthrough_gain(passthrough.gain_db);
+        
+        let misc = self.read_register(regs::MISC_CTL) & !MISC_PASSMUTE_MASK;
+        self.write_register(regs::MISC_CTL, misc | MISC_PASSTHRU_MASK);
+        self.set_pcm_mute([!passthrough.mix_pcm; 2]);
+        info!("Analog passthrough enabled: {}", passthrough);
+    }
+    
+    /// Set the passthrough gain, -60 to +12 dB in 0.5 dB steps
+    pub fn set_passthrough_gain(&mut self, db: f32) {
+        let val = half_db_steps(db, -60.0, 12.0) as u8;
+        self.write_register(regs::PASSTHROUGH_VOL_A, val);
+        self.write_register(regs::PASSTHROUGH_VOL_B, val);
+        debug!("Passthrough gain set to {} dB", db);
+    }
+    
+    /// Stop routing analog inputs to the outputs
+    ///
+    /// Restores the PCM mute state from before [`enable_passthrough`](Self::enable_passthrough).
+    pub fn disable_passthrough(&mut self) {
+        let misc = self.read_register(regs::MISC_CTL) & !MISC_PASSTHRU_MASK;
+        self.write_register(regs::MISC_CTL, misc | MISC_PASSMUTE_MASK);
+        self.write_register(regs::PASSTHROUGH_A, 0x00);
+        self.write_register(regs::PASSTHROUGH_B, 0x00);
+        if self.passthrough {
+            self.set_pcm_mute(self.pcm_mute_before_passthrough);
+        }
         
-        self.write_register(regs::MASTER_VOL_A, val);
-        self.write_register(regs::MASTER_VOL_B, val);
-        debug!("Volume set to {}%", volume.0);
+        self.passthrough = false;
+        self.update_clock();
+        info!("Analog passthrough disabled");
+    }
+    
+    /// Read the mute bits of the left and right PCM volume registers
+    fn pcm_mute(&mut self) -> [bool; 2] {
+        [regs::PCM_VOL_A, regs::PCM_VOL_B].map(|reg| self.read_register(reg) & PCM_MUTE != 0)
+    }
+    
+    /// Set or clear the mute bits of the left and right PCM volume registers
+    fn set_pcm_mute(&mut self, mute: [bool; 2]) {
+        for (reg, mute) in [regs::PCM_VOL_A, regs::PCM_VOL_B].into_iter().zip(mute) {
+            let val = self.read_register(reg) & !PCM_MUTE;
+            self.write_register(reg, if mute { val | PCM_MUTE } else { val });
+        }
     }
     
     /// Mute the output
     ///
-    /// Temporarily mutes audio output without changing the volume setting.
-    /// Use `unmute()` to restore the previous volume level.
+    /// Mutes the headphone and speaker outputs without changing the volume setting.
+    /// Use `unmute()` to restore the output.
     pub fn mute(&mut self) {
-        self.write_register(regs::MASTER_VOL_A, 0x00);
-        self.write_register(regs::MASTER_VOL_B, 0x00);
+        let ctl = self.read_register(regs::PLAYBACK_CTL2);
+        self.write_register(regs::PLAYBACK_CTL2, ctl | PLAYBACK_MUTE_MASK);
     }
     
     /// Unmute the output
     ///
-    /// Restores audio output to the previously configured volume level
-    /// after muting.
+    /// Restores the headphone and speaker outputs after muting.
     pub fn unmute(&mut self) {
-        let val = self.volume.to_dac_value();
-        self.write_register(regs::MASTER_VOL_A, val);
-        self.write_register(regs::MASTER_VOL_B, val);
+        let ctl = self.read_register(regs::PLAYBACK_CTL2);
+        self.write_register(regs::PLAYBACK_CTL2, ctl & !PLAYBACK_MUTE_MASK);
+    }
+    
+    /// Write the master volume of both channels
+    fn write_master_volume(&mut self) {
+        let (left, right) = self.balance.attenuation();
+        let a = self.volume.attenuated(left).register_value();
+        let b = self.volume.attenuated(right).register_value();
+        self.write_register(regs::MASTER_VOL_A, a);
+        self.write_register(regs::MASTER_VOL_B, b);
+    }
+    
+    /// Read and clear the error flags
+    pub fn read_status(&mut self) -> DacStatus {
+        let status = self.read_register(regs::STATUS);
+        DacStatus {
+            serial_clock_error: status & STATUS_SPCLKERR != 0,
+            dsp_overflow_left: status & STATUS_DSPAOVFL != 0,
+            dsp_overflow_right: status & STATUS_DSPBOVFL != 0,
+            pcm_overflow_left: status & STATUS_PCMAOVFL != 0,
+            pcm_overflow_right: status & STATUS_PCMBOVFL != 0,
+        }
+    }
+    
+    /// Read the speaker short-circuit and headphone-detect state
+    pub fn read_speaker_status(&mut self) -> SpeakerStatus {
+        let status = self.read_register(regs::SPEAKER_STATUS);
+        SpeakerStatus {
+            short_left: status & SPEAKER_STATUS_SPKASHRT != 0,
+            short_right: status & SPEAKER_STATUS_SPKBSHRT != 0,
+            headphone_detected: status & SPEAKER_STATUS_SPKR_HP == 0,
+        }
+    }
+    
+    /// Check if headphones are plugged in
+    pub fn headphone_detected(&mut self) -> bool {
+        self.read_speaker_status().headphone_detected
     }
     
-    /// Play a beep tone (demonstration only)
+    /// Output device currently routed
     ///
-    /// Generates a simple beep tone using the CS43L22's internal tone generator.
-    /// 
-    /// # Arguments
-    /// * `frequency` - Frequency setting (0-255, actual frequency depends on configuration)
-    /// * `duration_ms` - Duration of the beep in milliseconds
+    /// Follows the headphone jack while the requested device is
+    /// [`OutputDevice::Auto`].
+    pub fn output(&self) -> OutputDevice {
+        self.output
+    }
+    
+    /// Output device selected with [`set_output`](Self::set_output)
+    pub fn requested_output(&self) -> OutputDevice {
+        self.requested_output
+    }
+    
+    /// Check the status registers and follow the headphone jack
     ///
-    /// # Note
-    /// This is a simplified implementation. The beep generator requires additional
-    /// configuration for proper operation including I2S clock setup.
-    pub fn beep(&mut self, frequency: u8, duration_ms: u16) {
-        // Configure beep frequency and duration
-        self.write_register(regs::BEEP_FREQ_ON_TIME, frequency);
-        self.write_register(regs::BEEP_VOL_OFF_TIME, 0x06); // Medium volume
+    /// Logs any faults. While the requested device is [`OutputDevice::Auto`], the
+    /// DAC switches to [`OutputDevice::Headphone`] while headphones are plugged in
+    /// and [`OutputDevice::Speaker`] otherwise; this returns the new output device
+    /// if it changed. Explicit selections are left alone.
+    ///
+    /// # Example
+    /// ```ignore
+    /// if let Some(output) = dac.update_output() {
+    ///     info!("Switched to {}", output);
+    /// }
+    /// ```
+    pub fn update_output(&mut self) -> Option<OutputDevice> {
+        let status = self.read_status();
+        if status.any() {
+            warn!("CS43L22 status: {}", status);
+        }
         
-        // Enable beep
-        self.write_register(regs::BEEP_TONE_CFG, 0xC0);
+        let speaker = self.read_speaker_status();
+        if speaker.short_left || speaker.short_right {
+            warn!("CS43L22 speaker short circuit: {}", speaker);
+        }
         
-        embassy_time::block_for(Duration::from_millis(duration_ms as u64));
+        if self.requested_output != OutputDevice::Auto {
+            return None;
+        }
+        
+        let output = if speaker.headphone_detected {
+            OutputDevice::Headphone
+        } else {
+            OutputDevice::Speaker
+        };
+        if output == self.output {
+            return None;
+        }
+        
+        self.output = output;
+        info!("CS43L22 output switched to {}", output);
+        Some(output)
+    }
+    
+    /// Hand over the master clock source
+    ///
+    /// The driver starts the clock while it needs it (beeps, analog passthrough) and stops it
+    /// afterwards. Without one, the DAC relies on a running I2S stream for MCLK.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut buffer = [0u16; 64];
+    /// dac.set_clock(AudioClock::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut buffer));
+    /// ```
+    pub fn set_clock(&mut self, clock: AudioClock<'a>) {
+        self.clock = Some(clock);
+        self.update_clock();
+    }
+    
+    /// Set the beep generator volume
+    pub fn set_beep_volume(&mut self, volume: BeepVolume) {
+        self.beep_volume = volume;
+        let off_time = self.read_register(regs::BEEP_VOL_OFF_TIME) & 0xE0;
+        self.write_register(regs::BEEP_VOL_OFF_TIME, off_time | volume.register_value());
+        debug!("Beep volume set to {} dB", volume.db());
+    }
+    
+    /// Start the beep generator
+    ///
+    /// Returns immediately. A [`BeepMode::Single`] beep ends by itself; the other
+    /// modes run until [`stop_beep`](Self::stop_beep). The beep is mixed into the
+    /// audio output, so the DAC must be powered on.
+    pub fn start_beep(&mut self, beep: Beep) {
+        self.beeping = true;
+        self.update_clock();
         
-        // Disable beep
-        self.write_register(regs::BEEP_TONE_CFG, 0x00);
+        // Keep the tone control settings in the low bits
+        let cfg = self.read_register(regs::BEEP_TONE_CFG) & !BEEP_MODE_MASK;
+        // The beep restarts on a transition from off
+        self.write_register(regs::BEEP_TONE_CFG, cfg);
+        self.write_register(
+            regs::BEEP_FREQ_ON_TIME,
+            (beep.frequency as u8) << 4 | beep.on_time as u8,
+        );
+        self.write_register(
+            regs::BEEP_VOL_OFF_TIME,
+            (beep.off_time as u8) << 5 | self.beep_volume.register_value(),
+        );
+        self.write_register(regs::BEEP_TONE_CFG, cfg | (beep.mode as u8) << 6);
+        debug!("Beep started: {}", beep);
+    }
+    
+    /// Stop the beep generator
+    pub fn stop_beep(&mut self) {
+        let cfg = self.read_register(regs::BEEP_TONE_CFG);
+        self.write_register(regs::BEEP_TONE_CFG, cfg & !BEEP_MODE_MASK);
+        self.beeping = false;
+        self.update_clock();
+    }
+    
+    /// Play one beep and wait until it has ended
+    ///
+    /// # Example
+    /// ```ignore
+    /// dac.beep(BeepFrequency::B5, BeepOnTime::Ms86).await;
+    /// ```
+    pub async fn beep(&mut self, frequency: BeepFrequency, on_time: BeepOnTime) {
+        self.start_beep(Beep::single(frequency, on_time));
+        Timer::after(on_time.duration()).await;
+        self.stop_beep();
+    }
+    
+    /// Play a continuous tone for `duration`
+    ///
+    /// # Example
+    /// ```ignore
+    /// dac.tone(BeepFrequency::A5, Duration::from_millis(200)).await;
+    /// ```
+    pub async fn tone(&mut self, frequency: BeepFrequency, duration: Duration) {
+        self.start_beep(Beep::continuous(frequency));
+        Timer::after(duration).await;
+        self.stop_beep();
+    }
+    
+    /// Run the master clock only while something needs it
+    fn update_clock(&mut self) {
+        let needed = self.beeping || self.passthrough;
+        match &mut self.clock {
+            Some(clock) if needed => clock.start(),
+            Some(clock) => clock.stop(),
+            None if needed => debug!("No audio clock set, relying on the I2S stream for MCLK"),
+            None => {}
+        }
     }
     
     /// Read a register
@@ -326,3 +1419,114 @@ impl<'a> CS43L22<'a> {
         self.i2c.blocking_write(CS43L22_ADDR, &[reg, value]).ok();
     }
 }
+
+/// Report faults and output switches forever
+///
+/// Polls the DAC every `interval`, locking `dac` only for the register reads,
+/// so other tasks can keep using it. See [`CS43L22::update_output`].
+pub async fn run_output_monitor<M: RawMutex>(dac: &Mutex<M, CS43L22<'_>>, interval: Duration) -> ! {
+    loop {
+        dac.lock().await.update_output();
+        Timer::after(interval).await;
+    }
+}
+
+/// Embassy task reporting faults and the speaker/headphone switches on plug insertion
+///
+/// # Example
+/// ```ignore
+/// let dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+/// let dac = cortex_m::singleton!(: Mutex<CriticalSectionRawMutex, CS43L22<'static>> = Mutex::new(dac)).unwrap();
+/// spawner.spawn(output_monitor_task(dac)).unwrap();
+/// dac.lock().await.beep(BeepFrequency::A5, BeepOnTime::Ms86).await;
+/// ```
+#[embassy_executor::task]
+pub async fn output_monitor_task(dac: &'static Mutex<CriticalSectionRawMutex, CS43L22<'static>>) {
+    run_output_monitor(dac, OUTPUT_MONITOR_INTERVAL).await
+}
+
+/// Master clock for the DAC from I2S3, without audio data
+///
+/// Runs I2S3 as a master transmitter at 48 kHz with MCLK output, with DMA sending
+/// silence from `buffer`:
+/// - MCK: PC7 (256 x 48.12 kHz = 12.32 MHz from the 135.5 MHz of [`configure_audio_pll`])
+/// - SCK: PC10
+/// - SD: PC12
+/// - WS: PA4
+///
+/// The I2S clock comes from PLLI2S, which must be enabled when initializing the HAL
+/// with [`configure_audio_pll`].
+/// [`PcmOutput`](crate::playback::PcmOutput) uses the same peripherals; use one or the other.
+///
+/// [`configure_audio_pll`]: crate::playback::configure_audio_pll
+///
+/// # Example
+/// ```ignore
+/// let mut buffer = [0u16; 64];
+/// let mut clock = AudioClock::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut buffer);
+/// clock.start();
+/// ```
+pub struct AudioClock<'d> {
+    /// Stream sending silence, owns I2S3, its pins and DMA stream
+    _i2s: I2S<'d, u16>,
+    running: bool,
+}
+
+impl<'d> AudioClock<'d> {
+    /// Claim I2S3, its pins and DMA stream; the clock is started with [`start`](Self::start)
+    ///
+    /// `buffer` is the DMA ring buffer, a few words are enough.
+    pub fn new(
+        spi3: Peri<'d, SPI3>,
+        mck: Peri<'d, PC7>,
+        ck: Peri<'d, PC10>,
+        sd: Peri<'d, PC12>,
+        ws: Peri<'d, PA4>,
+        dma: Peri<'d, DMA1_CH5>,
+        buffer: &'d mut [u16],
+    ) -> Self {
+        buffer.fill(0);
+        let mut config = i2s::Config::default();
+        config.frequency = Hertz(SAMPLE_RATE);
+
+        let mut i2s = I2S::new_txonly(spi3, sd, ws, ck, mck, dma, buffer, config);
+        i2s.start();
+        // The HAL enables I2S3 right away; keep the clock generator off until `start`
+        pac::SPI3.i2scfgr().modify(|w| w.set_i2se(false));
+
+        Self { _i2s: i2s, running: false }
+    }
+
+    /// Start the clocks
+    pub fn start(&mut self) {
+        if self.running {
+            return;
+        }
+
+        pac::SPI3.i2scfgr().modify(|w| w.set_i2se(true));
+        self.running = true;
+
+        // With MCLK enabled, MCLK is the I2S clock divided by (2 x I2SDIV + ODD)
+        let i2spr = pac::SPI3.i2spr().read();
+        let divider = 2 * i2spr.i2sdiv() as u32 + (i2spr.odd() == spi_vals::Odd::ODD) as u32;
+        debug!("Audio clock started: MCLK {} Hz", i2s_clock() / divider);
+    }
+
+    /// Stop the clocks
+    ///
+    /// Turns the I2S3 clock generator off; the pins and the DMA stay set up.
+    pub fn stop(&mut self) {
+        if !self.running {
+            return;
+        }
+
+        pac::SPI3.i2scfgr().modify(|w| w.set_i2se(false));
+        self.running = false;
+        debug!("Audio clock stopped");
+    }
+
+    /// Check whether the clocks are running
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
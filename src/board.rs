@@ -0,0 +1,202 @@
+//! Board revision detection
+//!
+//! The STM32F411E-DISCO (MB1115) has shipped in two sensor configurations:
+//! - Revision B: L3GD20 gyroscope, LSM303DLHC e-compass, MP45DT02 microphone
+//! - Revision D: I3G4250D gyroscope, LSM303AGR e-compass, IMP34DT05 microphone
+//!
+//! The revision is detected by probing the e-compass magnetometer identification
+//! registers. [`ECompass`] picks the matching driver automatically, and the
+//! [`gyro::L3GD20`](crate::gyro::L3GD20) driver handles both gyroscopes.
+//!
+//! [User manual](docs/um1842-discovery-kit-with-stm32f411ve-mcu-stmicroelectronics.pdf)
+
+use defmt::{info, warn};
+use embassy_stm32::i2c::{Config as I2cConfig, I2c};
+use embassy_stm32::{i2c, Peri};
+
+use crate::compass::{
+    self, AccelConfig, Acceleration, AccelDataRate, AccelScale, MagConfig, MagDataRate, MagneticField,
+    LSM303DLHC,
+};
+use crate::lsm303agr::{self, AgrMagDataRate, LSM303AGR};
+use crate::sensor::{Accelerometer, Magnetometer};
+use crate::temperature::Temperature;
+
+/// Board hardware revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BoardRevision {
+    /// MB1115 B-02/B-03: L3GD20, LSM303DLHC, MP45DT02
+    B,
+    /// MB1115 D-01: I3G4250D, LSM303AGR, IMP34DT05
+    D,
+}
+
+impl BoardRevision {
+    /// Detect the board revision
+    ///
+    /// Pass reborrowed peripherals so they can be used for the sensor drivers afterwards.
+    /// Returns `None` if no known e-compass answers.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let revision = BoardRevision::detect(p.I2C1.reborrow(), p.PB6.reborrow(), p.PB9.reborrow());
+    /// ```
+    pub fn detect<T: i2c::Instance>(
+        i2c1: Peri<'_, T>,
+        scl: Peri<'_, impl i2c::SclPin<T>>,
+        sda: Peri<'_, impl i2c::SdaPin<T>>,
+    ) -> Option<Self> {
+        let mut i2c = I2c::new_blocking(i2c1, scl, sda, I2cConfig::default());
+        probe(&mut i2c)
+    }
+}
+
+/// Identify the e-compass on the bus
+fn probe(i2c: &mut I2c<'_, embassy_stm32::mode::Blocking, i2c::Master>) -> Option<BoardRevision> {
+    let mut buf = [0u8; 1];
+
+    let agr = i2c.blocking_write_read(lsm303agr::MAG_ADDR, &[lsm303agr::mag_regs::WHO_AM_I_M], &mut buf);
+    if agr.is_ok() && buf[0] == lsm303agr::MAG_WHO_AM_I {
+        info!("Detected board revision D (LSM303AGR)");
+        return Some(BoardRevision::D);
+    }
+
+    let dlhc = i2c.blocking_write_read(compass::MAG_ADDR, &[compass::mag_regs::IRA_REG_M], &mut buf);
+    if dlhc.is_ok() && buf[0] == compass::MAG_IRA {
+        info!("Detected board revision B (LSM303DLHC)");
+        return Some(BoardRevision::B);
+    }
+
+    None
+}
+
+/// E-compass driver for whichever part is fitted
+///
+/// # Example
+/// ```ignore
+/// let mut compass = ECompass::new(p.I2C1, p.PB6, p.PB9);
+/// let accel = compass.read_acceleration();
+/// ```
+pub enum ECompass<'a> {
+    /// Revision B boards
+    Lsm303dlhc(LSM303DLHC<'a>),
+    /// Revision D boards
+    Lsm303agr(LSM303AGR<'a>),
+}
+
+impl<'a> ECompass<'a> {
+    /// Probe the bus and create the matching driver
+    ///
+    /// Falls back to the LSM303DLHC driver if neither part is recognized.
+    pub fn new<T: i2c::Instance>(
+        i2c1: Peri<'a, T>,
+        scl: Peri<'a, impl i2c::SclPin<T>>,
+        sda: Peri<'a, impl i2c::SdaPin<T>>,
+    ) -> Self {
+        let mut i2c = I2c::new_blocking(i2c1, scl, sda, I2cConfig::default());
+
+        match probe(&mut i2c) {
+            Some(BoardRevision::D) => ECompass::Lsm303agr(LSM303AGR::from_i2c(i2c)),
+            Some(BoardRevision::B) => ECompass::Lsm303dlhc(LSM303DLHC::from_i2c(i2c)),
+            None => {
+                warn!("No known e-compass found, assuming LSM303DLHC");
+                ECompass::Lsm303dlhc(LSM303DLHC::from_i2c(i2c))
+            }
+        }
+    }
+
+    /// Board revision implied by the fitted part
+    pub fn revision(&self) -> BoardRevision {
+        match self {
+            ECompass::Lsm303dlhc(_) => BoardRevision::B,
+            ECompass::Lsm303agr(_) => BoardRevision::D,
+        }
+    }
+
+    /// Set accelerometer scale
+    pub fn set_accel_scale(&mut self, scale: AccelScale) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.set_accel_scale(scale),
+            ECompass::Lsm303agr(c) => c.set_accel_scale(scale),
+        }
+    }
+
+    /// Set accelerometer data rate
+    pub fn set_accel_data_rate(&mut self, rate: AccelDataRate) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.set_accel_data_rate(rate),
+            ECompass::Lsm303agr(c) => c.set_accel_data_rate(rate),
+        }
+    }
+
+    /// Read temperature in °C
+    pub fn read_temperature(&mut self) -> Temperature {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.read_temperature(),
+            ECompass::Lsm303agr(c) => c.read_temperature(),
+        }
+    }
+}
+
+impl Accelerometer for ECompass<'_> {
+    type AccelConfig = AccelConfig;
+
+    fn configure_accel(&mut self, config: AccelConfig) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.configure_accel(config),
+            ECompass::Lsm303agr(c) => c.configure_accel(config),
+        }
+    }
+
+    fn accel_data_ready(&mut self) -> bool {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.accel_data_ready(),
+            ECompass::Lsm303agr(c) => c.accel_data_ready(),
+        }
+    }
+
+    fn read_acceleration(&mut self) -> Acceleration {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.read_acceleration(),
+            ECompass::Lsm303agr(c) => c.read_acceleration(),
+        }
+    }
+}
+
+impl Magnetometer for ECompass<'_> {
+    type MagConfig = MagConfig;
+
+    /// Apply a magnetometer configuration
+    ///
+    /// The LSM303AGR has a fixed ±50 gauss range, so the gain is ignored there and
+    /// the data rate is rounded to the nearest supported one.
+    fn configure_mag(&mut self, config: MagConfig) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.configure_mag(config),
+            ECompass::Lsm303agr(c) => c.configure_mag(match config.data_rate {
+                MagDataRate::Hz0_75
+                | MagDataRate::Hz1_5
+                | MagDataRate::Hz3
+                | MagDataRate::Hz7_5
+                | MagDataRate::Hz15 => AgrMagDataRate::Hz10,
+                MagDataRate::Hz30 => AgrMagDataRate::Hz20,
+                MagDataRate::Hz75 => AgrMagDataRate::Hz50,
+                MagDataRate::Hz220 => AgrMagDataRate::Hz100,
+            }),
+        }
+    }
+
+    fn mag_data_ready(&mut self) -> bool {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.mag_data_ready(),
+            ECompass::Lsm303agr(c) => c.mag_data_ready(),
+        }
+    }
+
+    fn read_magnetic_field(&mut self) -> MagneticField {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.read_magnetic_field(),
+            ECompass::Lsm303agr(c) => c.read_magnetic_field(),
+        }
+    }
+}
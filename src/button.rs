@@ -2,9 +2,17 @@
 //!
 //! The STM32F411E Discovery board has a user button on PA0.
 //! The button is active HIGH (pressed = HIGH).
+//!
+//! [`Button`] polls the pin level. [`ExtiButton`] uses the EXTI0 interrupt so tasks
+//! sleep until the button changes, and debounces the contacts.
 
+use embassy_stm32::exti::ExtiInput;
 use embassy_stm32::gpio::{Input, Pull};
 use embassy_stm32::Peri;
+use embassy_time::{Duration, Timer};
+
+/// Default time the button must stay in a new state before the change is accepted
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(50);
 
 /// User button (B1) on PA0
 pub struct Button<'d> {
@@ -23,3 +31,85 @@ impl<'d> Button<'d> {
         self.inner.is_high()
     }
 }
+
+/// User button (B1) on PA0 with interrupt-driven, debounced events
+///
+/// # Example
+/// ```ignore
+/// let mut button = ExtiButton::new(p.PA0, p.EXTI0);
+/// loop {
+///     button.wait_for_press().await;
+///     leds.toggle(Led::Green);
+/// }
+/// ```
+pub struct ExtiButton<'d> {
+    inner: ExtiInput<'d>,
+    pressed: bool,
+    debounce: Duration,
+}
+
+impl<'d> ExtiButton<'d> {
+    /// Initialize the user button with pull-down resistor on its EXTI line
+    ///
+    /// # Arguments
+    /// * `pin` - PA0
+    /// * `exti` - EXTI0
+    pub fn new<T: embassy_stm32::gpio::Pin>(
+        pin: Peri<'d, T>,
+        exti: Peri<'d, T::ExtiChannel>,
+    ) -> Self {
+        let inner = ExtiInput::new(pin, exti, Pull::Down);
+        let pressed = inner.is_high();
+        Self {
+            inner,
+            pressed,
+            debounce: DEFAULT_DEBOUNCE,
+        }
+    }
+
+    /// Set the debounce time (default 50 ms)
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Debounced button state
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Wait for the debounced state to change
+    ///
+    /// Returns the new state (`true` = pressed). Changes shorter than the debounce
+    /// time are ignored.
+    pub async fn wait_for_change(&mut self) -> bool {
+        loop {
+            if self.pressed {
+                self.inner.wait_for_low().await;
+            } else {
+                self.inner.wait_for_high().await;
+            }
+
+            Timer::after(self.debounce).await;
+            let level = self.inner.is_high();
+            if level != self.pressed {
+                self.pressed = level;
+                return level;
+            }
+        }
+    }
+
+    /// Wait for the button to be pressed
+    ///
+    /// If the button is already held, this waits for it to be released and
+    /// pressed again.
+    pub async fn wait_for_press(&mut self) {
+        while !self.wait_for_change().await {}
+    }
+
+    /// Wait for the button to be released
+    ///
+    /// If the button is already released, this waits for a full press and release.
+    pub async fn wait_for_release(&mut self) {
+        while self.wait_for_change().await {}
+    }
+}
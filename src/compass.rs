@@ -15,13 +15,20 @@
 //!
 //! [Datasheet](https://www.st.com/resource/en/datasheet/lsm303dlhc.pdf)
 
-use defmt::{debug, info};
+use defmt::{debug, info, warn};
 use embassy_stm32::i2c::{Config as I2cConfig, I2c};
 use embassy_stm32::{i2c, Peri};
+use embassy_time::{with_timeout, Duration, Timer};
+
+use crate::sensor::{Accelerometer, Magnetometer, SelfTestError, Vector3};
+use crate::temperature::{Temperature, TemperatureCalibration, NOMINAL_CELSIUS};
 
 /// I2C addresses
 const ACCEL_ADDR: u8 = 0x19; // 0x32 >> 1
-const MAG_ADDR: u8 = 0x1E;   // 0x3C >> 1
+pub(crate) const MAG_ADDR: u8 = 0x1E;   // 0x3C >> 1
+
+/// Expected IRA_REG_M value ('H')
+pub(crate) const MAG_IRA: u8 = 0x48;
 
 /// Accelerometer register addresses
 #[allow(dead_code)]
@@ -40,11 +47,15 @@ mod accel_regs {
     pub const OUT_Y_H_A: u8 = 0x2B;
     pub const OUT_Z_L_A: u8 = 0x2C;
     pub const OUT_Z_H_A: u8 = 0x2D;
+    pub const INT1_CFG_A: u8 = 0x30;
+    pub const INT1_SRC_A: u8 = 0x31;
+    pub const INT1_THS_A: u8 = 0x32;
+    pub const INT1_DURATION_A: u8 = 0x33;
 }
 
 /// Magnetometer register addresses
 #[allow(dead_code)]
-mod mag_regs {
+pub(crate) mod mag_regs {
     pub const CRA_REG_M: u8 = 0x00;
     pub const CRB_REG_M: u8 = 0x01;
     pub const MR_REG_M: u8 = 0x02;
@@ -62,8 +73,46 @@ mod mag_regs {
     pub const TEMP_OUT_L_M: u8 = 0x32;
 }
 
+/// INT1_CFG_A: OR of the X, Y and Z high events
+const INT1_CFG_XYZ_HIGH: u8 = 0x2A;
+
+/// CTRL_REG3_A: AOI1 interrupt on the INT1 pin
+const CTRL3_I1_AOI1: u8 = 0x40;
+
+/// CTRL_REG5_A: latch INT1 until INT1_SRC_A is read
+const CTRL5_LIR_INT1: u8 = 0x08;
+
+/// INT1_SRC_A interrupt active bit
+const INT1_SRC_IA: u8 = 0x40;
+
+/// MR_REG_M modes
+const MAG_CONTINUOUS: u8 = 0x00;
+const MAG_SLEEP: u8 = 0x03;
+
+/// CTRL_REG1_A data rate bits and low-power mode enable bit
+const CTRL1_A_ODR: u8 = 0xF0;
+const CTRL1_A_LPEN: u8 = 0x08;
+
+/// CTRL_REG4_A high-resolution output mode bit
+const CTRL4_A_HR: u8 = 0x08;
+
+/// CRA_REG_M data rate bits
+const CRA_DO: u8 = 0x1C;
+
+/// CRA_REG_M temperature sensor enable bit
+const TEMP_EN: u8 = 0x80;
+
+/// Temperature sensor sensitivity in LSB/°C
+const TEMP_SENSITIVITY: f32 = 8.0;
+
+/// CTRL_REG4_A self-test 0 (positive sign) bits
+const CTRL4_A_ST0: u8 = 0x02;
+
+/// Longest wait for a sample during the self-test (5 periods at 50 Hz)
+const SELF_TEST_DATA_TIMEOUT: Duration = Duration::from_millis(100);
+
 /// Accelerometer full scale selection
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum AccelScale {
     /// ±2g
     G2 = 0x00,
@@ -88,7 +137,7 @@ impl AccelScale {
 }
 
 /// Magnetometer gain selection
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum MagGain {
     /// ±1.3 gauss
     Gauss1_3 = 0x20,
@@ -134,7 +183,7 @@ impl MagGain {
 }
 
 /// Accelerometer data rate
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum AccelDataRate {
     /// Power-down mode
     PowerDown = 0x00,
@@ -158,8 +207,100 @@ pub enum AccelDataRate {
     Hz1344 = 0x90,
 }
 
+impl AccelDataRate {
+    /// Output data rate in Hz (normal mode)
+    pub fn hz(&self) -> f32 {
+        match self {
+            AccelDataRate::PowerDown => 0.0,
+            AccelDataRate::Hz1 => 1.0,
+            AccelDataRate::Hz10 => 10.0,
+            AccelDataRate::Hz25 => 25.0,
+            AccelDataRate::Hz50 => 50.0,
+            AccelDataRate::Hz100 => 100.0,
+            AccelDataRate::Hz200 => 200.0,
+            AccelDataRate::Hz400 => 400.0,
+            AccelDataRate::Hz1620LP => 1620.0,
+            AccelDataRate::Hz1344 => 1344.0,
+        }
+    }
+}
+
+/// Accelerometer high-pass filter mode
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum AccelHighPassMode {
+    /// Normal mode, reading the REFERENCE_A register resets the filter
+    NormalWithReset = 0x00,
+    /// Output is the input minus the REFERENCE_A register value
+    Reference = 0x40,
+    /// Normal mode
+    Normal = 0x80,
+    /// Filter resets automatically on an interrupt event
+    AutoResetOnInterrupt = 0xC0,
+}
+
+/// Accelerometer high-pass filter cutoff selection
+///
+/// The datasheet gives no table; the cutoff is approximately the output data rate
+/// divided by the factor in the variant name (as for ST's LIS3DH core).
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum AccelHighPassCutoff {
+    /// ODR / 50 (e.g. 2 Hz at 100 Hz)
+    Odr50 = 0x00,
+    /// ODR / 100 (e.g. 1 Hz at 100 Hz)
+    Odr100 = 0x10,
+    /// ODR / 200 (e.g. 0.5 Hz at 100 Hz)
+    Odr200 = 0x20,
+    /// ODR / 400 (e.g. 0.25 Hz at 100 Hz)
+    Odr400 = 0x30,
+}
+
+impl AccelHighPassCutoff {
+    /// Approximate cutoff frequency in Hz at the given output data rate
+    pub fn frequency(&self, rate: AccelDataRate) -> f32 {
+        let divider = match self {
+            AccelHighPassCutoff::Odr50 => 50.0,
+            AccelHighPassCutoff::Odr100 => 100.0,
+            AccelHighPassCutoff::Odr200 => 200.0,
+            AccelHighPassCutoff::Odr400 => 400.0,
+        };
+        rate.hz() / divider
+    }
+}
+
+/// Accelerometer high-pass filter configuration
+///
+/// Each flag selects whether that consumer sees filtered data.
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct AccelHighPassConfig {
+    /// Filter mode
+    pub mode: AccelHighPassMode,
+    /// Cutoff frequency selection
+    pub cutoff: AccelHighPassCutoff,
+    /// Output registers and FIFO
+    pub output: bool,
+    /// Click detection
+    pub click: bool,
+    /// Interrupt 1 motion (AOI) function
+    pub int1: bool,
+    /// Interrupt 2 motion (AOI) function
+    pub int2: bool,
+}
+
+impl Default for AccelHighPassConfig {
+    fn default() -> Self {
+        Self {
+            mode: AccelHighPassMode::Normal,
+            cutoff: AccelHighPassCutoff::Odr50,
+            output: true,
+            click: false,
+            int1: false,
+            int2: false,
+        }
+    }
+}
+
 /// Magnetometer data rate
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum MagDataRate {
     /// 0.75 Hz
     Hz0_75 = 0x00,
@@ -179,26 +320,83 @@ pub enum MagDataRate {
     Hz220 = 0x1C,
 }
 
-/// 3-axis acceleration data
-#[derive(Debug, Default, Clone, Copy)]
-pub struct Acceleration {
-    /// X-axis acceleration in g
-    pub x: f32,
-    /// Y-axis acceleration in g
-    pub y: f32,
-    /// Z-axis acceleration in g
-    pub z: f32,
+/// 3-axis acceleration data in g
+pub type Acceleration = Vector3;
+
+/// 3-axis magnetic field data in gauss
+pub type MagneticField = Vector3;
+
+/// Accelerometer configuration applied with [`Accelerometer::configure_accel`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct AccelConfig {
+    /// Full scale range
+    pub scale: AccelScale,
+    /// Output data rate
+    pub data_rate: AccelDataRate,
+}
+
+impl Default for AccelConfig {
+    fn default() -> Self {
+        Self {
+            scale: AccelScale::G2,
+            data_rate: AccelDataRate::Hz100,
+        }
+    }
+}
+
+/// Magnetometer configuration applied with [`Magnetometer::configure_mag`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct MagConfig {
+    /// Gain (full scale range)
+    pub gain: MagGain,
+    /// Output data rate
+    pub data_rate: MagDataRate,
+}
+
+impl Default for MagConfig {
+    fn default() -> Self {
+        Self {
+            gain: MagGain::Gauss1_3,
+            data_rate: MagDataRate::Hz15,
+        }
+    }
+}
+
+/// Accepted accelerometer self-test output change in g at ±2g full scale,
+/// see [`LSM303DLHC::accel_self_test`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct AccelSelfTestLimits {
+    /// Smallest accepted change on each axis
+    pub min_g: f32,
+    /// Largest accepted change on each axis
+    pub max_g: f32,
+}
+
+impl Default for AccelSelfTestLimits {
+    /// 68-1440 mg, the LSM303AGR's limits (17-360 LSB at 4 mg/LSB)
+    ///
+    /// The LSM303DLHC datasheet does not specify a self-test output change, so these
+    /// come from the LSM303AGR, which uses a similar accelerometer core. Set limits
+    /// characterized on known-good boards for a production test.
+    fn default() -> Self {
+        Self {
+            min_g: 0.068,
+            max_g: 1.44,
+        }
+    }
 }
 
-/// 3-axis magnetic field data
-#[derive(Debug, Default, Clone, Copy)]
-pub struct MagneticField {
-    /// X-axis magnetic field in gauss
-    pub x: f32,
-    /// Y-axis magnetic field in gauss
-    pub y: f32,
-    /// Z-axis magnetic field in gauss
-    pub z: f32,
+/// Result of [`LSM303DLHC::accel_self_test`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct AccelSelfTestReport {
+    /// Average output with self-test disabled
+    pub baseline: Acceleration,
+    /// Average output with self-test enabled
+    pub self_test: Acceleration,
+    /// Absolute output change on each axis
+    pub delta: Acceleration,
+    /// Whether every axis changed by an amount within the accepted limits
+    pub passed: bool,
 }
 
 /// LSM303DLHC e-compass driver
@@ -206,6 +404,9 @@ pub struct LSM303DLHC<'a> {
     i2c: I2c<'a, embassy_stm32::mode::Blocking, i2c::Master>,
     accel_scale: AccelScale,
     mag_gain: MagGain,
+    accel_rate: AccelDataRate,
+    mag_rate: MagDataRate,
+    temp_cal: TemperatureCalibration,
 }
 
 impl<'a> LSM303DLHC<'a> {
@@ -219,11 +420,18 @@ impl<'a> LSM303DLHC<'a> {
         let config = I2cConfig::default();
         
         let i2c = I2c::new_blocking(i2c1, scl, sda, config);
-        
+        Self::from_i2c(i2c)
+    }
+    
+    /// Create a driver on an already configured I2C bus
+    pub(crate) fn from_i2c(i2c: I2c<'a, embassy_stm32::mode::Blocking, i2c::Master>) -> Self {
         let mut compass = Self {
             i2c,
             accel_scale: AccelScale::G2,
             mag_gain: MagGain::Gauss1_3,
+            accel_rate: AccelDataRate::Hz100,
+            mag_rate: MagDataRate::Hz15,
+            temp_cal: TemperatureCalibration::NONE,
         };
         
         // Initialize both sensors
@@ -274,12 +482,49 @@ impl<'a> LSM303DLHC<'a> {
     
     /// Set accelerometer data rate
     pub fn set_accel_data_rate(&mut self, rate: AccelDataRate) {
+        self.accel_rate = rate;
         let mut ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
         ctrl1 = (ctrl1 & 0x0F) | (rate as u8);
         self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
         debug!("Accelerometer data rate set to {:?}", rate);
     }
     
+    /// Configure and enable the accelerometer high-pass filter
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Remove gravity from the output registers, keep it for interrupt 1
+    /// compass.set_accel_high_pass(AccelHighPassConfig::default());
+    /// ```
+    pub fn set_accel_high_pass(&mut self, config: AccelHighPassConfig) {
+        let ctrl2 = config.mode as u8
+            | config.cutoff as u8
+            | if config.output { 0x08 } else { 0x00 }
+            | if config.click { 0x04 } else { 0x00 }
+            | if config.int2 { 0x02 } else { 0x00 }
+            | if config.int1 { 0x01 } else { 0x00 };
+        self.write_accel_register(accel_regs::CTRL_REG2_A, ctrl2);
+        debug!("Accelerometer high-pass filter set to {:?}", config);
+    }
+    
+    /// Disable the accelerometer high-pass filter on all outputs
+    pub fn disable_accel_high_pass(&mut self) {
+        self.write_accel_register(accel_regs::CTRL_REG2_A, 0x00);
+        debug!("Accelerometer high-pass filter disabled");
+    }
+    
+    /// Set the REFERENCE_A register used by [`AccelHighPassMode::Reference`]
+    pub fn set_accel_high_pass_reference(&mut self, reference: u8) {
+        self.write_accel_register(accel_regs::REFERENCE_A, reference);
+    }
+    
+    /// Reset the accelerometer high-pass filter (in [`AccelHighPassMode::NormalWithReset`])
+    ///
+    /// The filter settles to the current input, removing any DC component immediately.
+    pub fn reset_accel_high_pass(&mut self) {
+        self.read_accel_register(accel_regs::REFERENCE_A);
+    }
+    
     /// Set magnetometer gain
     pub fn set_mag_gain(&mut self, gain: MagGain) {
         self.mag_gain = gain;
@@ -289,12 +534,125 @@ impl<'a> LSM303DLHC<'a> {
     
     /// Set magnetometer data rate
     pub fn set_mag_data_rate(&mut self, rate: MagDataRate) {
+        self.mag_rate = rate;
         let mut cra = self.read_mag_register(mag_regs::CRA_REG_M);
-        cra = (cra & 0xE3) | (rate as u8);
+        cra = (cra & !CRA_DO) | (rate as u8);
         self.write_mag_register(mag_regs::CRA_REG_M, cra);
         debug!("Magnetometer data rate set to {:?}", rate);
     }
     
+    /// Raise INT1 (PE4) when the board moves
+    ///
+    /// Routes the high-pass filtered acceleration to interrupt generator 1, so gravity
+    /// is ignored, and latches INT1 until [`clear_motion_interrupt`](Self::clear_motion_interrupt)
+    /// is called. Used as a wake source by [`crate::power`].
+    ///
+    /// # Arguments
+    /// * `threshold_g` - Acceleration change that triggers the interrupt, in g
+    ///
+    /// # Note
+    /// This replaces the high-pass filter configuration set by
+    /// [`set_accel_high_pass`](Self::set_accel_high_pass).
+    pub fn enable_motion_interrupt(&mut self, threshold_g: f32) {
+        // 1 LSB = 16/32/62/186 mg at ±2/4/8/16g (as for ST's LIS3DH core)
+        let lsb_mg = match self.accel_scale {
+            AccelScale::G2 => 16.0,
+            AccelScale::G4 => 32.0,
+            AccelScale::G8 => 62.0,
+            AccelScale::G16 => 186.0,
+        };
+        let threshold = ((threshold_g * 1000.0 / lsb_mg) as u8).clamp(1, 0x7F);
+        
+        self.set_accel_high_pass(AccelHighPassConfig {
+            output: false,
+            int1: true,
+            ..AccelHighPassConfig::default()
+        });
+        self.write_accel_register(accel_regs::INT1_THS_A, threshold);
+        self.write_accel_register(accel_regs::INT1_DURATION_A, 0x00);
+        self.write_accel_register(accel_regs::INT1_CFG_A, INT1_CFG_XYZ_HIGH);
+        
+        let ctrl5 = self.read_accel_register(accel_regs::CTRL_REG5_A);
+        self.write_accel_register(accel_regs::CTRL_REG5_A, ctrl5 | CTRL5_LIR_INT1);
+        self.write_accel_register(accel_regs::CTRL_REG3_A, CTRL3_I1_AOI1);
+        
+        self.clear_motion_interrupt();
+        debug!("Motion interrupt enabled, threshold {} LSB", threshold);
+    }
+    
+    /// Stop raising INT1 on motion and disable the high-pass filter
+    pub fn disable_motion_interrupt(&mut self) {
+        self.write_accel_register(accel_regs::CTRL_REG3_A, 0x00);
+        self.write_accel_register(accel_regs::INT1_CFG_A, 0x00);
+        let ctrl5 = self.read_accel_register(accel_regs::CTRL_REG5_A);
+        self.write_accel_register(accel_regs::CTRL_REG5_A, ctrl5 & !CTRL5_LIR_INT1);
+        self.disable_accel_high_pass();
+        self.clear_motion_interrupt();
+    }
+    
+    /// Clear a latched motion interrupt
+    ///
+    /// Returns `true` if motion was detected since the last call.
+    pub fn clear_motion_interrupt(&mut self) -> bool {
+        self.read_accel_register(accel_regs::INT1_SRC_A) & INT1_SRC_IA != 0
+    }
+    
+    /// Put the magnetometer to sleep or back into continuous conversion
+    ///
+    /// The accelerometer keeps running, e.g. to wake the MCU on motion.
+    pub fn set_mag_sleep(&mut self, sleep: bool) {
+        let mode = if sleep { MAG_SLEEP } else { MAG_CONTINUOUS };
+        self.write_mag_register(mag_regs::MR_REG_M, mode);
+        debug!("Magnetometer {}", if sleep { "asleep" } else { "awake" });
+    }
+    
+    /// Power down both sensors
+    ///
+    /// The accelerometer enters power-down mode and the magnetometer sleeps, for
+    /// about 1 µA in total. Register contents are kept.
+    pub fn power_down(&mut self) {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1 & !CTRL1_A_ODR);
+        self.write_mag_register(mag_regs::MR_REG_M, MAG_SLEEP);
+        debug!("LSM303DLHC powered down");
+    }
+    
+    /// Return to normal operation after [`power_down`](Self::power_down) or
+    /// [`low_power_sampling`](Self::low_power_sampling)
+    ///
+    /// Restores the configured data rates and high-resolution accelerometer output.
+    /// The accelerometer needs 7 samples to settle in normal mode.
+    pub fn power_up(&mut self) {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        let ctrl1 = (ctrl1 & !(CTRL1_A_ODR | CTRL1_A_LPEN)) | self.accel_rate as u8;
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
+        let ctrl4 = self.read_accel_register(accel_regs::CTRL_REG4_A);
+        self.write_accel_register(accel_regs::CTRL_REG4_A, ctrl4 | CTRL4_A_HR);
+        
+        let cra = self.read_mag_register(mag_regs::CRA_REG_M);
+        self.write_mag_register(mag_regs::CRA_REG_M, (cra & !CRA_DO) | self.mag_rate as u8);
+        self.write_mag_register(mag_regs::MR_REG_M, MAG_CONTINUOUS);
+        debug!("LSM303DLHC powered up");
+    }
+    
+    /// Keep sampling at a reduced rate and resolution
+    ///
+    /// The accelerometer runs in low-power mode (8-bit output) at 10 Hz and the
+    /// magnetometer at 0.75 Hz. Readings keep their units. The configured data rates
+    /// are remembered and restored by [`power_up`](Self::power_up).
+    pub fn low_power_sampling(&mut self) {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        let ctrl1 = (ctrl1 & !CTRL1_A_ODR) | AccelDataRate::Hz10 as u8 | CTRL1_A_LPEN;
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
+        let ctrl4 = self.read_accel_register(accel_regs::CTRL_REG4_A);
+        self.write_accel_register(accel_regs::CTRL_REG4_A, ctrl4 & !CTRL4_A_HR);
+        
+        let cra = self.read_mag_register(mag_regs::CRA_REG_M);
+        self.write_mag_register(mag_regs::CRA_REG_M, (cra & !CRA_DO) | MagDataRate::Hz0_75 as u8);
+        self.write_mag_register(mag_regs::MR_REG_M, MAG_CONTINUOUS);
+        debug!("LSM303DLHC in low-power sampling");
+    }
+    
     /// Check if new acceleration data is available
     pub fn accel_data_ready(&mut self) -> bool {
         let status = self.read_accel_register(accel_regs::STATUS_REG_A);
@@ -328,6 +686,89 @@ impl<'a> LSM303DLHC<'a> {
         }
     }
     
+    /// Run the accelerometer built-in self-test
+    ///
+    /// Averages `samples` readings with the self-test actuation off and on (at ±2g, 50 Hz),
+    /// then checks that each axis moved by an amount within `limits`. Keep the board still
+    /// while the test runs. The previous scale and data rate are restored afterwards.
+    ///
+    /// Returns [`SelfTestError::DataTimeout`] if the accelerometer stops producing data.
+    ///
+    /// # Note
+    /// The LSM303DLHC datasheet marks the ST bits of CTRL_REG4_A as reserved; they behave
+    /// like those of the other ST accelerometers built on the same core. It gives no
+    /// self-test limits, so [`AccelSelfTestLimits::default`] is not a datasheet min/max.
+    pub async fn accel_self_test(
+        &mut self,
+        samples: u8,
+        limits: AccelSelfTestLimits,
+    ) -> Result<AccelSelfTestReport, SelfTestError> {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        let ctrl2 = self.read_accel_register(accel_regs::CTRL_REG2_A);
+        let ctrl4 = self.read_accel_register(accel_regs::CTRL_REG4_A);
+        let scale = self.accel_scale;
+        
+        // 50 Hz with all axes enabled, ±2g, block data update, high resolution, no high-pass filter
+        self.write_accel_register(accel_regs::CTRL_REG1_A, 0x47);
+        self.write_accel_register(accel_regs::CTRL_REG2_A, 0x00);
+        self.accel_scale = AccelScale::G2;
+        self.write_accel_register(accel_regs::CTRL_REG4_A, 0x88);
+        let result = self.measure_accel_self_test(samples).await;
+        
+        // Restore the previous configuration
+        self.write_accel_register(accel_regs::CTRL_REG4_A, ctrl4);
+        self.write_accel_register(accel_regs::CTRL_REG2_A, ctrl2);
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
+        self.accel_scale = scale;
+        
+        let (baseline, self_test) = result.inspect_err(|e| warn!("Accelerometer self-test failed: {}", e))?;
+        let delta = (self_test - baseline).map(f32::abs);
+        let passed = [delta.x, delta.y, delta.z]
+            .iter()
+            .all(|d| (limits.min_g..=limits.max_g).contains(d));
+        
+        info!("Accelerometer self-test {}: {}", if passed { "passed" } else { "FAILED" }, delta);
+        Ok(AccelSelfTestReport {
+            baseline,
+            self_test,
+            delta,
+            passed,
+        })
+    }
+    
+    /// Average accelerometer readings with the self-test actuation off, then on
+    async fn measure_accel_self_test(&mut self, samples: u8) -> Result<(Acceleration, Acceleration), SelfTestError> {
+        Timer::after_millis(90).await;
+        let baseline = self.read_accel_averaged(samples).await?;
+        
+        self.write_accel_register(accel_regs::CTRL_REG4_A, 0x88 | CTRL4_A_ST0);
+        Timer::after_millis(90).await;
+        let self_test = self.read_accel_averaged(samples).await?;
+        Ok((baseline, self_test))
+    }
+    
+    /// Average `samples` fresh acceleration readings, discarding the first one
+    async fn read_accel_averaged(&mut self, samples: u8) -> Result<Acceleration, SelfTestError> {
+        let samples = samples.max(1);
+        let mut sum = Acceleration::default();
+        
+        for i in 0..=samples {
+            with_timeout(SELF_TEST_DATA_TIMEOUT, async {
+                while !self.accel_data_ready() {
+                    Timer::after_millis(1).await;
+                }
+            })
+            .await
+            .map_err(|_| SelfTestError::DataTimeout)?;
+            let accel = self.read_acceleration();
+            if i > 0 {
+                sum += accel;
+            }
+        }
+        
+        Ok(sum / samples as f32)
+    }
+    
     /// Read magnetic field data
     pub fn read_magnetic_field(&mut self) -> MagneticField {
         // Read all 6 bytes
@@ -351,8 +792,36 @@ impl<'a> LSM303DLHC<'a> {
         }
     }
     
-    /// Read magnetometer temperature
-    pub fn read_temperature(&mut self) -> i16 {
+    /// Enable or disable the temperature sensor (enabled by default)
+    pub fn set_temperature_enabled(&mut self, enabled: bool) {
+        let mut cra = self.read_mag_register(mag_regs::CRA_REG_M);
+        if enabled {
+            cra |= TEMP_EN;
+        } else {
+            cra &= !TEMP_EN;
+        }
+        self.write_mag_register(mag_regs::CRA_REG_M, cra);
+        debug!("Temperature sensor enabled: {}", enabled);
+    }
+    
+    /// Set the one-point calibration applied by [`Self::read_temperature`]
+    pub fn set_temperature_calibration(&mut self, cal: TemperatureCalibration) {
+        self.temp_cal = cal;
+        debug!("LSM303DLHC temperature offset set to {} °C", cal.offset);
+    }
+    
+    /// Read magnetometer temperature in °C
+    ///
+    /// Uncalibrated readings are relative to a nominal 25 °C, see [`crate::temperature`].
+    /// The temperature sensor must be enabled.
+    pub fn read_temperature(&mut self) -> Temperature {
+        let raw = self.read_temperature_raw();
+        let celsius = NOMINAL_CELSIUS + raw as f32 / TEMP_SENSITIVITY;
+        self.temp_cal.apply(Temperature::from_celsius(celsius))
+    }
+    
+    /// Read magnetometer temperature (raw 12-bit value, 8 LSB/°C)
+    pub fn read_temperature_raw(&mut self) -> i16 {
         let high = self.read_mag_register(mag_regs::TEMP_OUT_H_M);
         let low = self.read_mag_register(mag_regs::TEMP_OUT_L_M);
         i16::from_be_bytes([high, low]) >> 4 // 12-bit resolution
@@ -403,3 +872,37 @@ impl<'a> LSM303DLHC<'a> {
         self.i2c.blocking_write(MAG_ADDR, &[reg, value]).ok();
     }
 }
+
+impl Accelerometer for LSM303DLHC<'_> {
+    type AccelConfig = AccelConfig;
+    
+    fn configure_accel(&mut self, config: AccelConfig) {
+        self.set_accel_scale(config.scale);
+        self.set_accel_data_rate(config.data_rate);
+    }
+    
+    fn accel_data_ready(&mut self) -> bool {
+        LSM303DLHC::accel_data_ready(self)
+    }
+    
+    fn read_acceleration(&mut self) -> Acceleration {
+        LSM303DLHC::read_acceleration(self)
+    }
+}
+
+impl Magnetometer for LSM303DLHC<'_> {
+    type MagConfig = MagConfig;
+    
+    fn configure_mag(&mut self, config: MagConfig) {
+        self.set_mag_gain(config.gain);
+        self.set_mag_data_rate(config.data_rate);
+    }
+    
+    fn mag_data_ready(&mut self) -> bool {
+        LSM303DLHC::mag_data_ready(self)
+    }
+    
+    fn read_magnetic_field(&mut self) -> MagneticField {
+        LSM303DLHC::read_magnetic_field(self)
+    }
+}
@@ -0,0 +1,367 @@
+//! Audio processing blocks
+//!
+//! Small real-time filters for mono audio in blocks of `f32` samples, full scale
+//! being ±1.0. Blocks implement [`Processor`] and are combined into a chain as a
+//! tuple or array, processed in order; an `Option` can be switched off.
+//!
+//! - [`Gain`]: fixed gain in dB
+//! - [`Biquad`]: second-order filter (low/high-pass, band-pass, notch, peaking, shelves)
+//! - [`NoiseGate`]: mutes the signal while it stays below a threshold
+//!
+//! # Example
+//! ```ignore
+//! const RATE: u32 = 16_000;
+//!
+//! let mut chain = (
+//!     Biquad::high_pass(RATE, 100.0, 0.707),
+//!     Biquad::peaking(RATE, 2_500.0, 1.0, 6.0),
+//!     NoiseGate::new(RATE, GateSettings::default()),
+//!     Gain::from_db(20.0),
+//! );
+//! chain.process(&mut block);
+//! ```
+
+use embassy_time::Duration;
+// Host builds use std's float methods instead (see `host-tests/`)
+#[cfg(target_os = "none")]
+use micromath::F32Ext;
+
+/// A processing block
+pub trait Processor {
+    /// Process `samples` in place
+    fn process(&mut self, samples: &mut [f32]);
+
+    /// Clear the state kept between blocks
+    fn reset(&mut self) {}
+}
+
+impl<P: Processor + ?Sized> Processor for &mut P {
+    fn process(&mut self, samples: &mut [f32]) {
+        (**self).process(samples)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// A block that can be bypassed with `None`
+impl<P: Processor> Processor for Option<P> {
+    fn process(&mut self, samples: &mut [f32]) {
+        if let Some(p) = self {
+            p.process(samples);
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Some(p) = self {
+            p.reset();
+        }
+    }
+}
+
+/// Blocks of the same type in series, e.g. a multi-band equalizer
+impl<P: Processor, const N: usize> Processor for [P; N] {
+    fn process(&mut self, samples: &mut [f32]) {
+        for p in self.iter_mut() {
+            p.process(samples);
+        }
+    }
+
+    fn reset(&mut self) {
+        for p in self.iter_mut() {
+            p.reset();
+        }
+    }
+}
+
+/// Blocks of different types in series
+macro_rules! chain {
+    ($($p:ident $i:tt),+) => {
+        impl<$($p: Processor),+> Processor for ($($p,)+) {
+            fn process(&mut self, samples: &mut [f32]) {
+                $(self.$i.process(samples);)+
+            }
+
+            fn reset(&mut self) {
+                $(self.$i.reset();)+
+            }
+        }
+    };
+}
+
+chain!(A 0);
+chain!(A 0, B 1);
+chain!(A 0, B 1, C 2);
+chain!(A 0, B 1, C 2, D 3);
+chain!(A 0, B 1, C 2, D 3, E 4);
+chain!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Convert dB to a linear factor
+fn db_to_linear(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+/// Fixed gain
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Gain(f32);
+
+impl Gain {
+    /// Gain of 0 dB
+    pub const UNITY: Self = Self(1.0);
+
+    /// Gain in dB (negative to attenuate)
+    pub fn from_db(db: f32) -> Self {
+        Self(db_to_linear(db))
+    }
+
+    /// Linear factor
+    pub fn factor(&self) -> f32 {
+        self.0
+    }
+
+    /// Gain in dB
+    pub fn db(&self) -> f32 {
+        20.0 * self.0.log10()
+    }
+}
+
+impl Processor for Gain {
+    fn process(&mut self, samples: &mut [f32]) {
+        for s in samples {
+            *s *= self.0;
+        }
+    }
+}
+
+/// Second-order IIR filter
+///
+/// Coefficients follow the Audio EQ Cookbook (R. Bristow-Johnson); the filter runs
+/// in transposed direct form II. `q` sets the bandwidth: 0.707 gives a flat
+/// (Butterworth) low- or high-pass, higher values a narrower band.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Filter from normalized coefficients (`a0` = 1)
+    pub const fn new(b0: f32, b1: f32, b2: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Remove frequencies above `cutoff` Hz
+    pub fn low_pass(sample_rate: u32, cutoff: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, cutoff, q);
+        let b = (1.0 - cos) / 2.0;
+        Self::normalized([b, 1.0 - cos, b], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    /// Remove frequencies below `cutoff` Hz
+    pub fn high_pass(sample_rate: u32, cutoff: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, cutoff, q);
+        let b = (1.0 + cos) / 2.0;
+        Self::normalized([b, -(1.0 + cos), b], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    /// Keep a band around `center` Hz (0 dB peak gain)
+    pub fn band_pass(sample_rate: u32, center: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, center, q);
+        Self::normalized([alpha, 0.0, -alpha], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    /// Remove a narrow band around `center` Hz, e.g. mains hum
+    pub fn notch(sample_rate: u32, center: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, center, q);
+        Self::normalized([1.0, -2.0 * cos, 1.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    /// Boost or cut a band around `center` Hz by `gain_db`
+    pub fn peaking(sample_rate: u32, center: f32, q: f32, gain_db: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, center, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        Self::normalized(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    /// Boost or cut frequencies below `corner` Hz by `gain_db`
+    pub fn low_shelf(sample_rate: u32, corner: f32, gain_db: f32) -> Self {
+        let (a, cos, beta) = Self::shelf(sample_rate, corner, gain_db);
+        Self::normalized(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + beta),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - beta),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + beta,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - beta,
+            ],
+        )
+    }
+
+    /// Boost or cut frequencies above `corner` Hz by `gain_db`
+    pub fn high_shelf(sample_rate: u32, corner: f32, gain_db: f32) -> Self {
+        let (a, cos, beta) = Self::shelf(sample_rate, corner, gain_db);
+        Self::normalized(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + beta),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - beta),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + beta,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - beta,
+            ],
+        )
+    }
+
+    /// Process one sample
+    pub fn tick(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// cos(w0) and alpha for a frequency and Q
+    fn prewarp(sample_rate: u32, frequency: f32, q: f32) -> (f32, f32) {
+        let w0 = 2.0 * core::f32::consts::PI * frequency / sample_rate as f32;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    /// A, cos(w0) and 2·sqrt(A)·alpha for a shelf with slope 1
+    fn shelf(sample_rate: u32, corner: f32, gain_db: f32) -> (f32, f32, f32) {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::prewarp(sample_rate, corner, core::f32::consts::FRAC_1_SQRT_2);
+        (a, cos, 2.0 * a.sqrt() * alpha)
+    }
+
+    /// Divide the coefficients by `a0`
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self::new(b[0] / a[0], b[1] / a[0], b[2] / a[0], a[1] / a[0], a[2] / a[0])
+    }
+}
+
+impl Processor for Biquad {
+    fn process(&mut self, samples: &mut [f32]) {
+        for s in samples {
+            *s = self.tick(*s);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+/// Noise gate settings
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct GateSettings {
+    /// Level that opens the gate, in dB below full scale
+    pub threshold_db: f32,
+    /// Fade-in time when the gate opens
+    pub attack: Duration,
+    /// Time the gate stays open after the level drops
+    pub hold: Duration,
+    /// Fade-out time when the gate closes
+    pub release: Duration,
+}
+
+impl Default for GateSettings {
+    /// -50 dB threshold, 1 ms attack, 100 ms hold, 150 ms release
+    fn default() -> Self {
+        Self {
+            threshold_db: -50.0,
+            attack: Duration::from_millis(1),
+            hold: Duration::from_millis(100),
+            release: Duration::from_millis(150),
+        }
+    }
+}
+
+/// Time constant of the gate's level detector
+const GATE_DETECTOR: Duration = Duration::from_millis(10);
+
+/// Mutes background noise between louder sounds
+///
+/// The gate opens when the peak level rises above the threshold and closes once
+/// it has stayed below for the hold time, fading in and out to avoid clicks.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct NoiseGate {
+    threshold: f32,
+    attack_step: f32,
+    release_step: f32,
+    hold_samples: u32,
+    decay: f32,
+    level: f32,
+    gain: f32,
+    hold: u32,
+}
+
+impl NoiseGate {
+    /// Create a closed gate for audio at `sample_rate` Hz
+    pub fn new(sample_rate: u32, settings: GateSettings) -> Self {
+        let samples = |d: Duration| (d.as_micros() * sample_rate as u64 / 1_000_000) as u32;
+        let step = |d: Duration| 1.0 / samples(d).max(1) as f32;
+        Self {
+            threshold: db_to_linear(settings.threshold_db),
+            attack_step: step(settings.attack),
+            release_step: step(settings.release),
+            hold_samples: samples(settings.hold).max(1),
+            decay: (-1.0 / samples(GATE_DETECTOR).max(1) as f32).exp(),
+            level: 0.0,
+            gain: 0.0,
+            hold: 0,
+        }
+    }
+
+    /// Check whether the gate lets the signal through
+    pub fn is_open(&self) -> bool {
+        self.gain > 0.0
+    }
+}
+
+impl Processor for NoiseGate {
+    fn process(&mut self, samples: &mut [f32]) {
+        for s in samples {
+            self.level = s.abs().max(self.level * self.decay);
+            if self.level >= self.threshold {
+                self.hold = self.hold_samples;
+            }
+
+            if self.hold > 0 {
+                self.hold -= 1;
+                self.gain = (self.gain + self.attack_step).min(1.0);
+            } else {
+                self.gain = (self.gain - self.release_step).max(0.0);
+            }
+            *s *= self.gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.level = 0.0;
+        self.gain = 0.0;
+        self.hold = 0;
+    }
+}
@@ -0,0 +1,223 @@
+//! Button gesture recognition
+//!
+//! Turns debounced press/release changes of the user button into higher-level
+//! [`ButtonEvent`]s, so a single button can drive a small UI:
+//!
+//! - **Click**: a short press, reported once the double-click window has passed
+//! - **DoubleClick**: two short presses within the double-click window
+//! - **LongPress**: the button held for the long-press time
+//! - **Hold(n)**: repeated every hold-repeat interval while still held after a long press
+//!
+//! [`GestureDecoder`] is a pure state machine driven by timestamps, so it can be fed
+//! a simulated timeline, as the host tests do. [`gesture_task`] runs it on an
+//! [`ExtiButton`] and sends the events to a [`Channel`].
+//!
+//! # Example
+//! ```ignore
+//! static EVENTS: ButtonEvents = ButtonEvents::new();
+//!
+//! let button = ExtiButton::new(p.PA0, p.EXTI0);
+//! spawner.spawn(gesture_task(button, GestureConfig::default(), &EVENTS)).unwrap();
+//!
+//! match EVENTS.receive().await {
+//!     ButtonEvent::Click => next_mode(),
+//!     ButtonEvent::LongPress => power_off(),
+//!     _ => {}
+//! }
+//! ```
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Instant};
+// The button task is left out of host builds (see `host-tests/`)
+#[cfg(target_os = "none")]
+use {
+    crate::button::ExtiButton,
+    defmt::{debug, warn},
+    embassy_sync::{blocking_mutex::raw::RawMutex, channel::Sender},
+    embassy_time::with_deadline,
+};
+
+/// A recognized button gesture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ButtonEvent {
+    /// Single short press
+    Click,
+    /// Two short presses in quick succession
+    DoubleClick,
+    /// Button held for the long-press time
+    LongPress,
+    /// Button still held after a long press; counts up from 1
+    Hold(u16),
+}
+
+/// Gesture timings
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct GestureConfig {
+    /// Maximum time from releasing the first click to pressing the second
+    pub double_click: Duration,
+    /// Time the button must be held to count as a long press
+    pub long_press: Duration,
+    /// Interval between [`ButtonEvent::Hold`] events
+    pub hold_repeat: Duration,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            double_click: Duration::from_millis(300),
+            long_press: Duration::from_millis(800),
+            hold_repeat: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Decoder state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+enum State {
+    /// Released, nothing pending
+    Idle,
+    /// Pressed; `second` if this is the second press of a possible double click
+    Pressed { since: Instant, second: bool },
+    /// Released after a short press, waiting for a second one
+    Released { at: Instant },
+    /// Held past the long-press time
+    Held { next: Instant, count: u16 },
+}
+
+/// Gesture timing state machine
+///
+/// Call [`on_change`](Self::on_change) for every debounced button change and
+/// [`poll`](Self::poll) when [`deadline`](Self::deadline) is reached.
+///
+/// # Note
+/// Single clicks are only reported after the double-click window expires. If the
+/// second press of a double click turns into a long press, only the long press is
+/// reported.
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct GestureDecoder {
+    config: GestureConfig,
+    state: State,
+}
+
+impl GestureDecoder {
+    /// Create a decoder with the button released
+    pub const fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            state: State::Idle,
+        }
+    }
+
+    /// Timings in use
+    pub fn config(&self) -> GestureConfig {
+        self.config
+    }
+
+    /// Feed a debounced button change
+    ///
+    /// # Arguments
+    /// * `pressed` - New button state
+    /// * `now` - Time of the change
+    pub fn on_change(&mut self, pressed: bool, now: Instant) -> Option<ButtonEvent> {
+        // Timeouts that passed before this change take effect first
+        let timed_out = self.poll(now);
+
+        let (state, event) = match (self.state, pressed) {
+            (State::Idle, true) => (State::Pressed { since: now, second: false }, None),
+            (State::Released { .. }, true) => (State::Pressed { since: now, second: true }, None),
+            (State::Pressed { second: false, .. }, false) => (State::Released { at: now }, None),
+            (State::Pressed { second: true, .. }, false) => {
+                (State::Idle, Some(ButtonEvent::DoubleClick))
+            }
+            (State::Held { .. }, false) => (State::Idle, None),
+            // Repeated state, e.g. after a missed change
+            (state, _) => (state, None),
+        };
+        self.state = state;
+
+        timed_out.or(event)
+    }
+
+    /// Handle timeouts at time `now`
+    pub fn poll(&mut self, now: Instant) -> Option<ButtonEvent> {
+        match self.state {
+            State::Released { at } if now >= at + self.config.double_click => {
+                self.state = State::Idle;
+                Some(ButtonEvent::Click)
+            }
+            State::Pressed { since, .. } if now >= since + self.config.long_press => {
+                self.state = State::Held {
+                    next: since + self.config.long_press + self.config.hold_repeat,
+                    count: 0,
+                };
+                Some(ButtonEvent::LongPress)
+            }
+            State::Held { next, count } if now >= next => {
+                let count = count.saturating_add(1);
+                self.state = State::Held {
+                    next: next + self.config.hold_repeat,
+                    count,
+                };
+                Some(ButtonEvent::Hold(count))
+            }
+            _ => None,
+        }
+    }
+
+    /// Time of the next timeout, if any
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.state {
+            State::Idle => None,
+            State::Pressed { since, .. } => Some(since + self.config.long_press),
+            State::Released { at } => Some(at + self.config.double_click),
+            State::Held { next, .. } => Some(next),
+        }
+    }
+}
+
+/// Channel for button events, sized for a few unread events
+pub type ButtonEvents = Channel<CriticalSectionRawMutex, ButtonEvent, 4>;
+
+/// Decode gestures from `button` and send them to `events` forever
+///
+/// Events are dropped with a warning if the channel is full.
+#[cfg(target_os = "none")]
+pub async fn run_gestures<M: RawMutex, const N: usize>(
+    button: &mut ExtiButton<'_>,
+    config: GestureConfig,
+    events: Sender<'_, M, ButtonEvent, N>,
+) -> ! {
+    let mut decoder = GestureDecoder::new(config);
+
+    loop {
+        let event = match decoder.deadline() {
+            Some(deadline) => match with_deadline(deadline, button.wait_for_change()).await {
+                Ok(pressed) => decoder.on_change(pressed, Instant::now()),
+                Err(_) => decoder.poll(Instant::now()),
+            },
+            None => {
+                let pressed = button.wait_for_change().await;
+                decoder.on_change(pressed, Instant::now())
+            }
+        };
+
+        if let Some(event) = event {
+            debug!("Button event: {}", event);
+            if events.try_send(event).is_err() {
+                warn!("Button event channel full, dropping {}", event);
+            }
+        }
+    }
+}
+
+/// Embassy task decoding gestures from the user button into `events`
+#[cfg(target_os = "none")]
+#[embassy_executor::task]
+pub async fn gesture_task(
+    mut button: ExtiButton<'static>,
+    config: GestureConfig,
+    events: &'static ButtonEvents,
+) {
+    run_gestures(&mut button, config, events.sender()).await
+}
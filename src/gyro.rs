@@ -3,6 +3,9 @@
 //! The L3GD20 is a low-power three-axis angular rate sensor with a digital SPI interface.
 //! It provides 16-bit rate value for each axis.
 //!
+//! Revision D boards carry an I3G4250D instead, which is register-compatible.
+//! The driver detects it by its WHO_AM_I value, see [`L3GD20::model`].
+//!
 //! ## Features
 //! - 3-axis angular rate sensor
 //! - ±250/±500/±2000 dps full scale
@@ -17,11 +20,15 @@
 //!
 //! [Datasheet](https://www.st.com/resource/en/datasheet/l3gd20.pdf)
 
-use defmt::{debug, info};
+use defmt::{debug, info, warn};
 use embassy_stm32::gpio::{Level, Output, Speed};
 use embassy_stm32::spi::{Config, Spi};
 use embassy_stm32::time::Hertz;
 use embassy_stm32::{spi, Peri};
+use embassy_time::{with_timeout, Duration, Timer};
+
+use crate::sensor::{Gyroscope, SelfTestError, Vector3};
+use crate::temperature::{Temperature, TemperatureCalibration, NOMINAL_CELSIUS};
 
 /// L3GD20 register addresses
 #[allow(dead_code)]
@@ -43,8 +50,61 @@ mod regs {
     pub const OUT_Z_H: u8 = 0x2D;
 }
 
+/// Temperature sensor sensitivity in LSB/°C
+const TEMP_SENSITIVITY: f32 = -1.0;
+
+/// CTRL_REG1 power-down control bit (1 = normal mode)
+const CTRL1_PD: u8 = 0x08;
+
+/// CTRL_REG1 X, Y and Z axis enable bits
+const CTRL1_AXES: u8 = 0x07;
+
+/// CTRL_REG5 high-pass filter enable bit
+const CTRL5_HPEN: u8 = 0x10;
+
+/// CTRL_REG4 self-test 0 (positive sign) bits
+const CTRL4_ST0: u8 = 0x02;
+
+/// Longest wait for a sample during the self-test (about 5 periods at 95 Hz)
+const SELF_TEST_DATA_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Gyroscope part, identified by its WHO_AM_I value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum GyroModel {
+    /// L3GD20 (revision B boards)
+    L3GD20,
+    /// L3GD20H
+    L3GD20H,
+    /// I3G4250D (revision D boards)
+    I3G4250D,
+    /// Unrecognized WHO_AM_I value
+    Unknown(u8),
+}
+
+impl GyroModel {
+    /// Identify the part from its WHO_AM_I value
+    pub fn from_who_am_i(who_am_i: u8) -> Self {
+        match who_am_i {
+            0xD4 => GyroModel::L3GD20,
+            0xD7 => GyroModel::L3GD20H,
+            0xD3 => GyroModel::I3G4250D,
+            other => GyroModel::Unknown(other),
+        }
+    }
+    
+    /// Output data rate in Hz for a data rate setting
+    ///
+    /// The I3G4250D runs each setting slightly faster than the L3GD20.
+    pub fn data_rate_hz(&self, rate: DataRate) -> f32 {
+        match self {
+            GyroModel::I3G4250D => [105.0, 208.0, 420.0, 840.0][((rate as u8) >> 6) as usize],
+            _ => rate.hz(),
+        }
+    }
+}
+
 /// Full scale selection
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum FullScale {
     /// ±250 degrees per second
     Dps250 = 0x00,
@@ -66,7 +126,7 @@ impl FullScale {
 }
 
 /// Output data rate and bandwidth selection
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum DataRate {
     /// 95 Hz, 12.5 Hz cutoff
     Hz95 = 0x00,
@@ -98,22 +158,176 @@ pub enum DataRate {
     Hz760_100 = 0xF0,
 }
 
-/// 3-axis angular rate data
-#[derive(Debug, Default, Clone, Copy)]
-pub struct AngularRate {
-    /// X-axis angular rate in degrees per second
-    pub x: f32,
-    /// Y-axis angular rate in degrees per second
-    pub y: f32,
-    /// Z-axis angular rate in degrees per second
-    pub z: f32,
+impl DataRate {
+    /// Output data rate in Hz on the L3GD20 (see [`GyroModel::data_rate_hz`])
+    pub fn hz(&self) -> f32 {
+        match (*self as u8) >> 6 {
+            0 => 95.0,
+            1 => 190.0,
+            2 => 380.0,
+            _ => 760.0,
+        }
+    }
+}
+
+/// High-pass filter mode
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum HighPassMode {
+    /// Normal mode, reading the REFERENCE register resets the filter
+    NormalWithReset = 0x00,
+    /// Output is the input minus the REFERENCE register value
+    Reference = 0x10,
+    /// Normal mode
+    Normal = 0x20,
+    /// Filter resets automatically on an interrupt event
+    AutoResetOnInterrupt = 0x30,
+}
+
+/// High-pass filter cutoff selection
+///
+/// The actual cutoff frequency scales with the output data rate,
+/// see [`HighPassCutoff::frequency`]. Values below are for 95 / 760 Hz.
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum HighPassCutoff {
+    /// 7.2 Hz / 51.4 Hz
+    Hpcf0 = 0x00,
+    /// 3.5 Hz / 27 Hz
+    Hpcf1 = 0x01,
+    /// 1.8 Hz / 13.5 Hz
+    Hpcf2 = 0x02,
+    /// 0.9 Hz / 7.2 Hz
+    Hpcf3 = 0x03,
+    /// 0.45 Hz / 3.5 Hz
+    Hpcf4 = 0x04,
+    /// 0.18 Hz / 1.8 Hz
+    Hpcf5 = 0x05,
+    /// 0.09 Hz / 0.9 Hz
+    Hpcf6 = 0x06,
+    /// 0.045 Hz / 0.45 Hz
+    Hpcf7 = 0x07,
+    /// 0.018 Hz / 0.18 Hz
+    Hpcf8 = 0x08,
+    /// 0.009 Hz / 0.09 Hz
+    Hpcf9 = 0x09,
+}
+
+impl HighPassCutoff {
+    /// Cutoff frequency in Hz at the given output data rate (datasheet table 26)
+    pub fn frequency(&self, rate: DataRate) -> f32 {
+        const TABLE: [[f32; 4]; 10] = [
+            [7.2, 13.5, 27.0, 51.4],
+            [3.5, 7.2, 13.5, 27.0],
+            [1.8, 3.5, 7.2, 13.5],
+            [0.9, 1.8, 3.5, 7.2],
+            [0.45, 0.9, 1.8, 3.5],
+            [0.18, 0.45, 0.9, 1.8],
+            [0.09, 0.18, 0.45, 0.9],
+            [0.045, 0.09, 0.18, 0.45],
+            [0.018, 0.045, 0.09, 0.18],
+            [0.009, 0.018, 0.045, 0.09],
+        ];
+        TABLE[*self as usize][((rate as u8) >> 6) as usize]
+    }
+}
+
+/// Filter chain feeding the output registers/FIFO or the interrupt generator
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum FilterPath {
+    /// Low-pass filter 1 only (high-pass filter bypassed)
+    LowPass1 = 0x00,
+    /// Low-pass filter 1 and high-pass filter
+    HighPass = 0x01,
+    /// Low-pass filter 1, high-pass filter and low-pass filter 2
+    HighPassLowPass2 = 0x02,
+}
+
+/// High-pass filter configuration
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct HighPassConfig {
+    /// Filter mode
+    pub mode: HighPassMode,
+    /// Cutoff frequency selection
+    pub cutoff: HighPassCutoff,
+    /// Path for the output registers and FIFO
+    pub output: FilterPath,
+    /// Path for the interrupt generator
+    pub interrupt: FilterPath,
+}
+
+impl Default for HighPassConfig {
+    fn default() -> Self {
+        Self {
+            mode: HighPassMode::Normal,
+            cutoff: HighPassCutoff::Hpcf0,
+            output: FilterPath::HighPassLowPass2,
+            interrupt: FilterPath::LowPass1,
+        }
+    }
+}
+
+/// 3-axis angular rate data in degrees per second
+pub type AngularRate = Vector3;
+
+/// Gyroscope configuration applied with [`Gyroscope::configure_gyro`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct GyroConfig {
+    /// Full scale range
+    pub scale: FullScale,
+    /// Output data rate and bandwidth
+    pub data_rate: DataRate,
+}
+
+impl Default for GyroConfig {
+    fn default() -> Self {
+        Self {
+            scale: FullScale::Dps250,
+            data_rate: DataRate::Hz95,
+        }
+    }
+}
+
+/// Accepted self-test output change in dps at ±2000 dps full scale, see [`L3GD20::self_test`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct SelfTestLimits {
+    /// Smallest accepted change on each axis
+    pub min_dps: f32,
+    /// Largest accepted change on each axis
+    pub max_dps: f32,
+}
+
+impl Default for SelfTestLimits {
+    /// 265-795 dps, ±50% around the typical change of 530 dps
+    ///
+    /// The datasheet gives only the typical value, not a min/max, so set limits
+    /// characterized on known-good boards for a production test.
+    fn default() -> Self {
+        Self {
+            min_dps: 265.0,
+            max_dps: 795.0,
+        }
+    }
+}
+
+/// Result of [`L3GD20::self_test`]
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct SelfTestReport {
+    /// Average output with self-test disabled
+    pub baseline: AngularRate,
+    /// Average output with self-test enabled
+    pub self_test: AngularRate,
+    /// Absolute output change on each axis
+    pub delta: AngularRate,
+    /// Whether every axis changed by an amount within the accepted limits
+    pub passed: bool,
 }
 
 /// L3GD20 gyroscope driver
 pub struct L3GD20<'a> {
     spi: Spi<'a, embassy_stm32::mode::Blocking>,
     cs: Output<'a>,
+    model: GyroModel,
     scale: FullScale,
+    temp_cal: TemperatureCalibration,
 }
 
 impl<'a> L3GD20<'a> {
@@ -139,7 +353,9 @@ impl<'a> L3GD20<'a> {
         let mut gyro = Self {
             spi,
             cs,
+            model: GyroModel::Unknown(0),
             scale: FullScale::Dps250,
+            temp_cal: TemperatureCalibration::NONE,
         };
         
         // Initialize the sensor
@@ -153,7 +369,8 @@ impl<'a> L3GD20<'a> {
         
         // Check WHO_AM_I register
         let who_am_i = self.read_register(regs::WHO_AM_I);
-        info!("L3GD20 WHO_AM_I: {:#x} (expected 0xD4 or 0xD7)", who_am_i);
+        self.model = GyroModel::from_who_am_i(who_am_i);
+        info!("Gyroscope WHO_AM_I: {:#x} ({:?})", who_am_i, self.model);
         
         // Power on and enable all axes
         // PD=1 (normal mode), Zen=1, Yen=1, Xen=1
@@ -174,6 +391,11 @@ impl<'a> L3GD20<'a> {
         info!("L3GD20 initialized");
     }
     
+    /// Detected gyroscope part
+    pub fn model(&self) -> GyroModel {
+        self.model
+    }
+    
     /// Set the full scale range
     pub fn set_scale(&mut self, scale: FullScale) {
         self.scale = scale;
@@ -191,6 +413,76 @@ impl<'a> L3GD20<'a> {
         debug!("L3GD20 data rate set to {:?}", rate);
     }
     
+    /// Enter power-down mode
+    ///
+    /// Consumption drops to a few µA. Register contents are kept.
+    pub fn power_down(&mut self) {
+        let ctrl1 = self.read_register(regs::CTRL_REG1);
+        self.write_register(regs::CTRL_REG1, ctrl1 & !CTRL1_PD);
+        debug!("L3GD20 powered down");
+    }
+    
+    /// Enter sleep mode
+    ///
+    /// All axes are disabled but the sensor stays biased, so it draws about 2 mA
+    /// (6.1 mA in normal mode, 5 µA in power-down) and turns on faster than
+    /// from power-down.
+    pub fn sleep(&mut self) {
+        let ctrl1 = self.read_register(regs::CTRL_REG1);
+        self.write_register(regs::CTRL_REG1, (ctrl1 | CTRL1_PD) & !CTRL1_AXES);
+        debug!("L3GD20 asleep");
+    }
+    
+    /// Return to normal mode after [`power_down`](Self::power_down) or [`sleep`](Self::sleep)
+    ///
+    /// Enables all three axes. The first samples after power-up are not yet settled.
+    pub fn power_up(&mut self) {
+        let ctrl1 = self.read_register(regs::CTRL_REG1);
+        self.write_register(regs::CTRL_REG1, ctrl1 | CTRL1_PD | CTRL1_AXES);
+        debug!("L3GD20 powered up");
+    }
+    
+    /// Configure and enable the high-pass filter
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Remove slow drift below ~0.9 Hz (at 95 Hz ODR) from the output registers
+    /// gyro.set_high_pass(HighPassConfig {
+    ///     cutoff: HighPassCutoff::Hpcf3,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_high_pass(&mut self, config: HighPassConfig) {
+        self.write_register(regs::CTRL_REG2, config.mode as u8 | config.cutoff as u8);
+        let mut ctrl5 = self.read_register(regs::CTRL_REG5);
+        ctrl5 = (ctrl5 & 0xE0)
+            | CTRL5_HPEN
+            | ((config.interrupt as u8) << 2)
+            | config.output as u8;
+        self.write_register(regs::CTRL_REG5, ctrl5);
+        debug!("L3GD20 high-pass filter set to {:?}", config);
+    }
+    
+    /// Disable the high-pass filter and route unfiltered data to all outputs
+    pub fn disable_high_pass(&mut self) {
+        let ctrl5 = self.read_register(regs::CTRL_REG5) & 0xE0;
+        self.write_register(regs::CTRL_REG5, ctrl5);
+        self.write_register(regs::CTRL_REG2, 0x00);
+        debug!("L3GD20 high-pass filter disabled");
+    }
+    
+    /// Set the REFERENCE register used by [`HighPassMode::Reference`]
+    pub fn set_high_pass_reference(&mut self, reference: u8) {
+        self.write_register(regs::REFERENCE, reference);
+    }
+    
+    /// Reset the high-pass filter (in [`HighPassMode::NormalWithReset`])
+    ///
+    /// The filter settles to the current input, removing any DC component immediately.
+    pub fn reset_high_pass(&mut self) {
+        self.read_register(regs::REFERENCE);
+    }
+    
     /// Check if new data is available
     pub fn data_ready(&mut self) -> bool {
         let status = self.read_register(regs::STATUS_REG);
@@ -218,8 +510,102 @@ impl<'a> L3GD20<'a> {
         }
     }
     
-    /// Read temperature (raw value)
-    pub fn read_temperature(&mut self) -> i8 {
+    /// Run the built-in self-test
+    ///
+    /// Averages `samples` readings with the self-test actuation off and on (at ±2000 dps),
+    /// then checks that each axis moved by an amount within `limits`. Keep the board still
+    /// while the test runs. The previous scale and data rate are restored afterwards.
+    ///
+    /// Returns [`SelfTestError::DataTimeout`] if the gyro stops producing data.
+    ///
+    /// # Note
+    /// The ST bits in CTRL_REG4 are documented in the first datasheet revision only, with
+    /// a typical output change but no min/max. [`SelfTestLimits::default`] is a window
+    /// around the typical value, not a datasheet limit.
+    pub async fn self_test(&mut self, samples: u8, limits: SelfTestLimits) -> Result<SelfTestReport, SelfTestError> {
+        let ctrl1 = self.read_register(regs::CTRL_REG1);
+        let ctrl4 = self.read_register(regs::CTRL_REG4);
+        let ctrl5 = self.read_register(regs::CTRL_REG5);
+        let scale = self.scale;
+        
+        // Power on with all axes enabled, ±2000 dps, block data update, no high-pass filter
+        self.write_register(regs::CTRL_REG1, ctrl1 | 0x0F);
+        self.write_register(regs::CTRL_REG5, ctrl5 & 0xE0);
+        self.scale = FullScale::Dps2000;
+        self.write_register(regs::CTRL_REG4, 0x80 | FullScale::Dps2000 as u8);
+        let result = self.measure_self_test(samples).await;
+        
+        // Restore the previous configuration
+        self.write_register(regs::CTRL_REG4, ctrl4);
+        self.write_register(regs::CTRL_REG5, ctrl5);
+        self.write_register(regs::CTRL_REG1, ctrl1);
+        self.scale = scale;
+        
+        let (baseline, self_test) = result.inspect_err(|e| warn!("L3GD20 self-test failed: {}", e))?;
+        let delta = (self_test - baseline).map(f32::abs);
+        let passed = [delta.x, delta.y, delta.z]
+            .iter()
+            .all(|d| (limits.min_dps..=limits.max_dps).contains(d));
+        
+        info!("L3GD20 self-test {}: {}", if passed { "passed" } else { "FAILED" }, delta);
+        Ok(SelfTestReport {
+            baseline,
+            self_test,
+            delta,
+            passed,
+        })
+    }
+    
+    /// Average readings with the self-test actuation off, then on
+    async fn measure_self_test(&mut self, samples: u8) -> Result<(AngularRate, AngularRate), SelfTestError> {
+        Timer::after_millis(100).await;
+        let baseline = self.read_averaged(samples).await?;
+        
+        self.write_register(regs::CTRL_REG4, 0x80 | FullScale::Dps2000 as u8 | CTRL4_ST0);
+        Timer::after_millis(60).await;
+        let self_test = self.read_averaged(samples).await?;
+        Ok((baseline, self_test))
+    }
+    
+    /// Average `samples` fresh readings, discarding the first one
+    async fn read_averaged(&mut self, samples: u8) -> Result<AngularRate, SelfTestError> {
+        let samples = samples.max(1);
+        let mut sum = AngularRate::default();
+        
+        for i in 0..=samples {
+            with_timeout(SELF_TEST_DATA_TIMEOUT, async {
+                while !self.data_ready() {
+                    Timer::after_millis(1).await;
+                }
+            })
+            .await
+            .map_err(|_| SelfTestError::DataTimeout)?;
+            let rate = self.read_angular_rate();
+            if i > 0 {
+                sum += rate;
+            }
+        }
+        
+        Ok(sum / samples as f32)
+    }
+    
+    /// Set the one-point calibration applied by [`Self::read_temperature`]
+    pub fn set_temperature_calibration(&mut self, cal: TemperatureCalibration) {
+        self.temp_cal = cal;
+        debug!("L3GD20 temperature offset set to {} °C", cal.offset);
+    }
+    
+    /// Read temperature in °C
+    ///
+    /// Uncalibrated readings are relative to a nominal 25 °C, see [`crate::temperature`].
+    pub fn read_temperature(&mut self) -> Temperature {
+        let raw = self.read_temperature_raw();
+        let celsius = NOMINAL_CELSIUS + raw as f32 / TEMP_SENSITIVITY;
+        self.temp_cal.apply(Temperature::from_celsius(celsius))
+    }
+    
+    /// Read temperature (raw value, -1 LSB/°C)
+    pub fn read_temperature_raw(&mut self) -> i8 {
         self.read_register(regs::OUT_TEMP) as i8
     }
     
@@ -258,3 +644,20 @@ impl<'a> L3GD20<'a> {
         self.cs.set_high();
     }
 }
+
+impl Gyroscope for L3GD20<'_> {
+    type GyroConfig = GyroConfig;
+    
+    fn configure_gyro(&mut self, config: GyroConfig) {
+        self.set_scale(config.scale);
+        self.set_data_rate(config.data_rate);
+    }
+    
+    fn gyro_data_ready(&mut self) -> bool {
+        self.data_ready()
+    }
+    
+    fn read_angular_rate(&mut self) -> AngularRate {
+        L3GD20::read_angular_rate(self)
+    }
+}
@@ -0,0 +1,103 @@
+//! Board attitude shown on the compass-rose LEDs
+//!
+//! Each indicator returns a frame: a brightness from 0 to 100 per LED, indexed like
+//! [`Led`]. Show it with [`LedOutput::show`](crate::animation::LedOutput::show) on [`PwmLeds`](crate::leds::PwmLeds) for
+//! smooth brightness, or on [`Leds`](crate::leds::Leds), where LEDs at 50% or more
+//! light up.
+//!
+//! Axis convention: the accelerometer and magnetometer X axis points toward
+//! [`Led::RIGHT`] and the Y axis toward [`Led::UP`].
+//!
+//! # Example
+//! ```ignore
+//! let mut leds = PwmLeds::new(p.TIM4, p.PD13, p.PD12, p.PD14, p.PD15);
+//! let mut compass = ECompass::new(p.I2C1, p.PB6, p.PB9);
+//!
+//! loop {
+//!     leds.show(indicators::tilt(&compass.read_acceleration()));
+//!     Timer::after_millis(20).await;
+//! }
+//! ```
+
+use micromath::F32Ext;
+
+use crate::compass::Acceleration;
+use crate::leds::{normalize_degrees, Led};
+
+/// Tilt at which an LED reaches full brightness (sin 45°)
+const FULL_TILT: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+/// Tilt below which no LED lights (sin 5°), so a level board is dark
+const TILT_DEADBAND: f32 = 0.087;
+
+/// Light the LEDs on the side of the board that is tilted down
+///
+/// Brightness grows with the tilt toward each LED, reaching 100% at 45°.
+/// Returns all off when the board is level or in free fall.
+///
+/// # Arguments
+/// * `accel` - Acceleration in g, e.g. from
+///   [`Accelerometer::read_acceleration`](crate::sensor::Accelerometer::read_acceleration)
+pub fn tilt(accel: &Acceleration) -> [u8; 4] {
+    let norm = accel.norm();
+    if norm < 0.1 {
+        return [0; 4];
+    }
+
+    // At rest the accelerometer measures "up", so the lower side is opposite the
+    // horizontal component
+    let (x, y) = (-accel.x / norm, -accel.y / norm);
+    if (x * x + y * y).sqrt() < TILT_DEADBAND {
+        return [0; 4];
+    }
+
+    Led::ALL.map(|led| {
+        let angle = led.angle().to_radians();
+        let toward = x * angle.sin() + y * angle.cos();
+        percent(toward / FULL_TILT)
+    })
+}
+
+/// Direction of the downward tilt in degrees, clockwise from the top of the board
+///
+/// Returns `None` when the board is level (within 5°) or in free fall.
+pub fn tilt_direction(accel: &Acceleration) -> Option<f32> {
+    let norm = accel.norm();
+    if norm < 0.1 {
+        return None;
+    }
+    let (x, y) = (-accel.x / norm, -accel.y / norm);
+    if (x * x + y * y).sqrt() < TILT_DEADBAND {
+        return None;
+    }
+    Some(normalize_degrees(x.atan2(y).to_degrees()))
+}
+
+/// Point at an arbitrary direction, cross-fading between neighbouring LEDs
+///
+/// # Arguments
+/// * `degrees` - Direction clockwise from the top of the board
+pub fn direction(degrees: f32) -> [u8; 4] {
+    let degrees = normalize_degrees(degrees);
+    Led::ALL.map(|led| {
+        let mut diff = (degrees - led.angle()).abs();
+        if diff > 180.0 {
+            diff = 360.0 - diff;
+        }
+        percent(1.0 - diff / 90.0)
+    })
+}
+
+/// Point the LEDs at magnetic north
+///
+/// # Arguments
+/// * `degrees` - Board heading, clockwise from north to the top of the board
+///   (e.g. [`LSM303DLHC::calculate_heading`](crate::compass::LSM303DLHC::calculate_heading))
+pub fn heading(degrees: f32) -> [u8; 4] {
+    direction(-degrees)
+}
+
+/// Clamp a 0.0-1.0 fraction to a 0-100 brightness
+fn percent(fraction: f32) -> u8 {
+    (fraction.clamp(0.0, 1.0) * 100.0).round() as u8
+}
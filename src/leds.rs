@@ -5,11 +5,128 @@
 //! - LD4 (green)  - PD12 (PWM4 CH1)
 //! - LD5 (red)    - PD14 (PWM4 CH3)
 //! - LD6 (blue)   - PD15 (PWM4 CH4)
+//!
+//! Viewed with the USB connector at the bottom, the LEDs form a compass rose around
+//! the MCU: orange at the top, blue on the right, red at the bottom and green on the
+//! left. [`Led::UP`] etc. name them by position, and [`Leds::point_to`] lights the
+//! LED(s) nearest to an angle.
+//!
+//! [`Leds`] drives them as plain on/off outputs. [`PwmLeds`] drives them from TIM4
+//! for brightness control and fades; it is not available when the crate's
+//! `time-driver-tim4` feature is enabled.
+
+use core::ops::{Index, IndexMut};
 
 use embassy_stm32::gpio::{Level, Output, Speed};
 use embassy_stm32::Peri;
+#[cfg(not(feature = "time-driver-tim4"))]
+use {
+    embassy_stm32::gpio::OutputType,
+    embassy_stm32::peripherals::{PD12, PD13, PD14, PD15, TIM4},
+    embassy_stm32::time::hz,
+    embassy_stm32::timer::low_level::CountingMode,
+    embassy_stm32::timer::simple_pwm::{PwmPin, SimplePwm},
+    embassy_stm32::timer::Channel,
+    embassy_time::{Duration, Instant, Timer},
+    micromath::F32Ext,
+};
+
+/// One of the four user LEDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Led {
+    /// LD3 (orange) - PD13
+    Orange = 0,
+    /// LD4 (green) - PD12
+    Green = 1,
+    /// LD5 (red) - PD14
+    Red = 2,
+    /// LD6 (blue) - PD15
+    Blue = 3,
+}
+
+impl Led {
+    /// All four LEDs in order
+    pub const ALL: [Led; 4] = [Led::Orange, Led::Green, Led::Red, Led::Blue];
+
+    /// LEDs in clockwise order starting at the top
+    pub const CLOCKWISE: [Led; 4] = [Led::UP, Led::RIGHT, Led::DOWN, Led::LEFT];
+
+    /// LED at the top of the compass rose (orange)
+    pub const UP: Led = Led::Orange;
+    /// LED on the left of the compass rose (green)
+    pub const LEFT: Led = Led::Green;
+    /// LED at the bottom of the compass rose (red)
+    pub const DOWN: Led = Led::Red;
+    /// LED on the right of the compass rose (blue)
+    pub const RIGHT: Led = Led::Blue;
+
+    /// Bit for this LED in an LED mask
+    pub const fn mask(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Position on the compass rose in degrees, clockwise from the top
+    pub const fn angle(self) -> f32 {
+        match self {
+            Led::Orange => 0.0,
+            Led::Blue => 90.0,
+            Led::Red => 180.0,
+            Led::Green => 270.0,
+        }
+    }
+
+    /// The single LED nearest to `degrees` (clockwise from the top)
+    pub fn nearest(degrees: f32) -> Led {
+        Led::CLOCKWISE[((normalize_degrees(degrees) + 45.0) / 90.0) as usize % 4]
+    }
+
+    /// Mask of the LED(s) nearest to `degrees` (clockwise from the top)
+    ///
+    /// Angles within 22.5° of an LED light only that LED; angles in between light
+    /// both neighbours, giving eight distinguishable directions.
+    pub fn nearest_mask(degrees: f32) -> u8 {
+        let sector = ((normalize_degrees(degrees) + 22.5) / 45.0) as usize % 8;
+        let led = Led::CLOCKWISE[sector / 2];
+        if sector.is_multiple_of(2) {
+            led.mask()
+        } else {
+            led.mask() | Led::CLOCKWISE[(sector / 2 + 1) % 4].mask()
+        }
+    }
+
+    /// TIM4 channel driving this LED
+    #[cfg(not(feature = "time-driver-tim4"))]
+    fn channel(self) -> Channel {
+        match self {
+            Led::Green => Channel::Ch1,
+            Led::Orange => Channel::Ch2,
+            Led::Red => Channel::Ch3,
+            Led::Blue => Channel::Ch4,
+        }
+    }
+}
+
+/// Wrap an angle into 0..360 degrees
+pub(crate) fn normalize_degrees(degrees: f32) -> f32 {
+    let degrees = degrees % 360.0;
+    if degrees < 0.0 {
+        degrees + 360.0
+    } else {
+        degrees
+    }
+}
 
 /// All four user LEDs on the discovery board
+///
+/// Individual LEDs can be reached by field or by indexing with a [`Led`].
+///
+/// # Example
+/// ```ignore
+/// let mut leds = Leds::new(p.PD13, p.PD12, p.PD14, p.PD15);
+/// leds[Led::Red].set_high();
+/// leds.set_mask(Led::UP.mask() | Led::DOWN.mask());
+/// leds.point_to(heading);
+/// ```
 pub struct Leds<'d> {
     pub ld3_orange: Output<'d>,
     pub ld4_green: Output<'d>,
@@ -48,4 +165,227 @@ impl<'d> Leds<'d> {
         self.ld5_red.set_high();
         self.ld6_blue.set_high();
     }
+
+    /// Turn on the LEDs in `mask` and turn off the rest
+    ///
+    /// # Arguments
+    /// * `mask` - One bit per LED, see [`Led::mask`]
+    pub fn set_mask(&mut self, mask: u8) {
+        for led in Led::ALL {
+            self[led].set_level((mask & led.mask() != 0).into());
+        }
+    }
+
+    /// Mask of the LEDs that are currently on
+    pub fn mask(&self) -> u8 {
+        Led::ALL
+            .iter()
+            .filter(|&&led| self[led].is_set_high())
+            .fold(0, |mask, led| mask | led.mask())
+    }
+
+    /// Toggle one LED
+    pub fn toggle(&mut self, led: Led) {
+        self[led].toggle();
+    }
+
+    /// Light the LED(s) nearest to an angle and turn off the rest
+    ///
+    /// # Arguments
+    /// * `degrees` - Direction clockwise from the top of the board, e.g. a heading
+    pub fn point_to(&mut self, degrees: f32) {
+        self.set_mask(Led::nearest_mask(degrees));
+    }
+}
+
+impl<'d> Index<Led> for Leds<'d> {
+    type Output = Output<'d>;
+
+    fn index(&self, led: Led) -> &Output<'d> {
+        match led {
+            Led::Orange => &self.ld3_orange,
+            Led::Green => &self.ld4_green,
+            Led::Red => &self.ld5_red,
+            Led::Blue => &self.ld6_blue,
+        }
+    }
+}
+
+impl IndexMut<Led> for Leds<'_> {
+    fn index_mut(&mut self, led: Led) -> &mut Self::Output {
+        match led {
+            Led::Orange => &mut self.ld3_orange,
+            Led::Green => &mut self.ld4_green,
+            Led::Red => &mut self.ld5_red,
+            Led::Blue => &mut self.ld6_blue,
+        }
+    }
+}
+
+/// PWM frequency for the LEDs, well above visible flicker
+#[cfg(not(feature = "time-driver-tim4"))]
+const PWM_FREQ_HZ: u32 = 1_000;
+
+/// Default gamma for perceptually linear brightness
+#[cfg(not(feature = "time-driver-tim4"))]
+const DEFAULT_GAMMA: f32 = 2.2;
+
+/// Interval between brightness updates while fading
+#[cfg(not(feature = "time-driver-tim4"))]
+const FADE_STEP: Duration = Duration::from_millis(10);
+
+/// All four user LEDs with brightness control via TIM4
+///
+/// Brightness is given in percent (0-100) and gamma corrected, so 50% looks about
+/// half as bright as 100%.
+///
+/// # Example
+/// ```ignore
+/// let mut leds = PwmLeds::new(p.TIM4, p.PD13, p.PD12, p.PD14, p.PD15);
+/// leds.set_brightness(Led::Blue, 25);
+/// leds.fade(Led::Red, 100, Duration::from_millis(500)).await;
+/// ```
+///
+/// # Note
+/// TIM4 must not be used as the embassy-time driver (see the crate features).
+#[cfg(not(feature = "time-driver-tim4"))]
+pub struct PwmLeds<'d> {
+    pwm: SimplePwm<'d, TIM4>,
+    brightness: [u8; 4],
+    gamma: f32,
+}
+
+#[cfg(not(feature = "time-driver-tim4"))]
+impl<'d> PwmLeds<'d> {
+    /// Initialize TIM4 PWM on all four user LEDs (off by default)
+    pub fn new(
+        tim4: Peri<'d, TIM4>,
+        pd13: Peri<'d, PD13>,
+        pd12: Peri<'d, PD12>,
+        pd14: Peri<'d, PD14>,
+        pd15: Peri<'d, PD15>,
+    ) -> Self {
+        let mut pwm = SimplePwm::new(
+            tim4,
+            Some(PwmPin::new(pd12, OutputType::PushPull)),
+            Some(PwmPin::new(pd13, OutputType::PushPull)),
+            Some(PwmPin::new(pd14, OutputType::PushPull)),
+            Some(PwmPin::new(pd15, OutputType::PushPull)),
+            hz(PWM_FREQ_HZ),
+            CountingMode::EdgeAlignedUp,
+        );
+
+        for led in Led::ALL {
+            let mut ch = pwm.channel(led.channel());
+            ch.set_duty_cycle_fully_off();
+            ch.enable();
+        }
+
+        Self {
+            pwm,
+            brightness: [0; 4],
+            gamma: DEFAULT_GAMMA,
+        }
+    }
+
+    /// Set the gamma correction exponent
+    ///
+    /// Use 1.0 for a linear duty cycle. The default is 2.2.
+    pub fn set_gamma(&mut self, gamma: f32) {
+        self.gamma = gamma;
+        for led in Led::ALL {
+            self.apply(led);
+        }
+    }
+
+    /// Set the brightness of one LED
+    ///
+    /// # Arguments
+    /// * `led` - LED to change
+    /// * `percent` - Brightness from 0 (off) to 100 (full), clamped
+    pub fn set_brightness(&mut self, led: Led, percent: u8) {
+        self.brightness[led as usize] = percent.min(100);
+        self.apply(led);
+    }
+
+    /// Set the brightness of all LEDs
+    pub fn set_all(&mut self, percent: u8) {
+        for led in Led::ALL {
+            self.set_brightness(led, percent);
+        }
+    }
+
+    /// Current brightness of one LED in percent
+    pub fn brightness(&self, led: Led) -> u8 {
+        self.brightness[led as usize]
+    }
+
+    /// Turn all LEDs off
+    pub fn all_off(&mut self) {
+        self.set_all(0);
+    }
+
+    /// Turn all LEDs on at full brightness
+    pub fn all_on(&mut self) {
+        self.set_all(100);
+    }
+
+    /// Fade one LED from its current brightness to `percent` over `duration`
+    ///
+    /// # Arguments
+    /// * `led` - LED to fade
+    /// * `percent` - Target brightness from 0 to 100
+    /// * `duration` - Length of the fade
+    pub async fn fade(&mut self, led: Led, percent: u8, duration: Duration) {
+        let mut targets = self.brightness;
+        targets[led as usize] = percent.min(100);
+        self.fade_to(targets, duration).await;
+    }
+
+    /// Fade all LEDs to `percent` over `duration`
+    pub async fn fade_all(&mut self, percent: u8, duration: Duration) {
+        self.fade_to([percent.min(100); 4], duration).await;
+    }
+
+    /// Fade all LEDs simultaneously to per-LED targets
+    ///
+    /// # Arguments
+    /// * `targets` - Brightness for each LED, indexed like [`Led`]
+    /// * `duration` - Length of the fade
+    pub async fn fade_to(&mut self, targets: [u8; 4], duration: Duration) {
+        let start = self.brightness;
+        let begin = Instant::now();
+        let total = duration.as_micros().max(1) as f32;
+
+        loop {
+            let elapsed = begin.elapsed();
+            let t = (elapsed.as_micros() as f32 / total).min(1.0);
+
+            for led in Led::ALL {
+                let i = led as usize;
+                let from = start[i] as f32;
+                let to = targets[i].min(100) as f32;
+                self.brightness[i] = (from + (to - from) * t).round() as u8;
+                self.apply(led);
+            }
+
+            if elapsed >= duration {
+                break;
+            }
+            Timer::after(FADE_STEP).await;
+        }
+    }
+
+    /// Write the gamma-corrected duty cycle for one LED
+    fn apply(&mut self, led: Led) {
+        let percent = self.brightness[led as usize];
+        let mut ch = self.pwm.channel(led.channel());
+        let max = ch.max_duty_cycle();
+        let duty = match percent {
+            0 => 0,
+            100 => max,
+            _ => ((percent as f32 / 100.0).powf(self.gamma) * max as f32).round() as u16,
+        };
+        ch.set_duty_cycle(duty);
+    }
 }
@@ -15,8 +15,8 @@
 //! - **LEDs**: 4 user-controllable LEDs (orange, green, red, blue)
 //! - **Button**: 1 user button for input
 //! - **Sensors**: 
-//!   - L3GD20 3-axis digital gyroscope (±250/500/2000 dps)
-//!   - LSM303DLHC e-compass (3-axis accelerometer + 3-axis magnetometer)
+//!   - L3GD20 3-axis digital gyroscope (±250/500/2000 dps), I3G4250D on revision D
+//!   - LSM303DLHC e-compass (3-axis accelerometer + 3-axis magnetometer), LSM303AGR on revision D
 //! - **Audio**:
 //!   - MP45DT02 MEMS microphone with PDM output
 //!   - CS43L22 audio DAC with headphone/speaker amplifier
@@ -46,6 +46,10 @@
 //! # Audio demonstrations
 //! cargo run --example microphone
 //! cargo run --example audio_dac
+//! cargo run --example wav_player
+//! cargo run --example synth
+//! cargo run --example loopback
+//! cargo run --example resampler
 //! ```
 //! 
 //! ## Module Organization
@@ -53,18 +57,39 @@
 //! The BSP is organized into logical modules for each peripheral type:
 //! 
 //! - **Hardware Control**
-//!   - [`leds`] - Control the 4 onboard LEDs
-//!   - [`button`] - Read the user button state
-//!   - [`microphone`] - Interface with the MEMS microphone
+//!   - [`leds`] - Control the 4 onboard LEDs (on/off or PWM brightness)
+//!   - [`animation`] - Non-blocking, prioritized LED patterns
+//!   - [`indicators`] - Tilt and heading shown on the compass-rose LEDs
+//!   - [`morse`] - Signal text or fault codes on an LED or the beeper
+//!   - [`button`] - Read or await the user button state
+//!   - [`gesture`] - Click, double-click, long-press and hold events from the button
+//!   - [`microphone`] - Capture PCM audio from the MEMS microphone
+//!   - [`pdm`] - Decimate the microphone's PDM bit stream to PCM
 //!   - [`audio`] - Control the audio DAC
+//!   - [`playback`] - Stream PCM audio to the DAC over I2S
+//!   - [`wav`] - Parse WAV files embedded in flash
+//!   - [`adpcm`] - Decode 4-bit IMA ADPCM prompts while they play
+//!   - [`synth`] - Play polyphonic tones and RTTTL melodies
+//!   - [`dsp`] - Gain, biquad EQ and noise gate blocks for audio chains
+//!   - [`loopback`] - Play the microphone on the DAC in real time
+//!   - [`resample`] - Convert between sample rates and follow clock drift
 //! 
 //! - **Sensors**
 //!   - [`gyro`] - 3-axis gyroscope driver
 //!   - [`compass`] - Combined accelerometer and magnetometer driver
+//!   - [`lsm303agr`] - E-compass driver for revision D boards
+//!   - [`sensor`] - Common traits implemented by all motion sensors
+//!   - [`temperature`] - Calibrated temperature readings from all onboard sensors
+//! 
+//! - **Board**
+//!   - [`board`] - Board revision detection and revision-independent drivers
+//!   - [`power`] - Device power profiles, STOP and STANDBY modes with button, motion and RTC wake-up
+//!   - `storage` - Key-value store in internal flash for calibration data and settings
+//!     (`storage` feature)
 //! 
 //! ## Usage Example
 //! 
-//! ```no_run
+//! ```ignore
 //! use embassy_executor::Spawner;
 //! use stm32f411ve_disco::leds::Leds;
 //! 
@@ -82,10 +107,8 @@
 //! 
 //! ## Known Limitations
 //! 
-//! - **Audio DAC**: Currently provides I2C control only. Full audio playback requires 
-//!   I2S peripheral configuration which is not yet implemented.
-//! - **Microphone**: Basic GPIO interface only. Full PDM audio capture requires I2S/SPI
-//!   with DMA and decimation filtering.
+//! - **Microphone**: Capture uses a 4th-order CIC filter without droop compensation,
+//!   so the response falls off by a few dB towards the top of the band.
 //! - **USB OTG**: Not yet implemented.
 //! 
 //! ## Safety and Hardware Access
@@ -97,10 +120,31 @@
 
 // Onboard hardware
 pub mod leds;
+pub mod animation;   // Non-blocking LED patterns
+pub mod indicators;  // Tilt and heading shown on the LEDs
+pub mod morse;       // Morse and blink-code signalling
 pub mod button;
+pub mod gesture;     // Click/double-click/long-press decoding
 pub mod microphone;  // MP45DT02 MEMS microphone
+pub mod pdm;         // PDM to PCM decimation
 pub mod audio;       // CS43L22 audio DAC
+pub mod playback;    // PCM playback over I2S3
+pub mod wav;         // WAV file parser
+pub mod adpcm;       // IMA ADPCM codec
+pub mod synth;       // Polyphonic tone synthesizer
+pub mod dsp;         // Audio filters and processing chains
+pub mod loopback;    // Microphone-to-DAC loopback
+pub mod resample;    // Sample-rate conversion with drift compensation
 
 // Onboard sensors
 pub mod gyro;        // L3GD20 3-axis gyroscope
 pub mod compass;     // LSM303DLHC e-compass (accelerometer + magnetometer)
+pub mod lsm303agr;   // LSM303AGR e-compass (revision D boards)
+pub mod sensor;      // Common sensor traits
+pub mod temperature; // Temperature readings and calibration
+
+// Board information
+pub mod board;       // Board revision detection
+pub mod power;       // Power profiles and STOP/STANDBY low-power modes
+#[cfg(feature = "storage")]
+pub mod storage;     // Flash key-value store
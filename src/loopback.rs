@@ -0,0 +1,219 @@
+//! Microphone-to-DAC loopback
+//!
+//! Plays the MP45DT02 microphone on the CS43L22 outputs in real time, through a
+//! [`Processor`] chain. Useful as an end-to-end test of the audio path and as a
+//! starting point for hearing-aid-style processing.
+//!
+//! ## Pipeline
+//! ```text
+//! MP45DT02 --PDM--> I2S2 --DMA--> CIC decimator --> DSP chain --> I2S3 --DMA--> CS43L22
+//! ```
+//!
+//! Audio runs at 16 kHz in blocks of [`BLOCK_FRAMES`] (1 ms). Capture and playback
+//! share PLLI2S and run at exactly the same rate, so no samples pile up or run out.
+//!
+//! ## Latency
+//! With the buffer sizes below, sound takes about 6 ms from the microphone to the
+//! DAC input:
+//! - Microphone ring buffer of [`MIC_BUFFER_WORDS`]: 1-2 ms (DMA half-transfers)
+//! - One block: 1 ms
+//! - Output ring buffer of [`OUTPUT_BUFFER_WORDS`]: 4 ms
+//!
+//! Larger buffers survive longer stalls of the executor at the cost of latency.
+//!
+//! ## Other Rates
+//! [`run_resampled_loopback`] plays a microphone started at any rate (e.g. with
+//! [`start_with_clock`](PdmMicrophone::start_with_clock)) at a standard DAC rate. It
+//! converts with a [`Resampler`] and keeps the microphone buffer half full with a
+//! [`DriftControl`], so clocks that don't match exactly cause no clicks.
+//!
+//! # Example
+//! ```ignore
+//! let mut config = embassy_stm32::Config::default();
+//! loopback::configure_clocks(&mut config);
+//! let p = embassy_stm32::init(config);
+//!
+//! let mut mic_buffer = [0u16; MIC_BUFFER_WORDS];
+//! let mut mic = PdmMicrophone::new(p.SPI2, p.PB10, p.PC3, p.DMA1_CH3, &mut mic_buffer);
+//! let mut out_buffer = [0u16; OUTPUT_BUFFER_WORDS];
+//! let mut output = PcmOutput::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut out_buffer);
+//!
+//! let mut chain = (Biquad::high_pass(SAMPLE_RATE, 150.0, 0.707), Gain::from_db(24.0));
+//! run_loopback(&mut mic, &mut output, &mut chain).await;
+//! ```
+
+use defmt::{info, warn};
+use embassy_stm32::rcc::{
+    AHBPrescaler, APBPrescaler, Pll, PllMul, PllPDiv, PllPreDiv, PllQDiv, PllSource, Sysclk, HSI_FREQ,
+};
+
+use crate::dsp::Processor;
+use crate::microphone::PdmMicrophone;
+use crate::playback::{configure_audio_pll, PcmOutput};
+use crate::resample::{DriftControl, Resampler};
+
+/// Loopback sample rate
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Frames processed at a time (1 ms)
+pub const BLOCK_FRAMES: usize = 16;
+
+/// Suggested microphone DMA buffer size (2 ms of PDM data)
+pub const MIC_BUFFER_WORDS: usize = 128;
+
+/// Suggested output DMA buffer size (4 ms of stereo audio)
+pub const OUTPUT_BUFFER_WORDS: usize = 128;
+
+/// Suggested microphone DMA buffer size with resampling (64 samples)
+pub const RESAMPLED_MIC_BUFFER_WORDS: usize = 256;
+
+/// Full scale of a 16-bit sample
+const FULL_SCALE: f32 = 32768.0;
+
+/// Blocks between drift reports (about 1 s at 1 ms blocks)
+const DRIFT_REPORT_BLOCKS: u32 = 1000;
+
+/// Set up the clocks for real-time audio
+///
+/// Runs the core at 96 MHz from the PLL (the PDM filter needs about 10% of it)
+/// and enables PLLI2S with [`configure_audio_pll`].
+///
+/// # Example
+/// ```ignore
+/// let mut config = embassy_stm32::Config::default();
+/// loopback::configure_clocks(&mut config);
+/// let p = embassy_stm32::init(config);
+/// ```
+pub fn configure_clocks(config: &mut embassy_stm32::Config) {
+    let input = match (config.rcc.pll_src, config.rcc.hse) {
+        (PllSource::HSE, Some(hse)) => hse.freq,
+        _ => HSI_FREQ,
+    };
+
+    // 1 MHz x 192 / 2 = 96 MHz, and 48 MHz on the Q output
+    config.rcc.pll = Some(Pll {
+        prediv: PllPreDiv::from_bits((input.0 / 1_000_000) as u8),
+        mul: PllMul::MUL192,
+        divp: Some(PllPDiv::DIV2),
+        divq: Some(PllQDiv::DIV4),
+        divr: None,
+    });
+    config.rcc.sys = Sysclk::PLL1_P;
+    config.rcc.ahb_pre = AHBPrescaler::DIV1;
+    // APB1 is limited to 50 MHz
+    config.rcc.apb1_pre = APBPrescaler::DIV2;
+    config.rcc.apb2_pre = APBPrescaler::DIV1;
+
+    configure_audio_pll(config);
+}
+
+/// Play the microphone through `dsp` to the DAC, forever
+///
+/// (Re)starts the microphone at [`SAMPLE_RATE`], so that it runs at the output's
+/// rate; use [`run_resampled_loopback`] to keep another rate. Lost audio (a stalled
+/// executor) is logged and skipped. The DAC must be powered on; stop the loopback
+/// by dropping the future, e.g. with `select`, to change the chain between runs.
+pub async fn run_loopback(
+    mic: &mut PdmMicrophone<'_>,
+    output: &mut PcmOutput<'_>,
+    dsp: &mut impl Processor,
+) -> ! {
+    // Both ends divide PLLI2S to 16 039 Hz; a microphone started at another rate
+    // would slowly overrun or starve the output
+    mic.start(SAMPLE_RATE).await;
+    dsp.reset();
+
+    let mut stream = output.open(SAMPLE_RATE);
+    let mut samples = [0i16; BLOCK_FRAMES];
+    let mut frames = [[0i16; 2]; BLOCK_FRAMES];
+    info!("Loopback running at {} Hz", SAMPLE_RATE);
+
+    loop {
+        if let Err(e) = mic.read(&mut samples).await {
+            warn!("Microphone: {}", e);
+            continue;
+        }
+
+        process_block(&samples, dsp, &mut frames);
+        if let Err(e) = stream.write(&frames).await {
+            warn!("Output: {}", e);
+        }
+    }
+}
+
+/// Play the microphone through `dsp` to the DAC at `output_rate` Hz, forever
+///
+/// Like [`run_loopback`], but the microphone keeps its own rate (16 kHz if it is
+/// not running yet) and is converted to `output_rate`. `dsp` runs at the
+/// microphone rate. The microphone buffer needs room for a few blocks, e.g.
+/// [`RESAMPLED_MIC_BUFFER_WORDS`], which adds about half its length of latency.
+///
+/// # Example
+/// ```ignore
+/// mic.start_with_clock(SampleRate::MHz2_4).await;
+/// let mut chain = Biquad::high_pass(mic.sample_rate(), 120.0, 0.707);
+/// run_resampled_loopback(&mut mic, &mut output, 48_000, &mut chain).await;
+/// ```
+pub async fn run_resampled_loopback(
+    mic: &mut PdmMicrophone<'_>,
+    output: &mut PcmOutput<'_>,
+    output_rate: u32,
+    dsp: &mut impl Processor,
+) -> ! {
+    if !mic.is_running() {
+        mic.start(SAMPLE_RATE).await;
+    }
+    dsp.reset();
+
+    let mut resampler = Resampler::new(mic.sample_rate(), output_rate);
+    let mut drift = DriftControl::new(mic.capacity() / 2);
+    let mut stream = output.open(output_rate);
+    let mut samples = [0i16; BLOCK_FRAMES];
+    let mut frames = [[0i16; 2]; BLOCK_FRAMES];
+    let mut converted = [[0i16; 2]; 4 * BLOCK_FRAMES];
+    let mut blocks = 0u32;
+    info!("Loopback running at {} Hz, output at {} Hz", mic.sample_rate(), output_rate);
+
+    loop {
+        match mic.available() {
+            Ok(level) => resampler.set_trim_ppm(drift.update(level)),
+            Err(e) => warn!("Microphone: {}", e),
+        }
+        if let Err(e) = mic.read(&mut samples).await {
+            warn!("Microphone: {}", e);
+            continue;
+        }
+
+        process_block(&samples, dsp, &mut frames);
+        let mut input = &frames[..];
+        while !input.is_empty() {
+            let (consumed, produced) = resampler.process(input, &mut converted);
+            input = &input[consumed..];
+            if let Err(e) = stream.write(&converted[..produced]).await {
+                warn!("Output: {}", e);
+            }
+        }
+
+        blocks += 1;
+        if blocks.is_multiple_of(DRIFT_REPORT_BLOCKS) {
+            info!("Clock drift {} ppm, buffer level {}", drift.drift_ppm(), drift.level());
+        }
+    }
+}
+
+/// Run `dsp` on a block of samples and turn it into stereo frames
+fn process_block(
+    samples: &[i16; BLOCK_FRAMES],
+    dsp: &mut impl Processor,
+    frames: &mut [[i16; 2]; BLOCK_FRAMES],
+) {
+    let mut block = [0f32; BLOCK_FRAMES];
+    for (x, &sample) in block.iter_mut().zip(samples) {
+        *x = sample as f32 / FULL_SCALE;
+    }
+    dsp.process(&mut block);
+    for (frame, &x) in frames.iter_mut().zip(&block) {
+        let sample = (x * FULL_SCALE).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        *frame = [sample, sample];
+    }
+}
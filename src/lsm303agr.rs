@@ -0,0 +1,384 @@
+//! LSM303AGR e-compass driver (3-axis accelerometer + 3-axis magnetometer)
+//!
+//! The LSM303AGR replaces the LSM303DLHC on revision D boards (see [`crate::board`]).
+//! The accelerometer is register-compatible with the LSM303DLHC, while the magnetometer
+//! has a new register map with a fixed ±50 gauss range.
+//!
+//! ## Features
+//! - 3-axis accelerometer: ±2g/±4g/±8g/±16g full scale
+//! - 3-axis magnetometer: ±50 gauss, 1.5 mgauss/LSB
+//! - Temperature sensor (in the accelerometer)
+//! - I2C interface (up to 400 kHz)
+//!
+//! ## Pin connections on STM32F411E-DISCO:
+//! - SCL: PB6
+//! - SDA: PB9
+//!
+//! [Datasheet](https://www.st.com/resource/en/datasheet/lsm303agr.pdf)
+
+use defmt::{debug, info};
+use embassy_stm32::i2c::{Config as I2cConfig, I2c};
+use embassy_stm32::{i2c, Peri};
+
+use crate::compass::{AccelConfig, Acceleration, AccelDataRate, AccelScale, MagneticField};
+use crate::sensor::{Accelerometer, Magnetometer};
+use crate::temperature::{Temperature, TemperatureCalibration, NOMINAL_CELSIUS};
+
+/// I2C addresses
+pub(crate) const ACCEL_ADDR: u8 = 0x19;
+pub(crate) const MAG_ADDR: u8 = 0x1E;
+
+/// Expected WHO_AM_I_M value
+pub(crate) const MAG_WHO_AM_I: u8 = 0x40;
+
+/// Accelerometer register addresses
+#[allow(dead_code)]
+mod accel_regs {
+    pub const STATUS_REG_AUX_A: u8 = 0x07;
+    pub const OUT_TEMP_L_A: u8 = 0x0C;
+    pub const OUT_TEMP_H_A: u8 = 0x0D;
+    pub const WHO_AM_I_A: u8 = 0x0F;
+    pub const TEMP_CFG_REG_A: u8 = 0x1F;
+    pub const CTRL_REG1_A: u8 = 0x20;
+    pub const CTRL_REG2_A: u8 = 0x21;
+    pub const CTRL_REG3_A: u8 = 0x22;
+    pub const CTRL_REG4_A: u8 = 0x23;
+    pub const CTRL_REG5_A: u8 = 0x24;
+    pub const CTRL_REG6_A: u8 = 0x25;
+    pub const REFERENCE_A: u8 = 0x26;
+    pub const STATUS_REG_A: u8 = 0x27;
+    pub const OUT_X_L_A: u8 = 0x28;
+}
+
+/// Magnetometer register addresses
+#[allow(dead_code)]
+pub(crate) mod mag_regs {
+    pub const WHO_AM_I_M: u8 = 0x4F;
+    pub const CFG_REG_A_M: u8 = 0x60;
+    pub const CFG_REG_B_M: u8 = 0x61;
+    pub const CFG_REG_C_M: u8 = 0x62;
+    pub const STATUS_REG_M: u8 = 0x67;
+    pub const OUTX_L_REG_M: u8 = 0x68;
+}
+
+/// Magnetometer sensitivity in gauss/LSB
+const MAG_SENSITIVITY: f32 = 0.0015;
+
+/// Temperature sensor sensitivity in LSB/°C (8-bit output)
+const TEMP_SENSITIVITY: f32 = 1.0;
+
+/// CTRL_REG1_A data rate and low-power enable bits
+const CTRL1_A_ODR: u8 = 0xF0;
+const CTRL1_A_LPEN: u8 = 0x08;
+
+/// CTRL_REG4_A high-resolution bit
+const CTRL4_A_HR: u8 = 0x08;
+
+/// CFG_REG_A_M low-power, data rate and mode bits
+const CFG_A_M_LP: u8 = 0x10;
+const CFG_A_M_ODR: u8 = 0x0C;
+const CFG_A_M_MD: u8 = 0x03;
+
+/// Magnetometer modes (CFG_REG_A_M MD bits)
+const MAG_CONTINUOUS: u8 = 0x00;
+const MAG_IDLE: u8 = 0x03;
+
+/// Magnetometer data rate
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum AgrMagDataRate {
+    /// 10 Hz
+    Hz10 = 0x00,
+    /// 20 Hz
+    Hz20 = 0x04,
+    /// 50 Hz
+    Hz50 = 0x08,
+    /// 100 Hz
+    Hz100 = 0x0C,
+}
+
+/// LSM303AGR e-compass driver
+pub struct LSM303AGR<'a> {
+    i2c: I2c<'a, embassy_stm32::mode::Blocking, i2c::Master>,
+    accel_scale: AccelScale,
+    accel_rate: AccelDataRate,
+    mag_rate: AgrMagDataRate,
+    temp_cal: TemperatureCalibration,
+}
+
+impl<'a> LSM303AGR<'a> {
+    /// Create a new LSM303AGR driver instance
+    pub fn new<T: i2c::Instance>(
+        i2c1: Peri<'a, T>,
+        scl: Peri<'a, impl i2c::SclPin<T>>,
+        sda: Peri<'a, impl i2c::SdaPin<T>>,
+    ) -> Self {
+        let i2c = I2c::new_blocking(i2c1, scl, sda, I2cConfig::default());
+        Self::from_i2c(i2c)
+    }
+
+    /// Create a driver on an already configured I2C bus
+    pub(crate) fn from_i2c(i2c: I2c<'a, embassy_stm32::mode::Blocking, i2c::Master>) -> Self {
+        let mut compass = Self {
+            i2c,
+            accel_scale: AccelScale::G2,
+            accel_rate: AccelDataRate::Hz100,
+            mag_rate: AgrMagDataRate::Hz20,
+            temp_cal: TemperatureCalibration::NONE,
+        };
+
+        compass.init();
+
+        compass
+    }
+
+    /// Initialize the accelerometer and magnetometer
+    fn init(&mut self) {
+        let who_am_i = self.read_mag_register(mag_regs::WHO_AM_I_M);
+        info!("LSM303AGR WHO_AM_I_M: {:#x} (expected {:#x})", who_am_i, MAG_WHO_AM_I);
+
+        // Normal power mode, 100 Hz, all axes enabled
+        self.write_accel_register(accel_regs::CTRL_REG1_A, 0x57);
+
+        // Block data update (required for temperature), default scale (±2g), high resolution
+        self.write_accel_register(accel_regs::CTRL_REG4_A, 0x88);
+
+        // Temperature sensor enabled
+        self.write_accel_register(accel_regs::TEMP_CFG_REG_A, 0xC0);
+
+        // Temperature compensation, 20 Hz, continuous mode
+        self.write_mag_register(mag_regs::CFG_REG_A_M, 0x84);
+
+        // Offset cancellation
+        self.write_mag_register(mag_regs::CFG_REG_B_M, 0x02);
+
+        // Block data update
+        self.write_mag_register(mag_regs::CFG_REG_C_M, 0x10);
+
+        info!("LSM303AGR initialized");
+    }
+
+    /// Set accelerometer scale
+    pub fn set_accel_scale(&mut self, scale: AccelScale) {
+        self.accel_scale = scale;
+        let mut ctrl4 = self.read_accel_register(accel_regs::CTRL_REG4_A);
+        ctrl4 = (ctrl4 & 0xCF) | (scale as u8);
+        self.write_accel_register(accel_regs::CTRL_REG4_A, ctrl4);
+        debug!("Accelerometer scale set to {:?}", scale);
+    }
+
+    /// Set accelerometer data rate
+    pub fn set_accel_data_rate(&mut self, rate: AccelDataRate) {
+        self.accel_rate = rate;
+        let mut ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        ctrl1 = (ctrl1 & 0x0F) | (rate as u8);
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
+        debug!("Accelerometer data rate set to {:?}", rate);
+    }
+
+    /// Set magnetometer data rate
+    pub fn set_mag_data_rate(&mut self, rate: AgrMagDataRate) {
+        self.mag_rate = rate;
+        let mut cfg_a = self.read_mag_register(mag_regs::CFG_REG_A_M);
+        cfg_a = (cfg_a & 0xF3) | (rate as u8);
+        self.write_mag_register(mag_regs::CFG_REG_A_M, cfg_a);
+        debug!("Magnetometer data rate set to {:?}", rate);
+    }
+
+    /// Put the magnetometer in idle mode or back into continuous conversion
+    ///
+    /// The accelerometer keeps running.
+    pub fn set_mag_sleep(&mut self, sleep: bool) {
+        let mode = if sleep { MAG_IDLE } else { MAG_CONTINUOUS };
+        let cfg_a = self.read_mag_register(mag_regs::CFG_REG_A_M);
+        self.write_mag_register(mag_regs::CFG_REG_A_M, (cfg_a & !CFG_A_M_MD) | mode);
+        debug!("Magnetometer {}", if sleep { "asleep" } else { "awake" });
+    }
+
+    /// Power down both sensors
+    ///
+    /// The accelerometer enters power-down mode and the magnetometer idle mode.
+    /// Register contents are kept.
+    pub fn power_down(&mut self) {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1 & !CTRL1_A_ODR);
+        self.set_mag_sleep(true);
+        debug!("LSM303AGR powered down");
+    }
+
+    /// Return to normal operation after [`power_down`](Self::power_down) or
+    /// [`low_power_sampling`](Self::low_power_sampling)
+    ///
+    /// Restores the configured data rates, high-resolution accelerometer output
+    /// and high-resolution magnetometer mode.
+    pub fn power_up(&mut self) {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        let ctrl1 = (ctrl1 & !(CTRL1_A_ODR | CTRL1_A_LPEN)) | self.accel_rate as u8;
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
+        let ctrl4 = self.read_accel_register(accel_regs::CTRL_REG4_A);
+        self.write_accel_register(accel_regs::CTRL_REG4_A, ctrl4 | CTRL4_A_HR);
+
+        let cfg_a = self.read_mag_register(mag_regs::CFG_REG_A_M);
+        let cfg_a = cfg_a & !(CFG_A_M_LP | CFG_A_M_ODR | CFG_A_M_MD);
+        self.write_mag_register(mag_regs::CFG_REG_A_M, cfg_a | self.mag_rate as u8 | MAG_CONTINUOUS);
+        debug!("LSM303AGR powered up");
+    }
+
+    /// Keep sampling at a reduced rate and resolution
+    ///
+    /// The accelerometer runs in low-power mode (8-bit output) at 10 Hz and the
+    /// magnetometer in low-power mode at 10 Hz. Readings keep their units. The
+    /// configured data rates are remembered and restored by [`power_up`](Self::power_up).
+    pub fn low_power_sampling(&mut self) {
+        let ctrl1 = self.read_accel_register(accel_regs::CTRL_REG1_A);
+        let ctrl1 = (ctrl1 & !CTRL1_A_ODR) | AccelDataRate::Hz10 as u8 | CTRL1_A_LPEN;
+        self.write_accel_register(accel_regs::CTRL_REG1_A, ctrl1);
+        let ctrl4 = self.read_accel_register(accel_regs::CTRL_REG4_A);
+        self.write_accel_register(accel_regs::CTRL_REG4_A, ctrl4 & !CTRL4_A_HR);
+
+        let cfg_a = self.read_mag_register(mag_regs::CFG_REG_A_M);
+        let cfg_a = cfg_a & !(CFG_A_M_ODR | CFG_A_M_MD);
+        let cfg_a = cfg_a | AgrMagDataRate::Hz10 as u8 | CFG_A_M_LP | MAG_CONTINUOUS;
+        self.write_mag_register(mag_regs::CFG_REG_A_M, cfg_a);
+        debug!("LSM303AGR in low-power sampling");
+    }
+
+    /// Check if new acceleration data is available
+    pub fn accel_data_ready(&mut self) -> bool {
+        let status = self.read_accel_register(accel_regs::STATUS_REG_A);
+        (status & 0x08) != 0 // ZYXDA bit
+    }
+
+    /// Check if new magnetic data is available
+    pub fn mag_data_ready(&mut self) -> bool {
+        let status = self.read_mag_register(mag_regs::STATUS_REG_M);
+        (status & 0x08) != 0 // Zyxda bit
+    }
+
+    /// Read acceleration data
+    pub fn read_acceleration(&mut self) -> Acceleration {
+        let mut data = [0u8; 6];
+        self.read_accel_burst(accel_regs::OUT_X_L_A | 0x80, &mut data);
+
+        // Convert to signed 16-bit values (12-bit resolution, left-aligned)
+        let raw_x = i16::from_le_bytes([data[0], data[1]]) >> 4;
+        let raw_y = i16::from_le_bytes([data[2], data[3]]) >> 4;
+        let raw_z = i16::from_le_bytes([data[4], data[5]]) >> 4;
+
+        // Sensitivity in mg/LSB (high resolution mode)
+        let sensitivity = match self.accel_scale {
+            AccelScale::G2 => 0.98,
+            AccelScale::G4 => 1.95,
+            AccelScale::G8 => 3.9,
+            AccelScale::G16 => 11.72,
+        } / 1000.0;
+
+        Acceleration {
+            x: raw_x as f32 * sensitivity,
+            y: raw_y as f32 * sensitivity,
+            z: raw_z as f32 * sensitivity,
+        }
+    }
+
+    /// Read magnetic field data
+    pub fn read_magnetic_field(&mut self) -> MagneticField {
+        // X, Y, Z order, low byte first; the magnetometer auto-increments on its own
+        let mut data = [0u8; 6];
+        self.read_mag_burst(mag_regs::OUTX_L_REG_M, &mut data);
+
+        let raw_x = i16::from_le_bytes([data[0], data[1]]);
+        let raw_y = i16::from_le_bytes([data[2], data[3]]);
+        let raw_z = i16::from_le_bytes([data[4], data[5]]);
+
+        MagneticField {
+            x: raw_x as f32 * MAG_SENSITIVITY,
+            y: raw_y as f32 * MAG_SENSITIVITY,
+            z: raw_z as f32 * MAG_SENSITIVITY,
+        }
+    }
+
+    /// Set the one-point calibration applied by [`Self::read_temperature`]
+    pub fn set_temperature_calibration(&mut self, cal: TemperatureCalibration) {
+        self.temp_cal = cal;
+        debug!("LSM303AGR temperature offset set to {} °C", cal.offset);
+    }
+
+    /// Read temperature in °C
+    ///
+    /// Uncalibrated readings are relative to a nominal 25 °C, see [`crate::temperature`].
+    pub fn read_temperature(&mut self) -> Temperature {
+        let raw = self.read_temperature_raw();
+        let celsius = NOMINAL_CELSIUS + raw as f32 / TEMP_SENSITIVITY;
+        self.temp_cal.apply(Temperature::from_celsius(celsius))
+    }
+
+    /// Read temperature (raw 8-bit value, 1 LSB/°C)
+    pub fn read_temperature_raw(&mut self) -> i8 {
+        // Both bytes must be read for the output to update
+        let mut data = [0u8; 2];
+        self.read_accel_burst(accel_regs::OUT_TEMP_L_A | 0x80, &mut data);
+        data[1] as i8
+    }
+
+    // Accelerometer register access
+    fn read_accel_register(&mut self, reg: u8) -> u8 {
+        let mut buf = [0u8; 1];
+        self.i2c.blocking_write_read(ACCEL_ADDR, &[reg], &mut buf).ok();
+        buf[0]
+    }
+
+    fn read_accel_burst(&mut self, start_reg: u8, buf: &mut [u8]) {
+        self.i2c.blocking_write_read(ACCEL_ADDR, &[start_reg], buf).ok();
+    }
+
+    fn write_accel_register(&mut self, reg: u8, value: u8) {
+        self.i2c.blocking_write(ACCEL_ADDR, &[reg, value]).ok();
+    }
+
+    // Magnetometer register access
+    fn read_mag_register(&mut self, reg: u8) -> u8 {
+        let mut buf = [0u8; 1];
+        self.i2c.blocking_write_read(MAG_ADDR, &[reg], &mut buf).ok();
+        buf[0]
+    }
+
+    fn read_mag_burst(&mut self, start_reg: u8, buf: &mut [u8]) {
+        self.i2c.blocking_write_read(MAG_ADDR, &[start_reg], buf).ok();
+    }
+
+    fn write_mag_register(&mut self, reg: u8, value: u8) {
+        self.i2c.blocking_write(MAG_ADDR, &[reg, value]).ok();
+    }
+}
+
+impl Accelerometer for LSM303AGR<'_> {
+    type AccelConfig = AccelConfig;
+
+    fn configure_accel(&mut self, config: AccelConfig) {
+        self.set_accel_scale(config.scale);
+        self.set_accel_data_rate(config.data_rate);
+    }
+
+    fn accel_data_ready(&mut self) -> bool {
+        LSM303AGR::accel_data_ready(self)
+    }
+
+    fn read_acceleration(&mut self) -> Acceleration {
+        LSM303AGR::read_acceleration(self)
+    }
+}
+
+impl Magnetometer for LSM303AGR<'_> {
+    type MagConfig = AgrMagDataRate;
+
+    fn configure_mag(&mut self, config: AgrMagDataRate) {
+        self.set_mag_data_rate(config);
+    }
+
+    fn mag_data_ready(&mut self) -> bool {
+        LSM303AGR::mag_data_ready(self)
+    }
+
+    fn read_magnetic_field(&mut self) -> MagneticField {
+        LSM303AGR::read_magnetic_field(self)
+    }
+}
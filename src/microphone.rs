@@ -13,13 +13,33 @@
 //! - PDM_OUT: PC3
 //! - CLK_IN: PB10
 //!
+//! ## Drivers
+//! - [`PdmMicrophone`]: captures audio with I2S2 and DMA and converts it to 16-bit
+//!   PCM with a [`PdmDecimator`]
+//!
 //! [Datasheet](docs/mp45dt02.pdf)
 
-use defmt::info;
-use embassy_stm32::gpio::{Input, Output, Level, Speed, Pull};
+use defmt::{debug, info};
+use embassy_stm32::dma::{ReadableRingBuffer, TransferOptions};
+use embassy_stm32::pac::{self, gpio::vals as gpio_vals, spi::vals as spi_vals};
+use embassy_stm32::peripherals::{DMA1_CH3, PB10, PC3, SPI2};
+use embassy_stm32::spi::RxDma;
 use embassy_stm32::Peri;
 use embassy_time::{Duration, Timer};
 
+use crate::pdm::{PdmDecimator, DECIMATION, WORDS_PER_SAMPLE};
+use crate::playback::i2s_clock;
+
+/// Microphone errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// Capture is stopped: call [`PdmMicrophone::start`] first, or
+    /// [`power_up`](PdmMicrophone::power_up) after a power-down
+    NotStarted,
+    /// The ring buffer filled up and audio was lost
+    Overrun,
+}
+
 /// PDM sampling frequencies
 ///
 /// The MP45DT02 supports various clock frequencies for PDM output.
@@ -35,126 +55,276 @@ pub enum SampleRate {
     MHz3_2 = 3_200_000,
 }
 
-/// MP45DT02 microphone driver
+/// PCM samples decimated per DMA read
+const CHUNK_SAMPLES: usize = 16;
+
+/// Alternate function of the I2S2 pins
+const I2S2_AF: u8 = 5;
+
+/// Time from the first clock edge to valid data
+const STARTUP_TIME: Duration = Duration::from_millis(10);
+
+/// Lowest and highest PDM clock supported by the MP45DT02
+const MIN_PDM_CLOCK: u32 = 1_000_000;
+const MAX_PDM_CLOCK: u32 = 3_250_000;
+
+/// MP45DT02 audio capture through I2S2 and DMA
+///
+/// I2S2 runs as a master receiver clocking the microphone on PB10 and sampling its
+/// PDM output on PC3. DMA1 stream 3 copies the bits into a ring buffer, and
+/// [`read`](Self::read) turns them into 16-bit PCM at `DECIMATION` times less than
+/// the PDM clock.
+///
+/// The I2S clock comes from PLLI2S, shared with the DAC. Enable it with
+/// [`configure_audio_pll`](crate::playback::configure_audio_pll); with it, 16 and
+/// 48 kHz capture run at exactly the rate of a [`PcmOutput`](crate::playback::PcmOutput)
+/// stream, so the two can be connected without resampling. The PDM filter needs a
+/// fast core clock, see [`loopback::configure_clocks`](crate::loopback::configure_clocks).
+///
+/// The DMA wakes the reader at each half of the ring buffer, so a smaller buffer
+/// means lower latency: 128 words are 2 ms at 16 kHz.
 ///
-/// This driver provides a simplified interface to the MP45DT02 MEMS microphone.
-/// For full functionality, an I2S peripheral with PDM support and DMA would be required.
-/// 
-/// ## Current Implementation
-/// - Basic GPIO control for demonstration
-/// - Configurable sample rates
-/// - Placeholder for audio capture functions
-/// 
-/// ## Full Implementation Would Require
-/// - I2S peripheral configuration in PDM mode
-/// - DMA setup for continuous data streaming
-/// - CIC decimation filter for PDM to PCM conversion
-/// - Low-pass filtering for anti-aliasing
-pub struct MP45DT02<'a> {
-    #[allow(dead_code)]
-    pdm_data: Input<'a>,
-    pdm_clk: Output<'a>,
-    sample_rate: SampleRate,
+/// # Example
+/// ```ignore
+/// let mut buffer = [0u16; 128];
+/// let mut mic = PdmMicrophone::new(p.SPI2, p.PB10, p.PC3, p.DMA1_CH3, &mut buffer);
+/// mic.start(16_000).await;
+///
+/// let mut samples = [0i16; 160];
+/// mic.read(&mut samples).await.unwrap();
+/// ```
+pub struct PdmMicrophone<'d> {
+    _spi2: Peri<'d, SPI2>,
+    _pins: (Peri<'d, PB10>, Peri<'d, PC3>),
+    ring: ReadableRingBuffer<'d, u16>,
+    decimator: PdmDecimator,
+    sample_rate: u32,
+    running: bool,
+    /// Capture was running at [`power_down`](Self::power_down)
+    resume_capture: bool,
+    /// PDM words to drop before the next samples, while the microphone wakes up
+    settling: usize,
 }
 
-impl<'a> MP45DT02<'a> {
-    /// Create a new MP45DT02 driver instance
+impl<'d> PdmMicrophone<'d> {
+    /// Claim I2S2, its pins and DMA stream; `buffer` is the DMA ring buffer
     ///
     /// # Arguments
-    /// * `pdm_out` - PDM data output pin (PC3 on Discovery board)
-    /// * `clk_in` - Clock input pin (PB10 on Discovery board)
-    ///
-    /// # Example
-    /// ```no_run
-    /// let mic = MP45DT02::new(p.PC3, p.PB10);
-    /// ```
+    /// * `clk_in` - Microphone clock (PB10, I2S2_CK)
+    /// * `pdm_out` - Microphone data (PC3, I2S2_SD)
+    /// * `buffer` - DMA ring buffer, a multiple of 8 words
     pub fn new(
-        pdm_out: Peri<'a, impl embassy_stm32::gpio::Pin>,
-        clk_in: Peri<'a, impl embassy_stm32::gpio::Pin>,
+        spi2: Peri<'d, SPI2>,
+        clk_in: Peri<'d, PB10>,
+        pdm_out: Peri<'d, PC3>,
+        dma: Peri<'d, DMA1_CH3>,
+        buffer: &'d mut [u16],
     ) -> Self {
-        let pdm_data = Input::new(pdm_out, Pull::None);
-        let pdm_clk = Output::new(clk_in, Level::Low, Speed::VeryHigh);
-        
-        let mic = Self {
-            pdm_data,
-            pdm_clk,
-            sample_rate: SampleRate::MHz2_4,
+        embassy_stm32::rcc::enable_and_reset::<SPI2>();
+        for (port, pin) in [(pac::GPIOB, 10), (pac::GPIOC, 3)] {
+            port.ospeedr().modify(|w| w.set_ospeedr(pin, gpio_vals::Ospeedr::VERY_HIGH_SPEED));
+            port.afr(pin / 8).modify(|w| w.set_afr(pin % 8, I2S2_AF));
+            port.moder().modify(|w| w.set_moder(pin, gpio_vals::Moder::ALTERNATE));
+        }
+
+        let request = dma.request();
+        // SAFETY: the ring buffer reads the data register of SPI2, which this driver owns
+        let ring = unsafe {
+            ReadableRingBuffer::new(
+                dma,
+                request,
+                pac::SPI2.dr().as_ptr() as *mut u16,
+                buffer,
+                TransferOptions::default(),
+            )
         };
-        
-        info!("MP45DT02 microphone initialized");
-        mic
+
+        Self {
+            _spi2: spi2,
+            _pins: (clk_in, pdm_out),
+            ring,
+            decimator: PdmDecimator::new(),
+            sample_rate: 0,
+            running: false,
+            resume_capture: false,
+            settling: 0,
+        }
     }
-    
-    /// Set the PDM clock frequency
+
+    /// Start capturing at (close to) `sample_rate` Hz
     ///
-    /// Configures the clock rate for PDM data output. Higher rates provide
-    /// better audio quality but require more processing for decimation.
+    /// Returns once the microphone output is valid. The PDM clock is limited to
+    /// the 1-3.25 MHz the microphone supports, i.e. 15.6-50.8 kHz PCM.
+    pub async fn start(&mut self, sample_rate: u32) {
+        self.start_pdm(sample_rate * DECIMATION).await
+    }
+
+    /// Start capturing with a PDM clock of (close to) `clock`
     ///
-    /// # Arguments
-    /// * `rate` - The desired sample rate (1.0, 2.4, or 3.2 MHz)
-    pub fn set_sample_rate(&mut self, rate: SampleRate) {
-        self.sample_rate = rate;
-        info!("Microphone sample rate set to {:?} Hz", rate as u32);
+    /// The PCM rate is the clock divided by [`DECIMATION`], e.g. 37.5 kHz at 2.4 MHz;
+    /// use a [`Resampler`](crate::resample::Resampler) to play it at a DAC rate.
+    pub async fn start_with_clock(&mut self, clock: SampleRate) {
+        self.start_pdm(clock as u32).await
     }
-    
-    /// Start recording (simplified demonstration)
+
+    /// Start I2S2 and the DMA with a PDM clock close to `pdm_clock` Hz
+    async fn start_pdm(&mut self, pdm_clock: u32) {
+        self.stop().await;
+
+        let i2s_clock = i2s_clock();
+        let pdm_clock = pdm_clock.clamp(MIN_PDM_CLOCK, MAX_PDM_CLOCK);
+        // With 16-bit frames and no MCLK the bit clock is I2SCLK / (2 x I2SDIV + ODD)
+        let divider = ((i2s_clock + pdm_clock / 2) / pdm_clock).clamp(4, 511);
+        let spi = pac::SPI2;
+        spi.i2spr().write(|w| {
+            w.set_i2sdiv((divider / 2) as u8);
+            w.set_odd(if divider % 2 == 1 { spi_vals::Odd::ODD } else { spi_vals::Odd::EVEN });
+        });
+        // Same clock polarity and standard as ST's board support package
+        spi.i2scfgr().write(|w| {
+            w.set_i2smod(true);
+            w.set_i2scfg(spi_vals::I2scfg::MASTER_RX);
+            w.set_i2sstd(spi_vals::I2sstd::LSB);
+            w.set_ckpol(spi_vals::Ckpol::IDLE_HIGH);
+            w.set_datlen(spi_vals::Datlen::BITS16);
+        });
+
+        self.enable_capture();
+        self.sample_rate = i2s_clock / divider / DECIMATION;
+        info!(
+            "PDM capture started: clock {} Hz, {} Hz PCM",
+            i2s_clock / divider,
+            self.sample_rate
+        );
+
+        // Drop the samples from before the microphone settled
+        Timer::after(STARTUP_TIME).await;
+        self.ring.clear();
+    }
+
+    /// Start the DMA and I2S2 with the clock settings already in place
+    fn enable_capture(&mut self) {
+        self.decimator.reset();
+        self.resume_capture = false;
+        self.settling = 0;
+        self.ring.clear();
+        self.ring.start();
+        pac::SPI2.cr2().modify(|w| w.set_rxdmaen(true));
+        pac::SPI2.i2scfgr().modify(|w| w.set_i2se(true));
+        self.running = true;
+    }
+
+    /// Stop capturing and the microphone clock
+    pub async fn stop(&mut self) {
+        if !self.running {
+            return;
+        }
+        pac::SPI2.i2scfgr().modify(|w| w.set_i2se(false));
+        self.ring.stop().await;
+        pac::SPI2.cr2().modify(|w| w.set_rxdmaen(false));
+        self.running = false;
+        debug!("PDM capture stopped");
+    }
+
+    /// Stop capturing and the microphone clock without waiting, e.g. before STOP mode
     ///
-    /// This is a placeholder implementation. A complete implementation would:
-    /// 1. Configure the I2S peripheral for PDM reception
-    /// 2. Set up DMA for continuous data transfer
-    /// 3. Start the clock signal to the microphone
-    /// 4. Begin capturing PDM data stream
+    /// With its clock stopped the microphone draws 20 µA instead of 0.65 mA.
+    /// [`power_up`](Self::power_up) resumes capture if it was running.
+    pub fn power_down(&mut self) {
+        if !self.running {
+            return;
+        }
+        self.resume_capture = true;
+        pac::SPI2.i2scfgr().modify(|w| w.set_i2se(false));
+        self.ring.request_pause();
+        while self.ring.is_running() {}
+        pac::SPI2.cr2().modify(|w| w.set_rxdmaen(false));
+        self.running = false;
+        debug!("PDM capture powered down");
+    }
+
+    /// Resume capture stopped by [`power_down`](Self::power_down)
     ///
-    /// # Note
-    /// Currently just toggles pins for demonstration purposes.
-    pub async fn start_recording(&mut self) {
-        info!("Starting microphone recording...");
-        // In a real implementation, this would:
-        // 1. Configure I2S peripheral for PDM reception
-        // 2. Set up DMA for continuous data transfer
-        // 3. Configure decimation filter for PDM to PCM conversion
-        Timer::after(Duration::from_millis(10)).await;
+    /// Does not wait for the microphone to wake up: the next [`read`](Self::read)
+    /// drops its first 10 ms of output instead. PLLI2S must be running again,
+    /// which [`LowPower::stop`](crate::power::LowPower::stop) takes care of.
+    pub fn power_up(&mut self) {
+        if !core::mem::take(&mut self.resume_capture) {
+            return;
+        }
+        self.enable_capture();
+        let samples = self.sample_rate as u64 * STARTUP_TIME.as_millis() / 1000;
+        self.settling = samples as usize * WORDS_PER_SAMPLE;
+        debug!("PDM capture powered up");
+    }
+
+    /// Actual PCM sample rate (0 while stopped)
+    pub fn sample_rate(&self) -> u32 {
+        if self.running {
+            self.sample_rate
+        } else {
+            0
+        }
+    }
+
+    /// Check whether capture is running
+    pub fn is_running(&self) -> bool {
+        self.running
     }
-    
-    /// Stop recording
+
+    /// Number of PCM samples waiting in the ring buffer
     ///
-    /// Stops the microphone clock and ends data capture.
-    /// In a full implementation, this would also stop DMA transfers
-    /// and disable the I2S peripheral.
-    pub async fn stop_recording(&mut self) {
-        info!("Stopping microphone recording");
-        self.pdm_clk.set_low();
-        Timer::after(Duration::from_millis(1)).await;
+    /// Feed this to a [`DriftControl`](crate::resample::DriftControl) to follow the
+    /// microphone clock. Returns [`Error::Overrun`] if audio was lost, and
+    /// [`Error::NotStarted`] while capture is stopped.
+    pub fn available(&mut self) -> Result<usize, Error> {
+        if !self.running {
+            return Err(Error::NotStarted);
+        }
+        let words = self.ring.len().map_err(|_| Error::Overrun)?;
+        Ok(words / WORDS_PER_SAMPLE)
     }
-    
-    /// Read audio samples (demonstration only)
+
+    /// Number of PCM samples the ring buffer holds
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity() / WORDS_PER_SAMPLE
+    }
+
+    /// Fill `samples` with PCM, waiting for the microphone
     ///
-    /// This is a simplified implementation that returns dummy audio data.
-    /// 
-    /// # Real Implementation
-    /// A complete implementation would:
-    /// 1. Read PDM bit stream via I2S/SPI peripheral
-    /// 2. Apply CIC (Cascaded Integrator-Comb) decimation filter
-    /// 3. Apply low-pass filtering for anti-aliasing
-    /// 4. Convert decimated data to PCM samples
-    /// 
-    /// # Arguments
-    /// * `buffer` - Buffer to fill with audio samples
-    /// 
-    /// # Returns
-    /// Number of samples written to the buffer
-    pub async fn read_samples(&mut self, buffer: &mut [i16]) -> usize {
-        // Simplified implementation - real one would:
-        // 1. Read PDM bit stream via I2S/SPI
-        // 2. Apply CIC decimation filter
-        // 3. Apply low-pass filter
-        // 4. Convert to PCM samples
-        
-        // For now, generate some dummy audio data
-        for (i, sample) in buffer.iter_mut().enumerate() {
-            *sample = ((i as i16) * 100) % 32767;
+    /// Returns [`Error::Overrun`] if the ring buffer filled up since the last read,
+    /// i.e. audio was lost; the buffer is emptied and capture goes on. Capture is
+    /// not started on demand: while it is stopped, this returns [`Error::NotStarted`]
+    /// without waiting.
+    pub async fn read(&mut self, samples: &mut [i16]) -> Result<(), Error> {
+        if !self.running {
+            return Err(Error::NotStarted);
         }
-        
-        buffer.len()
+
+        let mut words = [0u16; CHUNK_SAMPLES * WORDS_PER_SAMPLE];
+        while self.settling > 0 {
+            let n = self.settling.min(words.len());
+            if self.ring.read_exact(&mut words[..n]).await.is_err() {
+                self.ring.clear();
+                return Err(Error::Overrun);
+            }
+            self.settling -= n;
+        }
+        for chunk in samples.chunks_mut(CHUNK_SAMPLES) {
+            let words = &mut words[..chunk.len() * WORDS_PER_SAMPLE];
+            if self.ring.read_exact(words).await.is_err() {
+                self.ring.clear();
+                return Err(Error::Overrun);
+            }
+            self.decimator.process(words, chunk);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PdmMicrophone<'_> {
+    fn drop(&mut self) {
+        pac::SPI2.i2scfgr().modify(|w| w.set_i2se(false));
+        pac::SPI2.cr2().modify(|w| w.set_rxdmaen(false));
     }
 }
@@ -0,0 +1,330 @@
+//! Morse code and blink-code signalling
+//!
+//! Reports text or numeric fault codes on an LED and/or the audio DAC's beeper, for
+//! units without a serial console. Encoders produce a sequence of [`Element`]s in
+//! time units; a [`Signaller`] plays them at a chosen speed on any [`SignalOutput`].
+//!
+//! - [`Morse`]: International Morse code (letters, digits, common punctuation)
+//! - [`BlinkCode`]: One group of blinks per decimal digit, e.g. 23 = `** ***`
+//!
+//! # Example
+//! ```ignore
+//! let signaller = Signaller::from_wpm(12);
+//! signaller.play(&mut leds[Led::Red], Morse::new("SOS")).await;
+//!
+//! // Fault 23 on the red LED and the beeper at once
+//! signaller.play(&mut (&mut leds[Led::Red], &mut dac), BlinkCode::new(23)).await;
+//! ```
+
+use core::str::Chars;
+
+use embassy_stm32::gpio::Output;
+use embassy_time::{Duration, Timer};
+
+use crate::audio::{Beep, BeepFrequency, CS43L22};
+
+/// Tone used by [`CS43L22`] as a [`SignalOutput`]
+const BEEP: Beep = Beep::continuous(BeepFrequency::B5);
+
+/// One on or off period of a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Element {
+    /// Output on (LED lit, tone playing) or off
+    pub on: bool,
+    /// Length in time units
+    pub units: u8,
+}
+
+impl Element {
+    /// An on period
+    pub const fn on(units: u8) -> Self {
+        Self { on: true, units }
+    }
+
+    /// An off period
+    pub const fn off(units: u8) -> Self {
+        Self { on: false, units }
+    }
+}
+
+/// Morse code for a character as dots and dashes, e.g. `'A'` is `".-"`
+///
+/// Letters are case-insensitive. Returns `None` for characters without a code.
+pub fn encode(c: char) -> Option<&'static str> {
+    Some(match c.to_ascii_uppercase() {
+        'A' => ".-",
+        'B' => "-...",
+        'C' => "-.-.",
+        'D' => "-..",
+        'E' => ".",
+        'F' => "..-.",
+        'G' => "--.",
+        'H' => "....",
+        'I' => "..",
+        'J' => ".---",
+        'K' => "-.-",
+        'L' => ".-..",
+        'M' => "--",
+        'N' => "-.",
+        'O' => "---",
+        'P' => ".--.",
+        'Q' => "--.-",
+        'R' => ".-.",
+        'S' => "...",
+        'T' => "-",
+        'U' => "..-",
+        'V' => "...-",
+        'W' => ".--",
+        'X' => "-..-",
+        'Y' => "-.--",
+        'Z' => "--..",
+        '0' => "-----",
+        '1' => ".----",
+        '2' => "..---",
+        '3' => "...--",
+        '4' => "....-",
+        '5' => ".....",
+        '6' => "-....",
+        '7' => "--...",
+        '8' => "---..",
+        '9' => "----.",
+        '.' => ".-.-.-",
+        ',' => "--..--",
+        '?' => "..--..",
+        '/' => "-..-.",
+        '-' => "-....-",
+        '=' => "-...-",
+        ':' => "---...",
+        '_' => "..--.-",
+        '@' => ".--.-.",
+        _ => return None,
+    })
+}
+
+/// Morse code encoder
+///
+/// Uses standard timing: dot 1 unit, dash 3, gap within a character 1, between
+/// characters 3 and between words 7. Characters without a code are skipped.
+pub struct Morse<'a> {
+    chars: Chars<'a>,
+    symbols: &'static [u8],
+    gap: u8,
+    started: bool,
+    word_break: bool,
+}
+
+impl<'a> Morse<'a> {
+    /// Encode `text`
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars(),
+            symbols: &[],
+            gap: 0,
+            started: false,
+            word_break: false,
+        }
+    }
+}
+
+impl Iterator for Morse<'_> {
+    type Item = Element;
+
+    fn next(&mut self) -> Option<Element> {
+        loop {
+            if let Some((&symbol, rest)) = self.symbols.split_first() {
+                if self.gap > 0 {
+                    return Some(Element::off(core::mem::take(&mut self.gap)));
+                }
+                self.symbols = rest;
+                self.gap = 1;
+                return Some(Element::on(if symbol == b'-' { 3 } else { 1 }));
+            }
+
+            let c = self.chars.next()?;
+            if c.is_whitespace() {
+                self.word_break = true;
+                continue;
+            }
+            let Some(code) = encode(c) else {
+                continue;
+            };
+
+            self.gap = match (self.started, self.word_break) {
+                (false, _) => 0,
+                (true, false) => 3,
+                (true, true) => 7,
+            };
+            self.started = true;
+            self.word_break = false;
+            self.symbols = code.as_bytes();
+        }
+    }
+}
+
+/// Blink-code encoder for numeric fault codes
+///
+/// Each decimal digit is sent as that many 1-unit blinks (10 for a zero) separated by
+/// 1-unit gaps, with a 3-unit gap between digits. Easier to read by eye than Morse.
+pub struct BlinkCode {
+    digits: [u8; 5],
+    len: usize,
+    index: usize,
+    remaining: u8,
+    gap: u8,
+}
+
+impl BlinkCode {
+    /// Encode `code`
+    pub fn new(code: u16) -> Self {
+        let mut digits = [0; 5];
+        let mut len = 0;
+        let mut rest = code;
+        loop {
+            digits[len] = (rest % 10) as u8;
+            len += 1;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        digits[..len].reverse();
+
+        Self {
+            digits,
+            len,
+            index: 0,
+            remaining: 0,
+            gap: 0,
+        }
+    }
+}
+
+impl Iterator for BlinkCode {
+    type Item = Element;
+
+    fn next(&mut self) -> Option<Element> {
+        loop {
+            if self.remaining > 0 {
+                if self.gap > 0 {
+                    return Some(Element::off(core::mem::take(&mut self.gap)));
+                }
+                self.remaining -= 1;
+                self.gap = 1;
+                return Some(Element::on(1));
+            }
+
+            if self.index >= self.len {
+                return None;
+            }
+            let digit = self.digits[self.index];
+            self.remaining = if digit == 0 { 10 } else { digit };
+            self.gap = if self.index > 0 { 3 } else { 0 };
+            self.index += 1;
+        }
+    }
+}
+
+/// Something that can be switched on and off to signal
+pub trait SignalOutput {
+    /// Start signalling (LED on, tone on)
+    fn signal_on(&mut self);
+
+    /// Stop signalling
+    fn signal_off(&mut self);
+}
+
+/// An LED or any other GPIO, e.g. `&mut leds[Led::Red]`
+impl SignalOutput for Output<'_> {
+    fn signal_on(&mut self) {
+        self.set_high();
+    }
+
+    fn signal_off(&mut self) {
+        self.set_low();
+    }
+}
+
+/// The audio DAC's beep generator at 1 kHz
+impl SignalOutput for CS43L22<'_> {
+    fn signal_on(&mut self) {
+        self.start_beep(BEEP);
+    }
+
+    fn signal_off(&mut self) {
+        self.stop_beep();
+    }
+}
+
+impl<T: SignalOutput + ?Sized> SignalOutput for &mut T {
+    fn signal_on(&mut self) {
+        (**self).signal_on();
+    }
+
+    fn signal_off(&mut self) {
+        (**self).signal_off();
+    }
+}
+
+/// Two outputs signalling together, e.g. an LED and the beeper
+impl<A: SignalOutput, B: SignalOutput> SignalOutput for (A, B) {
+    fn signal_on(&mut self) {
+        self.0.signal_on();
+        self.1.signal_on();
+    }
+
+    fn signal_off(&mut self) {
+        self.0.signal_off();
+        self.1.signal_off();
+    }
+}
+
+/// Plays [`Element`] sequences with a fixed time unit
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct Signaller {
+    /// Length of one time unit
+    pub unit: Duration,
+}
+
+impl Default for Signaller {
+    /// 200 ms units, slow enough to count blinks by eye
+    fn default() -> Self {
+        Self {
+            unit: Duration::from_millis(200),
+        }
+    }
+}
+
+impl Signaller {
+    /// Create a signaller with the given time unit
+    pub const fn new(unit: Duration) -> Self {
+        Self { unit }
+    }
+
+    /// Create a signaller for a Morse speed in words per minute (PARIS standard)
+    pub fn from_wpm(wpm: u8) -> Self {
+        Self {
+            unit: Duration::from_millis(1200 / wpm.max(1) as u64),
+        }
+    }
+
+    /// Play a sequence on `output`, leaving it off afterwards
+    ///
+    /// # Arguments
+    /// * `output` - LED, beeper or both
+    /// * `elements` - e.g. [`Morse::new`] or [`BlinkCode::new`]
+    pub async fn play(
+        &self,
+        output: &mut impl SignalOutput,
+        elements: impl IntoIterator<Item = Element>,
+    ) {
+        for element in elements {
+            if element.on {
+                output.signal_on();
+            } else {
+                output.signal_off();
+            }
+            Timer::after(self.unit * element.units as u32).await;
+        }
+        output.signal_off();
+    }
+}
@@ -0,0 +1,120 @@
+//! PDM to PCM conversion
+//!
+//! The MP45DT02 microphone outputs a 1-bit pulse density modulated (PDM) stream:
+//! the density of ones follows the sound pressure. [`PdmDecimator`] filters the
+//! bits into 16-bit PCM at [`DECIMATION`] times less than the PDM clock.
+//!
+//! The conversion does not touch the hardware, so it is tested on the host (see
+//! `host-tests/`); [`PdmMicrophone`](crate::microphone::PdmMicrophone) feeds it
+//! from the DMA.
+
+/// PDM bits per PCM sample
+pub const DECIMATION: u32 = 64;
+
+/// PDM words (16 bits) per PCM sample
+pub(crate) const WORDS_PER_SAMPLE: usize = DECIMATION as usize / 16;
+
+/// Order of the CIC filter
+const CIC_ORDER: usize = 4;
+
+/// Shift from the CIC output (gain 64^4 = 2^24) to 16 bits
+const CIC_SHIFT: u32 = 9;
+
+/// Pole of the DC-blocking filter (about 10 Hz at 16 kHz)
+const DC_POLE: f32 = 0.996;
+
+/// PDM to PCM converter
+///
+/// A 4th-order CIC filter decimating by 64, followed by a DC-blocking high-pass
+/// filter that removes the microphone's offset. A 1.024 MHz PDM clock gives
+/// 16 kHz PCM.
+///
+/// # Example
+/// ```ignore
+/// let mut decimator = PdmDecimator::new();
+/// let mut pcm = [0i16; 16];
+/// let n = decimator.process(&pdm_words, &mut pcm);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PdmDecimator {
+    integrators: [i32; CIC_ORDER],
+    combs: [i32; CIC_ORDER],
+    words: usize,
+    dc_input: f32,
+    dc_output: f32,
+}
+
+impl Default for PdmDecimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdmDecimator {
+    /// Create a decimator with cleared filters
+    pub const fn new() -> Self {
+        Self {
+            integrators: [0; CIC_ORDER],
+            combs: [0; CIC_ORDER],
+            words: 0,
+            dc_input: 0.0,
+            dc_output: 0.0,
+        }
+    }
+
+    /// Feed 16 PDM bits, first bit in the MSB
+    ///
+    /// Returns a PCM sample after every fourth word.
+    pub fn push(&mut self, word: u16) -> Option<i16> {
+        let [i1, i2, i3, i4] = &mut self.integrators;
+        for bit in (0..16).rev() {
+            // A one is +1, a zero -1; the integrators wrap, which the combs undo
+            let x = ((word >> bit) & 1) as i32 * 2 - 1;
+            *i1 = i1.wrapping_add(x);
+            *i2 = i2.wrapping_add(*i1);
+            *i3 = i3.wrapping_add(*i2);
+            *i4 = i4.wrapping_add(*i3);
+        }
+
+        self.words += 1;
+        if self.words < WORDS_PER_SAMPLE {
+            return None;
+        }
+        self.words = 0;
+
+        let mut y = self.integrators[CIC_ORDER - 1];
+        for delayed in &mut self.combs {
+            let x = y;
+            y = x.wrapping_sub(*delayed);
+            *delayed = x;
+        }
+
+        let x = y as f32;
+        self.dc_output = x - self.dc_input + DC_POLE * self.dc_output;
+        self.dc_input = x;
+        let sample = self.dc_output / (1 << CIC_SHIFT) as f32;
+        Some(sample.clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+
+    /// Convert PDM words to PCM, returning the number of samples written
+    ///
+    /// Stops early if `pcm` is full.
+    pub fn process(&mut self, pdm: &[u16], pcm: &mut [i16]) -> usize {
+        let mut n = 0;
+        for &word in pdm {
+            if n == pcm.len() {
+                break;
+            }
+            if let Some(sample) = self.push(word) {
+                pcm[n] = sample;
+                n += 1;
+            }
+        }
+        n
+    }
+
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
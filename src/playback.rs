@@ -0,0 +1,238 @@
+//! PCM playback through the CS43L22
+//!
+//! Streams 16-bit stereo audio to the DAC over I2S3, fed by DMA from a ring buffer.
+//! The sample rate is set per stream, so each sound plays at its own rate.
+//!
+//! ## Pin connections on STM32F411E-DISCO:
+//! - I2S3_MCK: PC7
+//! - I2S3_CK: PC10
+//! - I2S3_SD: PC12
+//! - I2S3_WS: PA4
+//! - TX DMA: DMA1 stream 5
+//!
+//! The I2S clock comes from PLLI2S, which must be enabled when initializing the HAL
+//! with [`configure_audio_pll`]. Its 135.5 MHz output gives sample rates within
+//! 0.25% for the 8, 11.025, 16, 22.05, 44.1 and 48 kHz families (32 kHz is 3% off).
+//!
+//! [`AudioClock`](crate::audio::AudioClock) uses the same peripherals; use one or the other.
+//!
+//! # Example
+//! ```ignore
+//! let mut config = embassy_stm32::Config::default();
+//! configure_audio_pll(&mut config);
+//! let p = embassy_stm32::init(config);
+//!
+//! let mut dac = CS43L22::new(p.I2C1, p.PB6, p.PB9, p.PD4);
+//! dac.power_on();
+//!
+//! let mut buffer = [0u16; 2048];
+//! let mut output = PcmOutput::new(p.SPI3, p.PC7, p.PC10, p.PC12, p.PA4, p.DMA1_CH5, &mut buffer);
+//! output.play_wav(&Wav::parse(include_bytes!("chime.wav")).unwrap()).await.unwrap();
+//! ```
+
+use defmt::{debug, info};
+use embassy_stm32::i2s::{self, I2S};
+use embassy_stm32::peripherals::{DMA1_CH5, PA4, PC10, PC12, PC7, RCC, SPI3};
+use embassy_stm32::rcc::{Pll, PllMul, PllPreDiv, PllRDiv, PllSource, HSI_FREQ};
+use embassy_stm32::time::Hertz;
+use embassy_stm32::Peri;
+
+use crate::wav::Wav;
+
+pub use embassy_stm32::i2s::Error;
+
+/// Frames converted per DMA write
+const CHUNK_FRAMES: usize = 64;
+
+/// PLLI2S settings for 135.5 MHz from a 1 MHz input
+const PLLI2S_N: u16 = 271;
+const PLLI2S_R: u8 = 2;
+
+/// I2S kernel clock set up by [`configure_audio_pll`], in Hz
+///
+/// For reference only: the drivers use the clock PLLI2S was actually configured
+/// with, which differs if `rcc.plli2s` is set up otherwise.
+pub const I2S_CLOCK: u32 = 1_000_000 * PLLI2S_N as u32 / PLLI2S_R as u32;
+
+/// I2S kernel clock from PLLI2S in Hz, as the HAL's I2S driver reads it
+///
+/// Panics if PLLI2S is off, see [`configure_audio_pll`].
+pub(crate) fn i2s_clock() -> u32 {
+    // SAFETY: the RCC token is only used to read the clock frequencies set by `embassy_stm32::init`
+    let rcc = unsafe { RCC::steal() };
+    embassy_stm32::rcc::clocks(&rcc)
+        .plli2s1_r
+        .to_hertz()
+        .expect("PLLI2S is off, see configure_audio_pll")
+        .0
+}
+
+/// Enable PLLI2S for audio in the HAL clock configuration
+///
+/// Sets up PLLI2S for a 135.5 MHz I2S clock from the configured PLL source.
+///
+/// # Example
+/// ```ignore
+/// let mut config = embassy_stm32::Config::default();
+/// configure_audio_pll(&mut config);
+/// let p = embassy_stm32::init(config);
+/// ```
+pub fn configure_audio_pll(config: &mut embassy_stm32::Config) {
+    let input = match (config.rcc.pll_src, config.rcc.hse) {
+        (PllSource::HSE, Some(hse)) => hse.freq,
+        _ => HSI_FREQ,
+    };
+
+    config.rcc.plli2s = Some(Pll {
+        prediv: PllPreDiv::from_bits((input.0 / 1_000_000) as u8),
+        mul: PllMul::from_bits(PLLI2S_N),
+        divp: None,
+        divq: None,
+        divr: Some(PllRDiv::from_bits(PLLI2S_R)),
+    });
+}
+
+/// I2S3 audio output to the CS43L22
+///
+/// Owns I2S3, its pins, the TX DMA stream and the DMA ring buffer. A larger buffer
+/// tolerates longer gaps between writes at the cost of latency; 2048 words hold
+/// about 21 ms of 48 kHz stereo audio.
+pub struct PcmOutput<'d> {
+    spi3: Peri<'d, SPI3>,
+    mck: Peri<'d, PC7>,
+    ck: Peri<'d, PC10>,
+    sd: Peri<'d, PC12>,
+    ws: Peri<'d, PA4>,
+    dma: Peri<'d, DMA1_CH5>,
+    buffer: &'d mut [u16],
+}
+
+impl<'d> PcmOutput<'d> {
+    /// Claim I2S3, its pins and DMA stream; `buffer` is the DMA ring buffer
+    pub fn new(
+        spi3: Peri<'d, SPI3>,
+        mck: Peri<'d, PC7>,
+        ck: Peri<'d, PC10>,
+        sd: Peri<'d, PC12>,
+        ws: Peri<'d, PA4>,
+        dma: Peri<'d, DMA1_CH5>,
+        buffer: &'d mut [u16],
+    ) -> Self {
+        Self {
+            spi3,
+            mck,
+            ck,
+            sd,
+            ws,
+            dma,
+            buffer,
+        }
+    }
+
+    /// Start a stream at `sample_rate` Hz
+    ///
+    /// The DAC plays silence until the first frames are written. The stream runs
+    /// until it is finished or dropped.
+    pub fn open(&mut self, sample_rate: u32) -> PcmStream<'_> {
+        self.buffer.fill(0);
+        let silence_words = self.buffer.len();
+
+        let mut config = i2s::Config::default();
+        config.frequency = Hertz(sample_rate);
+
+        let mut i2s = I2S::new_txonly(
+            self.spi3.reborrow(),
+            self.sd.reborrow(),
+            self.ws.reborrow(),
+            self.ck.reborrow(),
+            self.mck.reborrow(),
+            self.dma.reborrow(),
+            &mut *self.buffer,
+            config,
+        );
+        i2s.start();
+        debug!("PCM stream started at {} Hz", sample_rate);
+
+        PcmStream { i2s, silence_words }
+    }
+
+    /// Play 16-bit stereo frames at `sample_rate` Hz until the iterator ends
+    pub async fn play(
+        &mut self,
+        sample_rate: u32,
+        frames: impl IntoIterator<Item = [i16; 2]>,
+    ) -> Result<(), Error> {
+        let mut stream = self.open(sample_rate);
+        let mut frames = frames.into_iter();
+        let mut chunk = [[0i16; 2]; CHUNK_FRAMES];
+
+        loop {
+            let mut n = 0;
+            for (slot, frame) in chunk.iter_mut().zip(&mut frames) {
+                *slot = frame;
+                n += 1;
+            }
+            if n == 0 {
+                break;
+            }
+            stream.write(&chunk[..n]).await?;
+        }
+
+        stream.finish().await
+    }
+
+    /// Play a WAV file at its own sample rate
+    ///
+    /// Mono files play on both channels.
+    pub async fn play_wav(&mut self, wav: &Wav<'_>) -> Result<(), Error> {
+        let format = wav.format();
+        info!(
+            "Playing WAV: {} Hz, {} bit, {} channel(s), {} ms",
+            format.sample_rate,
+            format.bits_per_sample,
+            format.channels,
+            wav.duration_ms()
+        );
+        self.play(format.sample_rate, wav.frames()).await
+    }
+}
+
+/// A running I2S stream, see [`PcmOutput::open`]
+pub struct PcmStream<'s> {
+    i2s: I2S<'s, u16>,
+    silence_words: usize,
+}
+
+impl PcmStream<'_> {
+    /// Queue frames for playback
+    ///
+    /// Waits while the ring buffer is full. Returns [`Error::Overrun`] if the
+    /// buffer ran empty since the last write (an audible gap).
+    pub async fn write(&mut self, frames: &[[i16; 2]]) -> Result<(), Error> {
+        let mut words = [0u16; 2 * CHUNK_FRAMES];
+        for chunk in frames.chunks(CHUNK_FRAMES) {
+            for (pair, [left, right]) in words.chunks_exact_mut(2).zip(chunk) {
+                pair[0] = *left as u16;
+                pair[1] = *right as u16;
+            }
+            self.i2s.write(&words[..2 * chunk.len()]).await?;
+        }
+        Ok(())
+    }
+
+    /// Play out the queued frames and stop
+    pub async fn finish(mut self) -> Result<(), Error> {
+        // Push the last frames out of the ring buffer with silence
+        let silence = [0u16; 2 * CHUNK_FRAMES];
+        let mut remaining = self.silence_words;
+        while remaining > 0 {
+            let n = remaining.min(silence.len());
+            self.i2s.write(&silence[..n]).await?;
+            remaining -= n;
+        }
+
+        self.i2s.stop().await;
+        debug!("PCM stream stopped");
+        Ok(())
+    }
+}
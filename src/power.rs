@@ -0,0 +1,523 @@
+//! Power management: onboard device power profiles and low-power STOP and STANDBY modes
+//!
+//! [`PowerProfile`] switches the onboard devices between full operation, slow
+//! low-power sampling and off:
+//!
+//! | Device            | Active           | LowPowerSampling             | Off                  |
+//! |-------------------|------------------|------------------------------|----------------------|
+//! | L3GD20 / I3G4250D | Normal, 6.1 mA   | Sleep, 2 mA                  | Power-down, 5 µA     |
+//! | LSM303DLHC        | Configured rates | Accel 10 Hz LP, mag 0.75 Hz  | Power-down, 1 µA     |
+//! | LSM303AGR         | Configured rates | Accel 10 Hz LP, mag 10 Hz LP | Power-down, mag idle |
+//! | MP45DT02          | Capture resumed  | Clock stopped, 20 µA         | Clock stopped        |
+//! | CS43L22           | Powered on       | Powered down                 | Powered down         |
+//!
+//! The gyroscope rows apply to both parts, which share the [`L3GD20`] driver. The
+//! e-compass is covered either way through [`ECompass`], and the microphone
+//! through [`PdmMicrophone`], which stops I2S2 and its DMA along with the clock.
+//!
+//! The STM32F411 draws about 14 µA in STOP mode with the low-power regulator and
+//! flash in deep power-down, and 2-3 µA in STANDBY (datasheet, tables 27-30). The
+//! onboard sensors and audio DAC draw far more unless they are powered down as well.
+//!
+//! - **STOP**: all clocks stop, RAM and registers are kept. [`LowPower::stop`]
+//!   returns when a wake source fires, with the system clock restored.
+//! - **STANDBY**: the core domain is switched off. Waking up resets the MCU;
+//!   check [`woke_from_standby`] at startup.
+//!
+//! Wake sources ([`WakeSources`]):
+//! - User button B1 (PA0, also the WKUP pin)
+//! - LSM303DLHC accelerometer INT1 (PE4), see
+//!   [`LSM303DLHC::enable_motion_interrupt`](crate::compass::LSM303DLHC::enable_motion_interrupt)
+//! - RTC wake-up timer (1 s resolution, up to about 36 hours)
+//!
+//! # Note
+//! embassy-time does not advance while in STOP mode: the time driver's timer is
+//! stopped along with every other clock. The RTC keeps running, so use
+//! [`LowPower::rtc`] for wall-clock time across sleeps. With the default clock
+//! configuration the RTC runs from the LSI oscillator, which is only accurate to
+//! a few percent.
+//!
+//! # Example
+//! ```ignore
+//! let rtc = Rtc::new(p.RTC, RtcConfig::default());
+//! let mut power = LowPower::new(rtc, p.PE4, p.EXTI4);
+//! compass.enable_motion_interrupt(0.25);
+//!
+//! let wake = WakeSources { button: true, motion: true, rtc: Some(Duration::from_secs(600)) };
+//! let reason = power.stop(wake, &mut [&mut gyro, &mut compass, &mut dac]);
+//!
+//! // Between bursts of activity, keep only slow motion sampling running
+//! PowerProfile::LowPowerSampling.apply(&mut [&mut gyro, &mut compass, &mut mic, &mut dac]);
+//! ```
+
+use defmt::{debug, info, warn};
+use embassy_stm32::gpio::{Input, Pull};
+use embassy_stm32::pac::{self, exti::regs::Lines, pwr::vals::Pdds, rtc::vals::Wucksel};
+use embassy_stm32::peripherals::{EXTI4, PE4};
+use embassy_stm32::rtc::Rtc;
+use embassy_stm32::Peri;
+use embassy_time::Duration;
+
+use crate::audio::CS43L22;
+use crate::board::ECompass;
+use crate::compass::LSM303DLHC;
+use crate::gyro::L3GD20;
+use crate::lsm303agr::LSM303AGR;
+use crate::microphone::PdmMicrophone;
+
+/// EXTI lines used as wake sources
+const EXTI_BUTTON: usize = 0; // PA0
+const EXTI_MOTION: usize = 4; // PE4
+const EXTI_RTC_WAKEUP: usize = 22;
+
+/// SYSCFG_EXTICR port number for GPIOE
+const EXTICR_PORT_E: u8 = 4;
+
+/// Longest RTC wake-up period with the 1 Hz clock and the 2^16 offset
+const MAX_RTC_WAKEUP_SECS: u64 = 2 * 65_536;
+
+/// A device that can be powered down around a low-power period
+pub trait Suspend {
+    /// Power down before sleeping
+    fn suspend(&mut self);
+
+    /// Power back up after waking
+    fn resume(&mut self);
+}
+
+/// Gyroscope power-down mode (L3GD20 and I3G4250D)
+impl Suspend for L3GD20<'_> {
+    fn suspend(&mut self) {
+        self.power_down();
+    }
+
+    fn resume(&mut self) {
+        self.power_up();
+    }
+}
+
+/// Magnetometer sleep; the accelerometer keeps running for motion wake-up
+impl Suspend for LSM303DLHC<'_> {
+    fn suspend(&mut self) {
+        self.set_mag_sleep(true);
+    }
+
+    fn resume(&mut self) {
+        self.set_mag_sleep(false);
+    }
+}
+
+/// Magnetometer idle mode; the accelerometer keeps running
+impl Suspend for LSM303AGR<'_> {
+    fn suspend(&mut self) {
+        self.set_mag_sleep(true);
+    }
+
+    fn resume(&mut self) {
+        self.set_mag_sleep(false);
+    }
+}
+
+/// Magnetometer sleep on either e-compass
+impl Suspend for ECompass<'_> {
+    fn suspend(&mut self) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.suspend(),
+            ECompass::Lsm303agr(c) => c.suspend(),
+        }
+    }
+
+    fn resume(&mut self) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.resume(),
+            ECompass::Lsm303agr(c) => c.resume(),
+        }
+    }
+}
+
+/// DAC power-down; [`resume`](Suspend::resume) powers it back on
+impl Suspend for CS43L22<'_> {
+    fn suspend(&mut self) {
+        self.power_off();
+    }
+
+    fn resume(&mut self) {
+        self.power_on();
+    }
+}
+
+/// Capture and microphone clock stop; capture resumes if it was running
+impl Suspend for PdmMicrophone<'_> {
+    fn suspend(&mut self) {
+        self.power_down();
+    }
+
+    fn resume(&mut self) {
+        self.power_up();
+    }
+}
+
+/// Power state of the onboard devices while the MCU is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum PowerProfile {
+    /// Everything on at the configured data rates
+    Active,
+    /// Motion sensors sampling slowly (or ready to wake quickly), audio off
+    LowPowerSampling,
+    /// Everything powered down; register contents are kept
+    Off,
+}
+
+impl PowerProfile {
+    /// Switch `devices` to this profile
+    ///
+    /// # Example
+    /// ```ignore
+    /// PowerProfile::Off.apply(&mut [&mut gyro, &mut compass, &mut mic, &mut dac]);
+    /// ```
+    pub fn apply(self, devices: &mut [&mut dyn PowerManaged]) {
+        for device in devices.iter_mut() {
+            device.set_power_profile(self);
+        }
+        info!("Power profile: {}", self);
+    }
+}
+
+/// A device that follows the board's [`PowerProfile`]
+pub trait PowerManaged {
+    /// Switch to `profile`
+    fn set_power_profile(&mut self, profile: PowerProfile);
+}
+
+/// Sleep mode for low-power sampling, so the gyro wakes up quickly
+impl PowerManaged for L3GD20<'_> {
+    fn set_power_profile(&mut self, profile: PowerProfile) {
+        match profile {
+            PowerProfile::Active => self.power_up(),
+            PowerProfile::LowPowerSampling => self.sleep(),
+            PowerProfile::Off => self.power_down(),
+        }
+    }
+}
+
+impl PowerManaged for LSM303DLHC<'_> {
+    fn set_power_profile(&mut self, profile: PowerProfile) {
+        match profile {
+            PowerProfile::Active => self.power_up(),
+            PowerProfile::LowPowerSampling => self.low_power_sampling(),
+            PowerProfile::Off => self.power_down(),
+        }
+    }
+}
+
+impl PowerManaged for LSM303AGR<'_> {
+    fn set_power_profile(&mut self, profile: PowerProfile) {
+        match profile {
+            PowerProfile::Active => self.power_up(),
+            PowerProfile::LowPowerSampling => self.low_power_sampling(),
+            PowerProfile::Off => self.power_down(),
+        }
+    }
+}
+
+impl PowerManaged for ECompass<'_> {
+    fn set_power_profile(&mut self, profile: PowerProfile) {
+        match self {
+            ECompass::Lsm303dlhc(c) => c.set_power_profile(profile),
+            ECompass::Lsm303agr(c) => c.set_power_profile(profile),
+        }
+    }
+}
+
+/// Capture stops with the clock; [`PowerProfile::Active`] resumes it if it was running
+impl PowerManaged for PdmMicrophone<'_> {
+    fn set_power_profile(&mut self, profile: PowerProfile) {
+        match profile {
+            PowerProfile::Active => self.power_up(),
+            PowerProfile::LowPowerSampling | PowerProfile::Off => self.power_down(),
+        }
+    }
+}
+
+/// [`PowerProfile::Active`] blocks for 100 ms while the DAC powers up
+impl PowerManaged for CS43L22<'_> {
+    fn set_power_profile(&mut self, profile: PowerProfile) {
+        match profile {
+            PowerProfile::Active => self.power_on(),
+            PowerProfile::LowPowerSampling | PowerProfile::Off => self.power_off(),
+        }
+    }
+}
+
+/// Events that end a low-power period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct WakeSources {
+    /// User button press (PA0)
+    pub button: bool,
+    /// Accelerometer INT1 (PE4), STOP mode only
+    pub motion: bool,
+    /// RTC wake-up timer period, rounded to whole seconds
+    pub rtc: Option<Duration>,
+}
+
+impl Default for WakeSources {
+    /// Wake on the user button only
+    fn default() -> Self {
+        Self {
+            button: true,
+            motion: false,
+            rtc: None,
+        }
+    }
+}
+
+/// What ended a STOP period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum WakeReason {
+    /// User button pressed
+    Button,
+    /// Accelerometer motion interrupt
+    Motion,
+    /// RTC wake-up timer expired
+    Rtc,
+    /// Any other interrupt or event
+    Other,
+}
+
+/// Low-power mode control
+///
+/// Owns the RTC (for the wake-up timer), the accelerometer interrupt pin and its
+/// EXTI line, which is routed to port E while stopped.
+pub struct LowPower<'d> {
+    rtc: Rtc,
+    motion: Input<'d>,
+    _exti4: Peri<'d, EXTI4>,
+}
+
+impl<'d> LowPower<'d> {
+    /// Set up low-power control
+    ///
+    /// # Arguments
+    /// * `rtc` - RTC, providing the 1 Hz wake-up timer clock
+    /// * `pe4` - LSM303DLHC INT1 pin
+    /// * `exti4` - EXTI line of `pe4`, so no other `Px4` pin can use it
+    pub fn new(rtc: Rtc, pe4: Peri<'d, PE4>, exti4: Peri<'d, EXTI4>) -> Self {
+        Self {
+            rtc,
+            motion: Input::new(pe4, Pull::None),
+            _exti4: exti4,
+        }
+    }
+
+    /// The RTC, which keeps time across STOP and STANDBY
+    pub fn rtc(&mut self) -> &mut Rtc {
+        &mut self.rtc
+    }
+
+    /// Enter STOP mode until a wake source fires
+    ///
+    /// Suspends `devices` first and resumes them afterwards. The system clock
+    /// (HSE/PLL, if used) is restored before returning. Returns [`WakeReason::Motion`]
+    /// without stopping if the latched motion interrupt is still pending.
+    ///
+    /// # Arguments
+    /// * `wake` - Enabled wake sources
+    /// * `devices` - Onboard devices to power down while stopped
+    pub fn stop(&mut self, wake: WakeSources, devices: &mut [&mut dyn Suspend]) -> WakeReason {
+        for device in devices.iter_mut() {
+            device.suspend();
+        }
+
+        let rtsr = self.configure_wake_events(wake);
+        // INT1 is latched, so a pending motion interrupt never gives the rising edge
+        // that would end the STOP period
+        let reason = if wake.motion && self.motion.is_high() {
+            info!("Motion interrupt pending, not entering STOP mode");
+            WakeReason::Motion
+        } else {
+            info!("Entering STOP mode ({})", wake);
+
+            // Remember the clock tree; STOP switches back to HSI and turns HSE and the PLLs off
+            let cr = pac::RCC.cr().read();
+            let sw = pac::RCC.cfgr().read().sw();
+
+            pac::PWR.cr1().modify(|w| {
+                w.set_pdds(Pdds::STOP_MODE);
+                w.set_lpds(true);
+                w.set_fpds(true);
+            });
+            deep_sleep();
+
+            restore_clocks(cr.hseon(), cr.pllon(), cr.plli2son(), sw);
+
+            let reason = self.wake_reason(wake);
+            info!("Woke from STOP mode: {}", reason);
+            reason
+        };
+        self.clear_wake_events(rtsr);
+
+        for device in devices.iter_mut() {
+            device.resume();
+        }
+        reason
+    }
+
+    /// Enter STANDBY mode; the MCU resets when a wake source fires
+    ///
+    /// Only the button (WKUP pin) and the RTC can wake from STANDBY. `devices` are
+    /// suspended first, and stay suspended until re-initialized after the reset.
+    pub fn standby(&mut self, wake: WakeSources, devices: &mut [&mut dyn Suspend]) -> ! {
+        for device in devices.iter_mut() {
+            device.suspend();
+        }
+        if wake.motion {
+            warn!("Motion wake-up is not available in STANDBY mode");
+        }
+
+        self.configure_rtc_wakeup(wake.rtc);
+        info!("Entering STANDBY mode ({})", wake);
+
+        pac::PWR.csr1().modify(|w| w.set_ewup(wake.button));
+        // Wake-up flags must be clear, or STANDBY is left immediately
+        pac::PWR.cr1().modify(|w| {
+            w.set_cwuf(true);
+            w.set_pdds(Pdds::STANDBY_MODE);
+        });
+
+        loop {
+            deep_sleep();
+        }
+    }
+
+    /// Route the wake sources to EXTI events
+    ///
+    /// Returns the rising-edge triggers from before, for [`clear_wake_events`](Self::clear_wake_events).
+    fn configure_wake_events(&mut self, wake: WakeSources) -> Lines {
+        let rtsr = pac::EXTI.rtsr(0).read();
+        if wake.motion {
+            pac::SYSCFG
+                .exticr(EXTI_MOTION / 4)
+                .modify(|w| w.set_exti(EXTI_MOTION % 4, EXTICR_PORT_E));
+        }
+        self.configure_rtc_wakeup(wake.rtc);
+
+        for (line, enabled) in [
+            (EXTI_BUTTON, wake.button),
+            (EXTI_MOTION, wake.motion),
+            (EXTI_RTC_WAKEUP, wake.rtc.is_some()),
+        ] {
+            if enabled {
+                pac::EXTI.rtsr(0).modify(|w| w.set_line(line, true));
+                pac::EXTI.emr(0).modify(|w| w.set_line(line, true));
+            }
+        }
+        rtsr
+    }
+
+    /// Disable the wake events again and restore the rising-edge triggers in `rtsr`
+    fn clear_wake_events(&mut self, rtsr: Lines) {
+        for line in [EXTI_BUTTON, EXTI_MOTION, EXTI_RTC_WAKEUP] {
+            pac::EXTI.emr(0).modify(|w| w.set_line(line, false));
+            pac::EXTI.rtsr(0).modify(|w| w.set_line(line, rtsr.line(line)));
+        }
+        pac::EXTI.pr(0).write(|w| w.set_line(EXTI_RTC_WAKEUP, true));
+        self.configure_rtc_wakeup(None);
+    }
+
+    /// Start (or stop, for `None`) the RTC wake-up timer
+    fn configure_rtc_wakeup(&mut self, period: Option<Duration>) {
+        let rtc = pac::RTC;
+        rtc.wpr().write(|w| w.set_key(0xCA));
+        rtc.wpr().write(|w| w.set_key(0x53));
+
+        rtc.cr().modify(|w| {
+            w.set_wute(false);
+            w.set_wutie(false);
+        });
+
+        if let Some(period) = period {
+            while !rtc.isr().read().wutwf() {}
+
+            let secs = period.as_secs().clamp(1, MAX_RTC_WAKEUP_SECS);
+            // The timer fires after WUT + 1 clock cycles
+            let (wucksel, wut) = if secs > 65_536 {
+                (Wucksel::CLOCK_SPARE_WITH_OFFSET, secs - 65_536 - 1)
+            } else {
+                (Wucksel::CLOCK_SPARE, secs - 1)
+            };
+            rtc.wutr().write(|w| w.set_wut(wut as u16));
+            rtc.cr().modify(|w| w.set_wucksel(wucksel));
+            rtc.isr().modify(|w| w.set_wutf(false));
+            rtc.cr().modify(|w| {
+                w.set_wutie(true);
+                w.set_wute(true);
+            });
+            debug!("RTC wake-up timer set to {} s", secs);
+        }
+
+        rtc.wpr().write(|w| w.set_key(0xFF));
+    }
+
+    /// Work out which enabled source ended the STOP period
+    fn wake_reason(&mut self, wake: WakeSources) -> WakeReason {
+        if wake.rtc.is_some() && pac::RTC.isr().read().wutf() {
+            WakeReason::Rtc
+        } else if wake.motion && self.motion.is_high() {
+            WakeReason::Motion
+        } else if wake.button && button_pressed() {
+            WakeReason::Button
+        } else {
+            WakeReason::Other
+        }
+    }
+}
+
+/// Check whether the last reset was a wake-up from STANDBY, and clear the flags
+///
+/// Call this once at startup, before [`LowPower::standby`] is used again.
+pub fn woke_from_standby() -> bool {
+    let standby = pac::PWR.csr1().read().sbf();
+    pac::PWR.cr1().modify(|w| {
+        w.set_csbf(true);
+        w.set_cwuf(true);
+    });
+    standby
+}
+
+/// Read the user button level directly, whoever owns PA0
+fn button_pressed() -> bool {
+    pac::GPIOA.idr().read().idr(EXTI_BUTTON) == pac::gpio::vals::Idr::HIGH
+}
+
+/// Enter the deep-sleep mode selected in PWR_CR
+fn deep_sleep() {
+    // SAFETY: only the SLEEPDEEP bit is touched, and it is cleared again before returning
+    let mut scb = unsafe { cortex_m::Peripherals::steal() }.SCB;
+    scb.set_sleepdeep();
+
+    // The first WFE consumes any pending event, the second one sleeps
+    cortex_m::asm::sev();
+    cortex_m::asm::wfe();
+    cortex_m::asm::wfe();
+
+    scb.clear_sleepdeep();
+}
+
+/// Turn the oscillators and PLLs back on and reselect the system clock
+fn restore_clocks(hse: bool, pll: bool, plli2s: bool, sw: pac::rcc::vals::Sw) {
+    let rcc = pac::RCC;
+    if hse {
+        rcc.cr().modify(|w| w.set_hseon(true));
+        while !rcc.cr().read().hserdy() {}
+    }
+    if pll {
+        rcc.cr().modify(|w| w.set_pllon(true));
+        while !rcc.cr().read().pllrdy() {}
+    }
+    if plli2s {
+        rcc.cr().modify(|w| w.set_plli2son(true));
+        while !rcc.cr().read().plli2srdy() {}
+    }
+    rcc.cfgr().modify(|w| w.set_sw(sw));
+    while rcc.cfgr().read().sws() != sw {}
+}
@@ -0,0 +1,209 @@
+//! Sample-rate conversion
+//!
+//! Converts a stream of stereo frames from one rate to another, e.g. microphone
+//! audio decimated from a PDM clock to the DAC's I2S rate. Even nominally equal
+//! rates differ slightly when the clocks are not locked, which slowly fills or
+//! drains the buffers in between until audio is lost. [`DriftControl`] measures
+//! a buffer's fill level and trims the conversion ratio to keep it steady.
+//!
+//! - [`Resampler`]: streaming linear interpolation with a 32.32 fixed-point position
+//! - [`DriftControl`]: PI controller turning fill levels into a ratio trim in ppm
+//!
+//! Linear interpolation is cheap and fine for speech and for ratios near 1. It does
+//! not filter, so when converting down, frequencies above the new Nyquist limit
+//! alias; low-pass the input first (e.g. [`Biquad::low_pass`](crate::dsp::Biquad::low_pass)).
+//!
+//! # Example
+//! ```ignore
+//! let mut resampler = Resampler::new(37_500, 48_000);
+//! let mut drift = DriftControl::new(MIC_TARGET_SAMPLES);
+//!
+//! loop {
+//!     let ppm = drift.update(mic.available().unwrap());
+//!     resampler.set_trim_ppm(ppm);
+//!
+//!     mic.read(&mut samples).await.unwrap();
+//!     // ... samples to frames
+//!     let (_, produced) = resampler.process(&frames, &mut converted);
+//!     stream.write(&converted[..produced]).await.unwrap();
+//! }
+//! ```
+
+/// 1.0 in the 32.32 fixed-point position
+const ONE: u64 = 1 << 32;
+
+/// Largest ratio trim applied by [`DriftControl`], in ppm
+pub const MAX_TRIM_PPM: i32 = 5_000;
+
+/// Streaming linear-interpolation resampler for `[left, right]` frames
+///
+/// Input can be fed in blocks of any size; the position between input frames
+/// carries over between calls. The output lags the input by one frame.
+#[derive(Debug, Clone, defmt::Format)]
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    /// Nominal input frames per output frame (32.32)
+    nominal_step: u64,
+    /// Trimmed step
+    step: u64,
+    trim_ppm: i32,
+    /// Position after `last` (32.32), may reach past the next frame
+    position: u64,
+    /// Last input frame consumed
+    last: [i16; 2],
+}
+
+impl Resampler {
+    /// Create a resampler from `input_rate` to `output_rate` Hz
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        let nominal_step = ((input_rate as u64) << 32) / output_rate.max(1) as u64;
+        Self {
+            input_rate,
+            output_rate,
+            nominal_step,
+            step: nominal_step,
+            trim_ppm: 0,
+            position: 0,
+            last: [0; 2],
+        }
+    }
+
+    /// Input and output rates in Hz
+    pub fn rates(&self) -> (u32, u32) {
+        (self.input_rate, self.output_rate)
+    }
+
+    /// Input frames consumed per output frame, including the trim
+    pub fn ratio(&self) -> f32 {
+        self.step as f32 / ONE as f32
+    }
+
+    /// Speed up (positive) or slow down (negative) the input consumption
+    ///
+    /// Clamped to ±[`MAX_TRIM_PPM`].
+    pub fn set_trim_ppm(&mut self, ppm: i32) {
+        let ppm = ppm.clamp(-MAX_TRIM_PPM, MAX_TRIM_PPM);
+        self.trim_ppm = ppm;
+        let delta = self.nominal_step as i64 * ppm as i64 / 1_000_000;
+        self.step = (self.nominal_step as i64 + delta) as u64;
+    }
+
+    /// Current ratio trim in ppm
+    pub fn trim_ppm(&self) -> i32 {
+        self.trim_ppm
+    }
+
+    /// Output frames produced from `input_frames` frames, give or take one
+    pub fn output_len(&self, input_frames: usize) -> usize {
+        ((input_frames as u64 * ONE) / self.step) as usize + 1
+    }
+
+    /// Convert `input` into `output`
+    ///
+    /// Returns the number of input frames consumed and output frames written.
+    /// Stops when either runs out; unconsumed input must be passed again.
+    pub fn process(&mut self, input: &[[i16; 2]], output: &mut [[i16; 2]]) -> (usize, usize) {
+        let mut consumed = 0;
+        let mut produced = 0;
+
+        while produced < output.len() {
+            while self.position >= ONE {
+                let Some(&frame) = input.get(consumed) else {
+                    return (consumed, produced);
+                };
+                self.last = frame;
+                consumed += 1;
+                self.position -= ONE;
+            }
+            let Some(next) = input.get(consumed) else {
+                break;
+            };
+
+            let frac = (self.position >> 16) as i64; // 0..65536
+            output[produced] = [0, 1].map(|ch| {
+                let a = self.last[ch] as i64;
+                let b = next[ch] as i64;
+                // Full-scale steps (±65535) times the fraction need more than 32 bits
+                (a + (((b - a) * frac) >> 16)) as i16
+            });
+            produced += 1;
+            self.position += self.step;
+        }
+
+        (consumed, produced)
+    }
+
+    /// Clear the stream position and history, keeping the ratio
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.last = [0; 2];
+    }
+}
+
+/// Weight of a new fill level in the running average
+const LEVEL_SMOOTHING: f32 = 0.02;
+
+/// Proportional gain, ppm per frame of error
+const KP: f32 = 100.0;
+
+/// Integral gain, ppm per frame of error per update
+const KI: f32 = 0.2;
+
+/// Keeps a buffer between two clocks at a target fill level
+///
+/// Call [`update`](Self::update) once per block with the number of frames
+/// waiting in the buffer feeding the resampler (e.g. the microphone's DMA buffer)
+/// and pass the result to [`Resampler::set_trim_ppm`]. A rising level means the
+/// input clock is faster, so the resampler consumes faster. The integral term
+/// settles at the clock mismatch, which [`drift_ppm`](Self::drift_ppm) reports.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct DriftControl {
+    target: f32,
+    level: f32,
+    integral: f32,
+    trim_ppm: i32,
+}
+
+impl DriftControl {
+    /// Create a controller holding the buffer at `target` frames
+    pub fn new(target: usize) -> Self {
+        Self {
+            target: target as f32,
+            level: target as f32,
+            integral: 0.0,
+            trim_ppm: 0,
+        }
+    }
+
+    /// Feed a fill level, returning the ratio trim in ppm
+    pub fn update(&mut self, level: usize) -> i32 {
+        self.level += (level as f32 - self.level) * LEVEL_SMOOTHING;
+        let error = self.level - self.target;
+
+        let limit = MAX_TRIM_PPM as f32;
+        self.integral = (self.integral + KI * error).clamp(-limit, limit);
+        self.trim_ppm = (KP * error + self.integral).clamp(-limit, limit) as i32;
+        self.trim_ppm
+    }
+
+    /// Last trim in ppm
+    pub fn trim_ppm(&self) -> i32 {
+        self.trim_ppm
+    }
+
+    /// Smoothed fill level in frames
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Estimated clock mismatch in ppm (the integral term)
+    pub fn drift_ppm(&self) -> i32 {
+        self.integral as i32
+    }
+
+    /// Restart from the target level
+    pub fn reset(&mut self) {
+        *self = Self::new(self.target as usize);
+    }
+}
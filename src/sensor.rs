@@ -0,0 +1,207 @@
+//! Common sensor traits
+//!
+//! The STM32F411E-DISCO has shipped with different motion sensors over its revisions
+//! (see [`crate::board`]). These traits let application code read any of them
+//! without knowing which part is fitted, and let fusion, logging or calibration code
+//! be tested against mock sensors.
+//!
+//! All sensors report a [`Vector3`] in the units of their trait.
+//!
+//! # Example
+//! ```ignore
+//! fn tilt<A: Accelerometer>(accel: &mut A) -> f32 {
+//!     let g = accel.read_acceleration().normalized();
+//!     g.z.acos()
+//! }
+//! ```
+
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// Host builds use std's float methods instead (see `host-tests/`)
+#[cfg(target_os = "none")]
+use micromath::F32Ext;
+
+/// A 3-axis vector
+#[derive(Debug, Default, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Vector3 {
+    /// X component
+    pub x: f32,
+    /// Y component
+    pub y: f32,
+    /// Z component
+    pub z: f32,
+}
+
+impl Vector3 {
+    /// The zero vector
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+    /// Create a vector from its components
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Dot product
+    pub fn dot(&self, other: Vector3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Cross product
+    pub fn cross(&self, other: Vector3) -> Vector3 {
+        Vector3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    /// Euclidean length
+    pub fn norm(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    /// Unit vector in the same direction (zero stays zero)
+    pub fn normalized(&self) -> Vector3 {
+        let norm = self.norm();
+        if norm > 0.0 {
+            *self / norm
+        } else {
+            Vector3::ZERO
+        }
+    }
+
+    /// Apply `f` to each component
+    pub fn map(&self, f: impl Fn(f32) -> f32) -> Vector3 {
+        Vector3::new(f(self.x), f(self.y), f(self.z))
+    }
+
+    /// Rotate by a 3x3 rotation matrix (row-major)
+    pub fn rotate(&self, matrix: &[[f32; 3]; 3]) -> Vector3 {
+        let row = |r: &[f32; 3]| r[0] * self.x + r[1] * self.y + r[2] * self.z;
+        Vector3::new(row(&matrix[0]), row(&matrix[1]), row(&matrix[2]))
+    }
+
+    /// Rotate by `angle` radians around `axis` (right-hand rule)
+    pub fn rotate_about(&self, axis: Vector3, angle: f32) -> Vector3 {
+        // Rodrigues' rotation formula
+        let k = axis.normalized();
+        let (sin, cos) = (angle.sin(), angle.cos());
+        *self * cos + k.cross(*self) * sin + k * (k.dot(*self) * (1.0 - cos))
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, rhs: Vector3) -> Vector3 {
+        Vector3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, rhs: Vector3) -> Vector3 {
+        Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Mul<f32> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, rhs: f32) -> Vector3 {
+        Vector3::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Div<f32> for Vector3 {
+    type Output = Vector3;
+
+    fn div(self, rhs: f32) -> Vector3 {
+        Vector3::new(self.x / rhs, self.y / rhs, self.z / rhs)
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl AddAssign for Vector3 {
+    fn add_assign(&mut self, rhs: Vector3) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Vector3 {
+    fn sub_assign(&mut self, rhs: Vector3) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign<f32> for Vector3 {
+    fn mul_assign(&mut self, rhs: f32) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign<f32> for Vector3 {
+    fn div_assign(&mut self, rhs: f32) {
+        *self = *self / rhs;
+    }
+}
+
+/// Error from a sensor's built-in self-test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SelfTestError {
+    /// The sensor stopped producing new data
+    DataTimeout,
+}
+
+/// A 3-axis angular rate sensor
+pub trait Gyroscope {
+    /// Sensor-specific configuration (range, data rate, ...)
+    type GyroConfig;
+
+    /// Apply a configuration
+    fn configure_gyro(&mut self, config: Self::GyroConfig);
+
+    /// Check if new angular rate data is available
+    fn gyro_data_ready(&mut self) -> bool;
+
+    /// Read angular rate in degrees per second
+    fn read_angular_rate(&mut self) -> Vector3;
+}
+
+/// A 3-axis linear acceleration sensor
+pub trait Accelerometer {
+    /// Sensor-specific configuration (range, data rate, ...)
+    type AccelConfig;
+
+    /// Apply a configuration
+    fn configure_accel(&mut self, config: Self::AccelConfig);
+
+    /// Check if new acceleration data is available
+    fn accel_data_ready(&mut self) -> bool;
+
+    /// Read acceleration in g
+    fn read_acceleration(&mut self) -> Vector3;
+}
+
+/// A 3-axis magnetic field sensor
+pub trait Magnetometer {
+    /// Sensor-specific configuration (range, data rate, ...)
+    type MagConfig;
+
+    /// Apply a configuration
+    fn configure_mag(&mut self, config: Self::MagConfig);
+
+    /// Check if new magnetic field data is available
+    fn mag_data_ready(&mut self) -> bool;
+
+    /// Read magnetic field in gauss
+    fn read_magnetic_field(&mut self) -> Vector3;
+}
@@ -0,0 +1,809 @@
+//! Key-value store in internal flash
+//!
+//! Keeps calibration data and settings (gyro bias, magnetometer hard/soft iron,
+//! volume, LED brightness, ...) across resets in two 16 KiB sectors of the
+//! STM32F411VE's flash:
+//! - Sector 1: 0x0800_4000 - 0x0800_7FFF
+//! - Sector 2: 0x0800_8000 - 0x0800_BFFF
+//!
+//! The module needs the `storage` feature, which makes the BSP's linker script start
+//! the code at sector 3 (see `storage.x`). Sector 0 keeps the vector table, and the
+//! firmware still has 464 KiB. [`Storage::new`] panics if the code starts before
+//! sector 3, in case an application links with its own memory layout.
+//!
+//! ## Log Format
+//! Values are appended to a log in the active sector, so updating a key never
+//! erases anything. Each sector starts with a header, followed by the records:
+//!
+//! | Field      | Bytes | Contents                                    |
+//! |------------|-------|---------------------------------------------|
+//! | Magic      | 4     | `KVS1` (sector header)                      |
+//! | Generation | 4     | Counts compactions (sector header)          |
+//! | Key        | 2     | Record key, `0xFFFF` is reserved            |
+//! | Length     | 2     | Value length, 0 for a removed key           |
+//! | CRC        | 4     | CRC-32 of key, length and value             |
+//! | Value      | n     | Padded with `0xFF` to a multiple of 4 bytes |
+//!
+//! When the active sector is full, the latest value of every key is copied to the
+//! other sector, which then becomes active. The sectors are erased in turn, once
+//! per fill, spreading the wear over both (each is rated for 10,000 erase cycles).
+//! A table of the keys in the log (at most [`MAX_KEYS`]) lets the compaction find
+//! the latest record of every key in a single pass.
+//!
+//! ## Power-Fail Safety
+//! - A record torn by a reset or power loss fails its CRC check and is ignored,
+//!   so the key keeps its previous value.
+//! - The header of the new sector is written after all records have been copied.
+//!   An interrupted compaction leaves the old sector active.
+//!
+//! The log logic only relies on [`NorFlash`], so it can be exercised against an
+//! in-memory flash model with [`Storage::from_flash`]. The host tests do this,
+//! including power loss at every byte of an update or compaction (see `host-tests/`).
+//!
+//! # Note
+//! Programming and erasing stall the CPU, since code runs from the same flash.
+//! A compaction erases a 16 KiB sector, which takes 250-500 ms (datasheet, table 45).
+//!
+//! [Datasheet](docs/stm32f411ve.pdf)
+//!
+//! # Example
+//! ```ignore
+//! let mut storage = Storage::new(p.FLASH).unwrap();
+//!
+//! let bias = storage.load(keys::GYRO_BIAS).unwrap().unwrap_or_default();
+//! storage.save(keys::GYRO_BIAS, &new_bias).unwrap();
+//! ```
+
+use core::marker::PhantomData;
+
+use defmt::{debug, info, warn};
+// The hardware parts are left out of host builds (see `host-tests/`)
+#[cfg(target_os = "none")]
+use embassy_stm32::{
+    flash::{self, Blocking, Flash, FLASH_BASE},
+    peripherals::FLASH,
+    Peri,
+};
+use embedded_storage::nor_flash::NorFlash;
+
+#[cfg(target_os = "none")]
+use crate::audio::Volume;
+use crate::sensor::Vector3;
+#[cfg(target_os = "none")]
+use crate::temperature::TemperatureCalibration;
+
+/// Flash offsets of sectors 1 and 2, and their size
+#[cfg(target_os = "none")]
+const SECTOR_1: u32 = 0x4000;
+#[cfg(target_os = "none")]
+const SECTOR_2: u32 = 0x8000;
+#[cfg(target_os = "none")]
+const SECTOR_SIZE: u32 = 0x4000;
+
+/// Sector header magic, "KVS1"
+const MAGIC: u32 = u32::from_le_bytes(*b"KVS1");
+
+/// Size of the sector header and of a record header
+const SECTOR_HEADER: u32 = 8;
+const RECORD_HEADER: u32 = 8;
+
+/// Records are padded to whole flash words
+const ALIGN: u32 = 4;
+
+/// Key and length word of erased flash, marking the end of the log
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// Key reserved for the end-of-log marker
+const RESERVED_KEY: u16 = 0xFFFF;
+
+/// Largest value the typed helpers can store
+pub const MAX_VALUE_SIZE: usize = 64;
+
+/// Most keys the store holds; removed keys count until the next compaction
+pub const MAX_KEYS: usize = 64;
+
+/// Size of the chunks copied through RAM
+const CHUNK: usize = 32;
+
+/// Key-value store errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error<E> {
+    /// The flash driver failed
+    Flash(E),
+    /// Key `0xFFFF` is reserved
+    InvalidKey,
+    /// The value does not fit in a sector, or is empty
+    InvalidLength,
+    /// Even after compaction, there is no room for the value
+    Full,
+    /// Even after compaction, the store already holds [`MAX_KEYS`] other keys
+    TooManyKeys,
+}
+
+/// A record in the log
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    offset: u32,
+    key: u16,
+    len: u16,
+    crc: u32,
+}
+
+impl Record {
+    /// Flash space taken by the record, including padding
+    fn size(&self) -> u32 {
+        record_size(self.len as usize)
+    }
+
+    /// Offset of the next record
+    fn end(&self) -> u32 {
+        self.offset + self.size()
+    }
+}
+
+/// Wear-levelled, power-fail-safe key-value store
+///
+/// Keys are `u16` (except `0xFFFF`), values are byte strings. [`load`](Self::load)
+/// and [`save`](Self::save) handle typed values through a [`Key`].
+pub struct Storage<F> {
+    flash: F,
+    sectors: [u32; 2],
+    sector_size: u32,
+    active: usize,
+    generation: u32,
+    head: u32,
+    /// Keys in the active sector's log, in order of their first record
+    keys: [u16; MAX_KEYS],
+    key_count: usize,
+}
+
+#[cfg(target_os = "none")]
+impl<'d> Storage<Flash<'d, Blocking>> {
+    /// Open the store in sectors 1 and 2 of the internal flash
+    ///
+    /// Formats the sectors if they do not hold a valid log yet.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut storage = Storage::new(p.FLASH).unwrap();
+    /// ```
+    pub fn new(flash: Peri<'d, FLASH>) -> Result<Self, Error<flash::Error>> {
+        assert!(
+            code_start() >= FLASH_BASE as u32 + SECTOR_2 + SECTOR_SIZE,
+            "Firmware overlaps the storage sectors"
+        );
+        Self::from_flash(Flash::new_blocking(flash), [SECTOR_1, SECTOR_2], SECTOR_SIZE)
+    }
+}
+
+impl<F: NorFlash> Storage<F> {
+    /// Open the store on any NOR flash
+    ///
+    /// # Arguments
+    /// * `flash` - Flash driver (or an in-memory model)
+    /// * `sectors` - Offsets of the two erase sectors used by the log
+    /// * `sector_size` - Size of each sector in bytes
+    pub fn from_flash(flash: F, sectors: [u32; 2], sector_size: u32) -> Result<Self, Error<F::Error>> {
+        assert!(
+            (ALIGN as usize).is_multiple_of(F::WRITE_SIZE) && (ALIGN as usize).is_multiple_of(F::READ_SIZE),
+            "Flash word size not supported"
+        );
+
+        let mut storage = Self {
+            flash,
+            sectors,
+            sector_size,
+            active: 0,
+            generation: 0,
+            head: 0,
+            keys: [RESERVED_KEY; MAX_KEYS],
+            key_count: 0,
+        };
+        storage.mount()?;
+        Ok(storage)
+    }
+
+    /// Read the raw value of `key` into `buf`
+    ///
+    /// Returns the value length, or `None` if the key is not set. Only the first
+    /// `buf.len()` bytes are copied if the value is longer.
+    pub fn read(&mut self, key: u16, buf: &mut [u8]) -> Result<Option<usize>, Error<F::Error>> {
+        let Some(record) = self.find(key)? else {
+            return Ok(None);
+        };
+        let len = record.len as usize;
+        let copy = len.min(buf.len());
+        self.read_value(&record, &mut buf[..copy])?;
+        Ok(Some(len))
+    }
+
+    /// Store a raw value for `key`
+    ///
+    /// Nothing is written if the key already holds the same value.
+    pub fn write(&mut self, key: u16, value: &[u8]) -> Result<(), Error<F::Error>> {
+        if key == RESERVED_KEY {
+            return Err(Error::InvalidKey);
+        }
+        if value.is_empty() || value.len() > u16::MAX as usize {
+            return Err(Error::InvalidLength);
+        }
+        if let Some(record) = self.find(key)? {
+            if self.value_matches(&record, value)? {
+                return Ok(());
+            }
+        }
+        self.append(key, value)
+    }
+
+    /// Remove `key`
+    pub fn remove(&mut self, key: u16) -> Result<(), Error<F::Error>> {
+        if key == RESERVED_KEY {
+            return Err(Error::InvalidKey);
+        }
+        if self.find(key)?.is_some() {
+            self.append(key, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Load a typed value
+    ///
+    /// Returns `None` if the key is not set, or holds a value of another size
+    /// (e.g. written by an older firmware with a different type).
+    pub fn load<T: Storable>(&mut self, key: Key<T>) -> Result<Option<T>, Error<F::Error>> {
+        let mut buf = [0u8; MAX_VALUE_SIZE];
+        match self.read(key.id, &mut buf)? {
+            Some(len) if len == T::SIZE => Ok(Some(T::decode(&buf[..len]))),
+            Some(len) => {
+                warn!("Stored value for key {:#x} has {} bytes, expected {}", key.id, len, T::SIZE);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Save a typed value
+    pub fn save<T: Storable>(&mut self, key: Key<T>, value: &T) -> Result<(), Error<F::Error>> {
+        let mut buf = [0u8; MAX_VALUE_SIZE];
+        value.encode(&mut buf[..T::SIZE]);
+        self.write(key.id, &buf[..T::SIZE])
+    }
+
+    /// Erase both sectors, removing every key
+    pub fn format(&mut self) -> Result<(), Error<F::Error>> {
+        self.erase(1)?;
+        self.erase(0)?;
+        self.write_header(0, 1)?;
+        self.active = 0;
+        self.generation = 1;
+        self.head = self.sectors[0] + SECTOR_HEADER;
+        self.key_count = 0;
+        info!("Storage formatted");
+        Ok(())
+    }
+
+    /// Free space in the active sector, in bytes
+    ///
+    /// Compaction may free more, if keys have been overwritten or removed.
+    pub fn free(&self) -> u32 {
+        self.sectors[self.active] + self.sector_size - self.head
+    }
+
+    /// Find the active sector and the end of its log
+    fn mount(&mut self) -> Result<(), Error<F::Error>> {
+        let generations = [self.read_header(0)?, self.read_header(1)?];
+        let active = match generations {
+            [Some(a), Some(b)] => Some(if b > a { 1 } else { 0 }),
+            [Some(_), None] => Some(0),
+            [None, Some(_)] => Some(1),
+            [None, None] => None,
+        };
+
+        let Some(active) = active else {
+            warn!("No valid storage found, formatting");
+            return self.format();
+        };
+
+        self.active = active;
+        self.generation = generations[active].unwrap_or_default();
+        self.head = self.sectors[active] + SECTOR_HEADER;
+        self.key_count = 0;
+        while let Some(record) = self.record_at(active, self.head)? {
+            self.head = record.end();
+            if self.key_index(record.key).is_none() && !self.add_key(record.key) {
+                warn!("Storage holds more than {} keys, key {:#x} is lost on compaction", MAX_KEYS, record.key);
+            }
+        }
+        // A torn record header leaves programmed bytes behind; compact before the next write
+        if !self.is_erased(self.head)? {
+            warn!("Storage log damaged at {:#x}", self.head);
+            self.head = self.sectors[active] + self.sector_size;
+        }
+
+        debug!(
+            "Storage mounted: sector {}, generation {}, {} bytes free",
+            active,
+            self.generation,
+            self.free()
+        );
+        Ok(())
+    }
+
+    /// Append a record, compacting the log first if the sector is full
+    fn append(&mut self, key: u16, value: &[u8]) -> Result<(), Error<F::Error>> {
+        let size = record_size(value.len());
+        if size > self.sector_size - SECTOR_HEADER {
+            return Err(Error::InvalidLength);
+        }
+        let new_key = self.key_index(key).is_none();
+        if size > self.free() || (new_key && self.key_count == MAX_KEYS) {
+            self.compact()?;
+            if size > self.free() {
+                return Err(Error::Full);
+            }
+            if new_key && self.key_count == MAX_KEYS {
+                return Err(Error::TooManyKeys);
+            }
+        }
+
+        let mut header = [0u8; RECORD_HEADER as usize];
+        header[0..2].copy_from_slice(&key.to_le_bytes());
+        header[2..4].copy_from_slice(&(value.len() as u16).to_le_bytes());
+        let crc = crc32(crc32(CRC_INIT, &header[0..4]), value) ^ CRC_INIT;
+        header[4..8].copy_from_slice(&crc.to_le_bytes());
+
+        // The header goes first, so a torn value fails its CRC check
+        let offset = self.head;
+        self.flash.write(offset, &header).map_err(Error::Flash)?;
+
+        let mut chunk = [0xFFu8; CHUNK];
+        for (i, part) in value.chunks(CHUNK).enumerate() {
+            let padded = part.len().next_multiple_of(ALIGN as usize);
+            chunk[..part.len()].copy_from_slice(part);
+            chunk[part.len()..padded].fill(0xFF);
+            let at = offset + RECORD_HEADER + (i * CHUNK) as u32;
+            self.flash.write(at, &chunk[..padded]).map_err(Error::Flash)?;
+        }
+
+        self.head += size;
+        if new_key {
+            self.add_key(key);
+        }
+        Ok(())
+    }
+
+    /// Copy the latest value of every key to the other sector and switch to it
+    ///
+    /// One pass over the log finds the latest record of each key, so only the
+    /// records that are copied are CRC-checked (unless one of them is corrupt).
+    fn compact(&mut self) -> Result<(), Error<F::Error>> {
+        let from = self.active;
+        let to = 1 - from;
+        info!("Compacting storage into sector {}", to);
+
+        let mut latest = [None; MAX_KEYS];
+        let mut src = self.sectors[from] + SECTOR_HEADER;
+        while let Some(record) = self.record_at(from, src)? {
+            src = record.end();
+            if let Some(index) = self.key_index(record.key) {
+                latest[index] = Some(record);
+            }
+        }
+
+        self.erase(to)?;
+
+        let keys = self.keys;
+        let mut kept = [RESERVED_KEY; MAX_KEYS];
+        let mut kept_count = 0;
+        let mut dst = self.sectors[to] + SECTOR_HEADER;
+        for (&key, &newest) in keys[..self.key_count].iter().zip(&latest) {
+            let record = match newest {
+                Some(record) if !self.is_valid(&record)? => self.latest_valid(from, key, record.offset)?,
+                newest => newest,
+            };
+            let Some(record) = record.filter(|record| record.len > 0) else {
+                continue;
+            };
+            self.copy(&record, dst)?;
+            dst += record.size();
+            kept[kept_count] = key;
+            kept_count += 1;
+        }
+
+        // Writing the header last makes the new sector take over atomically
+        self.write_header(to, self.generation.wrapping_add(1))?;
+        self.active = to;
+        self.generation = self.generation.wrapping_add(1);
+        self.head = dst;
+        self.keys = kept;
+        self.key_count = kept_count;
+
+        info!("Storage compacted, {} bytes free", self.free());
+        Ok(())
+    }
+
+    /// Latest valid record for `key`, unless it was removed
+    fn find(&mut self, key: u16) -> Result<Option<Record>, Error<F::Error>> {
+        let end = self.sectors[self.active] + self.sector_size;
+        let found = self.latest_valid(self.active, key, end)?;
+        Ok(found.filter(|record| record.len > 0))
+    }
+
+    /// Latest valid record for `key` starting before `before`
+    ///
+    /// Only the newest candidate is CRC-checked; older records are looked at only
+    /// if it is corrupt.
+    fn latest_valid(&mut self, sector: usize, key: u16, mut before: u32) -> Result<Option<Record>, Error<F::Error>> {
+        loop {
+            let mut newest = None;
+            let mut offset = self.sectors[sector] + SECTOR_HEADER;
+            while let Some(record) = self.record_at(sector, offset)? {
+                if record.offset >= before {
+                    break;
+                }
+                offset = record.end();
+                if record.key == key {
+                    newest = Some(record);
+                }
+            }
+
+            match newest {
+                Some(record) if self.is_valid(&record)? => return Ok(Some(record)),
+                Some(record) => before = record.offset,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Position of `key` in the key table
+    fn key_index(&self, key: u16) -> Option<usize> {
+        self.keys[..self.key_count].iter().position(|&k| k == key)
+    }
+
+    /// Add `key` to the key table, returning `false` if it is full
+    fn add_key(&mut self, key: u16) -> bool {
+        if self.key_count == MAX_KEYS {
+            return false;
+        }
+        self.keys[self.key_count] = key;
+        self.key_count += 1;
+        true
+    }
+
+    /// Parse the record header at `offset`, or `None` at the end of the log
+    fn record_at(&mut self, sector: usize, offset: u32) -> Result<Option<Record>, Error<F::Error>> {
+        let sector_end = self.sectors[sector] + self.sector_size;
+        if offset + RECORD_HEADER > sector_end {
+            return Ok(None);
+        }
+
+        let mut header = [0u8; RECORD_HEADER as usize];
+        self.flash.read(offset, &mut header).map_err(Error::Flash)?;
+        if u32::from_le_bytes([header[0], header[1], header[2], header[3]]) == ERASED {
+            return Ok(None);
+        }
+
+        let record = Record {
+            offset,
+            key: u16::from_le_bytes([header[0], header[1]]),
+            len: u16::from_le_bytes([header[2], header[3]]),
+            crc: u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+        };
+        // A header damaged badly enough to point past the sector ends the log
+        if record.end() > sector_end {
+            warn!("Storage record at {:#x} runs past the sector", offset);
+            return Ok(None);
+        }
+        Ok(Some(record))
+    }
+
+    /// Check whether the record header at `offset` is still erased
+    fn is_erased(&mut self, offset: u32) -> Result<bool, Error<F::Error>> {
+        if offset + RECORD_HEADER > self.sectors[self.active] + self.sector_size {
+            return Ok(true);
+        }
+        let mut header = [0u8; RECORD_HEADER as usize];
+        self.flash.read(offset, &mut header).map_err(Error::Flash)?;
+        Ok(header.iter().all(|&byte| byte == 0xFF))
+    }
+
+    /// Check a record against its CRC
+    fn is_valid(&mut self, record: &Record) -> Result<bool, Error<F::Error>> {
+        let mut header = [0u8; 4];
+        header[0..2].copy_from_slice(&record.key.to_le_bytes());
+        header[2..4].copy_from_slice(&record.len.to_le_bytes());
+        let mut crc = crc32(CRC_INIT, &header);
+
+        let mut chunk = [0u8; CHUNK];
+        let mut offset = record.offset + RECORD_HEADER;
+        let mut remaining = record.len as usize;
+        while remaining > 0 {
+            let len = remaining.min(CHUNK);
+            let padded = len.next_multiple_of(ALIGN as usize);
+            self.flash.read(offset, &mut chunk[..padded]).map_err(Error::Flash)?;
+            crc = crc32(crc, &chunk[..len]);
+            offset += padded as u32;
+            remaining -= len;
+        }
+
+        let valid = crc ^ CRC_INIT == record.crc;
+        if !valid {
+            debug!("Ignoring corrupt storage record at {:#x}", record.offset);
+        }
+        Ok(valid)
+    }
+
+    /// Read the start of a record's value into `buf`
+    fn read_value(&mut self, record: &Record, buf: &mut [u8]) -> Result<(), Error<F::Error>> {
+        let mut chunk = [0u8; CHUNK];
+        let mut offset = record.offset + RECORD_HEADER;
+        for part in buf.chunks_mut(CHUNK) {
+            let padded = part.len().next_multiple_of(ALIGN as usize);
+            self.flash.read(offset, &mut chunk[..padded]).map_err(Error::Flash)?;
+            part.copy_from_slice(&chunk[..part.len()]);
+            offset += CHUNK as u32;
+        }
+        Ok(())
+    }
+
+    /// Compare a record's value with `value`
+    fn value_matches(&mut self, record: &Record, value: &[u8]) -> Result<bool, Error<F::Error>> {
+        if record.len as usize != value.len() {
+            return Ok(false);
+        }
+
+        let mut chunk = [0u8; CHUNK];
+        let mut offset = record.offset + RECORD_HEADER;
+        for part in value.chunks(CHUNK) {
+            let padded = part.len().next_multiple_of(ALIGN as usize);
+            self.flash.read(offset, &mut chunk[..padded]).map_err(Error::Flash)?;
+            if chunk[..part.len()] != *part {
+                return Ok(false);
+            }
+            offset += CHUNK as u32;
+        }
+        Ok(true)
+    }
+
+    /// Copy a whole record, header included, to `dst`
+    fn copy(&mut self, record: &Record, dst: u32) -> Result<(), Error<F::Error>> {
+        let mut chunk = [0u8; CHUNK];
+        let mut done = 0;
+        while done < record.size() {
+            let len = (record.size() - done).min(CHUNK as u32);
+            let buf = &mut chunk[..len as usize];
+            self.flash.read(record.offset + done, buf).map_err(Error::Flash)?;
+            self.flash.write(dst + done, buf).map_err(Error::Flash)?;
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// Read a sector header, returning its generation if valid
+    fn read_header(&mut self, sector: usize) -> Result<Option<u32>, Error<F::Error>> {
+        let mut header = [0u8; SECTOR_HEADER as usize];
+        self.flash.read(self.sectors[sector], &mut header).map_err(Error::Flash)?;
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let generation = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        Ok((magic == MAGIC && generation != ERASED).then_some(generation))
+    }
+
+    /// Mark an erased sector as holding a log
+    fn write_header(&mut self, sector: usize, generation: u32) -> Result<(), Error<F::Error>> {
+        let mut header = [0u8; SECTOR_HEADER as usize];
+        header[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&generation.to_le_bytes());
+        self.flash.write(self.sectors[sector], &header).map_err(Error::Flash)
+    }
+
+    /// Erase one of the two sectors
+    fn erase(&mut self, sector: usize) -> Result<(), Error<F::Error>> {
+        let start = self.sectors[sector];
+        self.flash.erase(start, start + self.sector_size).map_err(Error::Flash)
+    }
+}
+
+/// Flash space taken by a record with a `len`-byte value
+fn record_size(len: usize) -> u32 {
+    RECORD_HEADER + (len as u32).next_multiple_of(ALIGN)
+}
+
+/// Start address of the code in flash
+///
+/// Only the vector table comes before `.text`; everything else loaded from flash follows it.
+#[cfg(target_os = "none")]
+fn code_start() -> u32 {
+    extern "C" {
+        static __stext: u32;
+    }
+    // Taking the address of an extern static is safe; the value is never read
+    core::ptr::addr_of!(__stext) as u32
+}
+
+/// CRC-32 (IEEE 802.3) initial value and final XOR
+const CRC_INIT: u32 = 0xFFFF_FFFF;
+
+/// Update a CRC-32 (IEEE 802.3, reflected) with `bytes`
+fn crc32(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    crc
+}
+
+/// A value that can be stored in the key-value store
+///
+/// Values are stored little-endian, with a fixed size of at most [`MAX_VALUE_SIZE`].
+pub trait Storable: Sized {
+    /// Encoded size in bytes
+    const SIZE: usize;
+
+    /// Write the value to `buf` (exactly `SIZE` bytes)
+    fn encode(&self, buf: &mut [u8]);
+
+    /// Read the value from `buf` (exactly `SIZE` bytes)
+    fn decode(buf: &[u8]) -> Self;
+}
+
+macro_rules! storable_number {
+    ($($ty:ty),*) => {
+        $(
+            impl Storable for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+
+                fn encode(&self, buf: &mut [u8]) {
+                    buf.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(buf: &[u8]) -> Self {
+                    let mut bytes = [0u8; core::mem::size_of::<$ty>()];
+                    bytes.copy_from_slice(buf);
+                    <$ty>::from_le_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+storable_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32);
+
+impl Storable for bool {
+    const SIZE: usize = 1;
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = *self as u8;
+    }
+
+    fn decode(buf: &[u8]) -> Self {
+        buf[0] != 0
+    }
+}
+
+impl<T: Storable + Copy + Default, const N: usize> Storable for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn encode(&self, buf: &mut [u8]) {
+        for (item, out) in self.iter().zip(buf.chunks_exact_mut(T::SIZE)) {
+            item.encode(out);
+        }
+    }
+
+    fn decode(buf: &[u8]) -> Self {
+        let mut items = [T::default(); N];
+        for (item, bytes) in items.iter_mut().zip(buf.chunks_exact(T::SIZE)) {
+            *item = T::decode(bytes);
+        }
+        items
+    }
+}
+
+impl Storable for Vector3 {
+    const SIZE: usize = 12;
+
+    fn encode(&self, buf: &mut [u8]) {
+        [self.x, self.y, self.z].encode(buf);
+    }
+
+    fn decode(buf: &[u8]) -> Self {
+        let [x, y, z] = <[f32; 3]>::decode(buf);
+        Vector3::new(x, y, z)
+    }
+}
+
+#[cfg(target_os = "none")]
+impl Storable for Volume {
+    const SIZE: usize = 4;
+
+    fn encode(&self, buf: &mut [u8]) {
+        self.db().encode(buf);
+    }
+
+    fn decode(buf: &[u8]) -> Self {
+        Volume::from_db(f32::decode(buf))
+    }
+}
+
+#[cfg(target_os = "none")]
+impl Storable for TemperatureCalibration {
+    const SIZE: usize = 4;
+
+    fn encode(&self, buf: &mut [u8]) {
+        self.offset.encode(buf);
+    }
+
+    fn decode(buf: &[u8]) -> Self {
+        Self {
+            offset: f32::decode(buf),
+        }
+    }
+}
+
+/// A typed key
+///
+/// # Example
+/// ```ignore
+/// const BOOT_COUNT: Key<u32> = Key::new(0x1000);
+/// let boots = storage.load(BOOT_COUNT)?.unwrap_or(0) + 1;
+/// storage.save(BOOT_COUNT, &boots)?;
+/// ```
+pub struct Key<T> {
+    id: u16,
+    _value: PhantomData<T>,
+}
+
+impl<T: Storable> Key<T> {
+    /// Create a key; application keys should start at `0x1000`
+    ///
+    /// Fails to compile (in a `const`) if the value type is too large.
+    pub const fn new(id: u16) -> Self {
+        assert!(id != RESERVED_KEY, "Key 0xFFFF is reserved");
+        assert!(T::SIZE > 0 && T::SIZE <= MAX_VALUE_SIZE, "Value size not supported");
+        Self {
+            id,
+            _value: PhantomData,
+        }
+    }
+
+    /// Raw key
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+}
+
+impl<T> Clone for Key<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Key<T> {}
+
+/// Keys for the board's calibration data and settings
+#[cfg(target_os = "none")]
+pub mod keys {
+    use super::Key;
+    use crate::audio::Volume;
+    use crate::sensor::Vector3;
+    use crate::temperature::TemperatureCalibration;
+
+    /// Gyroscope zero-rate offset in dps
+    pub const GYRO_BIAS: Key<Vector3> = Key::new(0x0001);
+    /// Magnetometer hard-iron offset in gauss
+    pub const MAG_HARD_IRON: Key<Vector3> = Key::new(0x0002);
+    /// Magnetometer soft-iron correction matrix (row-major)
+    pub const MAG_SOFT_IRON: Key<[[f32; 3]; 3]> = Key::new(0x0003);
+    /// Accelerometer zero-g offset in g
+    pub const ACCEL_OFFSET: Key<Vector3> = Key::new(0x0004);
+    /// Gyroscope temperature sensor calibration
+    pub const GYRO_TEMPERATURE: Key<TemperatureCalibration> = Key::new(0x0005);
+    /// E-compass temperature sensor calibration
+    pub const COMPASS_TEMPERATURE: Key<TemperatureCalibration> = Key::new(0x0006);
+    /// Audio output volume
+    pub const VOLUME: Key<Volume> = Key::new(0x0010);
+    /// LED brightness in percent, indexed by [`Led`](crate::leds::Led)
+    pub const LED_BRIGHTNESS: Key<[u8; 4]> = Key::new(0x0011);
+}
@@ -0,0 +1,732 @@
+//! Polyphonic tone synthesizer
+//!
+//! Generates 16-bit stereo PCM for [`PcmOutput`](crate::playback::PcmOutput) from
+//! a few voices, each an oscillator shaped by an ADSR envelope. Unlike the DAC's
+//! beep generator, any pitch can be played, several notes can sound at once and
+//! playback runs from the DMA stream without blocking other tasks.
+//!
+//! - [`Synth`]: voices started and released by hand, for alert sounds
+//! - [`Rtttl`]: parser for RTTTL ringtones (`name:d=4,o=5,b=120:8c,8e,g`)
+//! - [`Sequencer`]: plays one melody per voice, e.g. a tune and its bass line
+//!
+//! # Example
+//! ```ignore
+//! const RATE: u32 = 48_000;
+//!
+//! let tune = Rtttl::parse("Jingle:d=8,o=5,b=160:c,e,g,4c6").unwrap();
+//! output.play(RATE, Sequencer::new(RATE, [(tune.notes(), Instrument::BELL)])).await.unwrap();
+//! ```
+
+use core::str::Split;
+
+use defmt::warn;
+use embassy_time::Duration;
+
+/// Entries in the sine table
+const SINE_SIZE: usize = 256;
+
+/// One period of a sine wave, full scale
+const SINE: [i16; SINE_SIZE] = sine_table();
+
+/// Fraction of a note's length played before the release (articulation)
+const GATE: f32 = 0.9;
+
+/// Build the sine table at compile time
+const fn sine_table() -> [i16; SINE_SIZE] {
+    const PI: f32 = core::f32::consts::PI;
+    let mut table = [0i16; SINE_SIZE];
+    let mut i = 0;
+    while i < SINE_SIZE {
+        // Fold the angle into 0..=pi/2, where the Taylor series converges quickly
+        let mut x = 2.0 * PI * i as f32 / SINE_SIZE as f32;
+        let mut sign = 1.0;
+        if x > PI {
+            x -= PI;
+            sign = -1.0;
+        }
+        if x > PI / 2.0 {
+            x = PI - x;
+        }
+        let x2 = x * x;
+        let sin = x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0 * (1.0 - x2 / 72.0))));
+        table[i] = (sign * sin * i16::MAX as f32) as i16;
+        i += 1;
+    }
+    table
+}
+
+/// Oscillator waveform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Waveform {
+    /// Pure tone
+    Sine,
+    /// Hollow, bright tone (odd harmonics)
+    Square,
+    /// Soft tone, between sine and square
+    Triangle,
+    /// Buzzy tone (all harmonics)
+    Sawtooth,
+    /// Random values at the note's frequency, for clicks and hisses
+    Noise,
+}
+
+/// ADSR envelope
+///
+/// The level rises to full scale over `attack`, falls to `sustain` over `decay`,
+/// holds while the note is held, and fades to silence over `release`.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Envelope {
+    /// Rise time
+    pub attack: Duration,
+    /// Fall time to the sustain level
+    pub decay: Duration,
+    /// Level while held (0.0-1.0)
+    pub sustain: f32,
+    /// Fade time after the note is released
+    pub release: Duration,
+}
+
+impl Envelope {
+    /// Full level while held, with short ramps against clicks
+    pub const ORGAN: Self = Self {
+        attack: Duration::from_millis(5),
+        decay: Duration::from_millis(0),
+        sustain: 1.0,
+        release: Duration::from_millis(20),
+    };
+
+    /// A struck sound that fades while held
+    pub const PLUCK: Self = Self {
+        attack: Duration::from_millis(2),
+        decay: Duration::from_millis(300),
+        sustain: 0.0,
+        release: Duration::from_millis(50),
+    };
+}
+
+/// Sound of a voice
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Instrument {
+    /// Oscillator waveform
+    pub waveform: Waveform,
+    /// Volume envelope
+    pub envelope: Envelope,
+    /// Peak level (0.0-1.0 of full scale)
+    pub volume: f32,
+}
+
+impl Instrument {
+    /// Sine with a long decay
+    pub const BELL: Self = Self {
+        waveform: Waveform::Sine,
+        envelope: Envelope {
+            attack: Duration::from_millis(2),
+            decay: Duration::from_millis(600),
+            sustain: 0.2,
+            release: Duration::from_millis(300),
+        },
+        volume: 0.5,
+    };
+
+    /// Square wave at constant level, like a buzzer
+    pub const BUZZER: Self = Self {
+        waveform: Waveform::Square,
+        envelope: Envelope::ORGAN,
+        volume: 0.25,
+    };
+
+    /// Triangle wave at constant level, for bass lines
+    pub const BASS: Self = Self {
+        waveform: Waveform::Triangle,
+        envelope: Envelope::ORGAN,
+        volume: 0.5,
+    };
+
+    /// Plucked sawtooth
+    pub const PLUCK: Self = Self {
+        waveform: Waveform::Sawtooth,
+        envelope: Envelope::PLUCK,
+        volume: 0.3,
+    };
+
+    /// Short noise burst
+    pub const CLICK: Self = Self {
+        waveform: Waveform::Noise,
+        envelope: Envelope {
+            attack: Duration::from_millis(0),
+            decay: Duration::from_millis(30),
+            sustain: 0.0,
+            release: Duration::from_millis(10),
+        },
+        volume: 0.3,
+    };
+}
+
+/// Envelope stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// One oscillator with its envelope
+#[derive(Debug, Clone, Copy)]
+struct Voice {
+    instrument: Instrument,
+    phase: u32,
+    phase_step: u32,
+    noise: u32,
+    noise_value: i16,
+    stage: Stage,
+    level: f32,
+    attack_step: f32,
+    decay_step: f32,
+    release_step: f32,
+}
+
+impl Voice {
+    const IDLE: Self = Self {
+        instrument: Instrument::BUZZER,
+        phase: 0,
+        phase_step: 0,
+        noise: 0x2545_F491,
+        noise_value: 0,
+        stage: Stage::Idle,
+        level: 0.0,
+        attack_step: 0.0,
+        decay_step: 0.0,
+        release_step: 0.0,
+    };
+
+    /// Start a note; the envelope restarts from the current level, without a click
+    fn start(&mut self, frequency: f32, instrument: Instrument, sample_rate: u32) {
+        self.instrument = instrument;
+        self.phase_step = (frequency / sample_rate as f32 * 4_294_967_296.0) as u32;
+        self.attack_step = step(instrument.envelope.attack, 1.0, sample_rate);
+        self.decay_step = step(instrument.envelope.decay, 1.0 - instrument.envelope.sustain, sample_rate);
+        self.release_step = step(instrument.envelope.release, 1.0, sample_rate);
+        self.stage = Stage::Attack;
+    }
+
+    /// Enter the release stage
+    fn release(&mut self) {
+        if self.stage != Stage::Idle {
+            self.stage = Stage::Release;
+        }
+    }
+
+    /// Advance the envelope by one sample
+    fn envelope(&mut self) -> f32 {
+        let sustain = self.instrument.envelope.sustain;
+        match self.stage {
+            Stage::Idle => {}
+            Stage::Attack => {
+                self.level += self.attack_step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -= self.decay_step;
+                if self.level <= sustain {
+                    self.level = sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => {}
+            Stage::Release => {
+                self.level -= self.release_step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = Stage::Idle;
+                }
+            }
+        }
+        self.level
+    }
+
+    /// Advance the oscillator by one sample
+    fn oscillator(&mut self) -> i16 {
+        let phase = self.phase;
+        self.phase = phase.wrapping_add(self.phase_step);
+
+        match self.instrument.waveform {
+            Waveform::Sine => SINE[(phase >> 24) as usize],
+            Waveform::Square => {
+                if phase < 1 << 31 {
+                    i16::MAX
+                } else {
+                    -i16::MAX
+                }
+            }
+            Waveform::Triangle => {
+                // Fold the rising ramp at half a period
+                let ramp = (phase >> 16) as i32; // 0..65536
+                let folded = if ramp < 32768 { ramp } else { 65535 - ramp };
+                (folded * 2 - 32767) as i16
+            }
+            Waveform::Sawtooth => ((phase >> 16) as i32 - 32768) as i16,
+            Waveform::Noise => {
+                // New random value once per period (xorshift32)
+                if self.phase < phase {
+                    self.noise ^= self.noise << 13;
+                    self.noise ^= self.noise >> 17;
+                    self.noise ^= self.noise << 5;
+                    self.noise_value = (self.noise >> 16) as i16;
+                }
+                self.noise_value
+            }
+        }
+    }
+
+    /// Next sample, scaled by the envelope and volume
+    fn sample(&mut self) -> f32 {
+        if self.stage == Stage::Idle {
+            return 0.0;
+        }
+        let level = self.envelope() * self.instrument.volume;
+        self.oscillator() as f32 * level
+    }
+}
+
+/// Per-sample level change to cover `range` in `duration`
+fn step(duration: Duration, range: f32, sample_rate: u32) -> f32 {
+    let samples = duration.as_micros() as f32 * sample_rate as f32 / 1_000_000.0;
+    if samples < 1.0 {
+        1.0
+    } else {
+        range / samples
+    }
+}
+
+/// A set of `VOICES` voices mixed into one mono signal, played on both channels
+///
+/// # Example
+/// ```ignore
+/// let mut synth: Synth<4> = Synth::new(48_000);
+/// let voice = synth.note_on(880.0, Instrument::BELL);
+/// let mut frames = [[0i16; 2]; 256];
+/// synth.render(&mut frames);
+/// synth.note_off(voice);
+/// ```
+pub struct Synth<const VOICES: usize> {
+    sample_rate: u32,
+    voices: [Voice; VOICES],
+}
+
+impl<const VOICES: usize> Synth<VOICES> {
+    /// Create a synthesizer producing `sample_rate` frames per second
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: [Voice::IDLE; VOICES],
+        }
+    }
+
+    /// Output sample rate
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Start a note on a free voice and return the voice number
+    ///
+    /// If all voices are busy, the quietest one is taken over.
+    pub fn note_on(&mut self, frequency: f32, instrument: Instrument) -> usize {
+        let mut voice = 0;
+        for (i, v) in self.voices.iter().enumerate() {
+            if v.stage == Stage::Idle {
+                voice = i;
+                break;
+            }
+            if v.level < self.voices[voice].level {
+                voice = i;
+            }
+        }
+        self.play_on(voice, frequency, instrument);
+        voice
+    }
+
+    /// Start a note on a specific voice, replacing what it plays
+    pub fn play_on(&mut self, voice: usize, frequency: f32, instrument: Instrument) {
+        self.voices[voice].start(frequency, instrument, self.sample_rate);
+    }
+
+    /// Release the note on `voice`
+    pub fn note_off(&mut self, voice: usize) {
+        self.voices[voice].release();
+    }
+
+    /// Release all notes
+    pub fn all_notes_off(&mut self) {
+        for voice in &mut self.voices {
+            voice.release();
+        }
+    }
+
+    /// Check whether `voice` is still sounding (including its release)
+    pub fn is_playing(&self, voice: usize) -> bool {
+        self.voices[voice].stage != Stage::Idle
+    }
+
+    /// Check whether all voices are silent
+    pub fn is_idle(&self) -> bool {
+        self.voices.iter().all(|v| v.stage == Stage::Idle)
+    }
+
+    /// Compute the next frame
+    pub fn next_frame(&mut self) -> [i16; 2] {
+        let sum: f32 = self.voices.iter_mut().map(Voice::sample).sum();
+        let sample = sum.clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        [sample, sample]
+    }
+
+    /// Fill `frames` with the next frames
+    pub fn render(&mut self, frames: &mut [[i16; 2]]) {
+        for frame in frames {
+            *frame = self.next_frame();
+        }
+    }
+}
+
+/// A note or rest of a melody
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Note {
+    /// Pitch in Hz, `None` for a rest
+    pub frequency: Option<f32>,
+    /// Length, including the release
+    pub duration: Duration,
+}
+
+impl Note {
+    /// A note at `frequency` Hz
+    pub const fn new(frequency: f32, duration: Duration) -> Self {
+        Self {
+            frequency: Some(frequency),
+            duration,
+        }
+    }
+
+    /// A rest
+    pub const fn rest(duration: Duration) -> Self {
+        Self {
+            frequency: None,
+            duration,
+        }
+    }
+}
+
+/// Frequency of a MIDI note number (69 is A4, 440 Hz)
+pub fn midi_to_hz(note: u8) -> f32 {
+    /// Octave 0 (C0 = MIDI 12) to scale by powers of two
+    const OCTAVE_0: [f32; 12] = [
+        16.351_6, 17.323_9, 18.354_0, 19.445_4, 20.601_7, 21.826_8, 23.124_7, 24.499_7, 25.956_5,
+        27.5, 29.135_2, 30.867_7,
+    ];
+    let octave = note / 12;
+    let hz = OCTAVE_0[(note % 12) as usize] * (1u32 << octave) as f32;
+    hz / 2.0
+}
+
+/// RTTTL parsing errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum RtttlError {
+    /// The name, defaults and notes are not separated by two colons
+    MissingSection,
+    /// A default (`d=`, `o=`, `b=`) is unknown or out of range
+    InvalidDefault,
+    /// The note at this index (0-based) cannot be parsed
+    InvalidNote(usize),
+}
+
+/// A ringtone in RTTTL (Ring Tone Text Transfer Language)
+///
+/// `name:d=<duration>,o=<octave>,b=<bpm>:<notes>`, where each note is
+/// `[duration]<c|d|e|f|g|a|b|h|p>[#][.][octave][.]`, `p` is a rest and a dot
+/// makes the note 1.5 times as long.
+///
+/// # Example
+/// ```ignore
+/// let tune = Rtttl::parse("Alert:d=16,o=6,b=200:c,e,g,c7,p,c7").unwrap();
+/// for note in tune.notes() {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Rtttl<'a> {
+    name: &'a str,
+    notes: &'a str,
+    duration: u8,
+    octave: u8,
+    bpm: u16,
+}
+
+impl<'a> Rtttl<'a> {
+    /// Parse and check a ringtone
+    pub fn parse(text: &'a str) -> Result<Self, RtttlError> {
+        let mut sections = text.splitn(3, ':');
+        let (Some(name), Some(defaults), Some(notes)) = (sections.next(), sections.next(), sections.next()) else {
+            return Err(RtttlError::MissingSection);
+        };
+
+        let mut tune = Self {
+            name: name.trim(),
+            notes,
+            duration: 4,
+            octave: 6,
+            bpm: 63,
+        };
+        for default in defaults.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (key, value) = default.split_once('=').ok_or(RtttlError::InvalidDefault)?;
+            let value: u16 = value.trim().parse().map_err(|_| RtttlError::InvalidDefault)?;
+            match (key.trim(), value) {
+                ("d", 1 | 2 | 4 | 8 | 16 | 32) => tune.duration = value as u8,
+                ("o", 0..=8) => tune.octave = value as u8,
+                ("b", 1..=900) => tune.bpm = value,
+                _ => return Err(RtttlError::InvalidDefault),
+            }
+        }
+
+        for (i, note) in tune.tokens().enumerate() {
+            tune.parse_note(note).ok_or(RtttlError::InvalidNote(i))?;
+        }
+        Ok(tune)
+    }
+
+    /// Name of the ringtone
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Tempo in beats (quarter notes) per minute
+    pub fn bpm(&self) -> u16 {
+        self.bpm
+    }
+
+    /// Iterate over the notes
+    pub fn notes(&self) -> RtttlNotes<'a> {
+        RtttlNotes {
+            tune: *self,
+            tokens: self.tokens(),
+        }
+    }
+
+    /// Total length
+    pub fn duration(&self) -> Duration {
+        self.notes().fold(Duration::from_ticks(0), |sum, note| sum + note.duration)
+    }
+
+    /// The note tokens, without empty ones
+    fn tokens(&self) -> core::iter::Filter<Split<'a, char>, fn(&&'a str) -> bool> {
+        self.notes.split(',').filter(|n| !n.trim().is_empty())
+    }
+
+    /// Parse one note token
+    fn parse_note(&self, token: &str) -> Option<Note> {
+        let token = token.trim().as_bytes();
+        let mut i = 0;
+
+        let digits = |i: &mut usize| {
+            let start = *i;
+            let mut value = 0u16;
+            while *i < token.len() && token[*i].is_ascii_digit() {
+                value = value.checked_mul(10)?.checked_add((token[*i] - b'0') as u16)?;
+                *i += 1;
+            }
+            Some((*i > start).then_some(value))
+        };
+
+        let duration = match digits(&mut i)? {
+            Some(d @ (1 | 2 | 4 | 8 | 16 | 32)) => d as u32,
+            Some(_) => return None,
+            None => self.duration as u32,
+        };
+
+        let semitone = match token.get(i)?.to_ascii_lowercase() {
+            b'c' => Some(0),
+            b'd' => Some(2),
+            b'e' => Some(4),
+            b'f' => Some(5),
+            b'g' => Some(7),
+            b'a' => Some(9),
+            b'b' | b'h' => Some(11),
+            b'p' => None,
+            _ => return None,
+        };
+        i += 1;
+
+        let mut sharp = false;
+        if token.get(i) == Some(&b'#') {
+            sharp = true;
+            i += 1;
+        }
+        let mut dotted = false;
+        if token.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        let octave = match digits(&mut i)? {
+            Some(o @ 0..=8) => o as u8,
+            Some(_) => return None,
+            None => self.octave,
+        };
+        if token.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        if i != token.len() {
+            return None;
+        }
+
+        // A whole note is four beats
+        let mut micros = 240_000_000 / (self.bpm as u64 * duration as u64);
+        if dotted {
+            micros += micros / 2;
+        }
+        let duration = Duration::from_micros(micros);
+
+        Some(match semitone {
+            Some(semitone) => {
+                let midi = 12 * (octave + 1) + semitone + sharp as u8;
+                Note::new(midi_to_hz(midi), duration)
+            }
+            None => Note::rest(duration),
+        })
+    }
+}
+
+/// Iterator over the notes of an [`Rtttl`] tune
+#[derive(Debug, Clone)]
+pub struct RtttlNotes<'a> {
+    tune: Rtttl<'a>,
+    tokens: core::iter::Filter<Split<'a, char>, fn(&&'a str) -> bool>,
+}
+
+impl Iterator for RtttlNotes<'_> {
+    type Item = Note;
+
+    fn next(&mut self) -> Option<Note> {
+        for token in self.tokens.by_ref() {
+            match self.tune.parse_note(token) {
+                Some(note) => return Some(note),
+                // Only reachable for tunes not checked by `Rtttl::parse`
+                None => warn!("Skipping invalid RTTTL note"),
+            }
+        }
+        None
+    }
+}
+
+/// Progress of one melody in a [`Sequencer`]
+struct Track<I> {
+    notes: I,
+    instrument: Instrument,
+    /// Samples until the next note
+    remaining: u32,
+    /// Samples until the current note is released
+    gate: u32,
+    done: bool,
+}
+
+/// Plays one melody per voice, as an iterator of frames
+///
+/// The iterator ends when all melodies are over and the last notes have faded out.
+///
+/// # Example
+/// ```ignore
+/// let melody = Rtttl::parse("m:d=8,o=5,b=140:c,e,g,c6,g,4c6").unwrap();
+/// let bass = Rtttl::parse("b:d=4,o=3,b=140:c,g,2c").unwrap();
+/// let song = Sequencer::new(RATE, [
+///     (melody.notes(), Instrument::BELL),
+///     (bass.notes(), Instrument::BASS),
+/// ]);
+/// output.play(RATE, song).await.unwrap();
+/// ```
+pub struct Sequencer<I, const TRACKS: usize> {
+    synth: Synth<TRACKS>,
+    tracks: [Track<I>; TRACKS],
+}
+
+impl<I: Iterator<Item = Note>, const TRACKS: usize> Sequencer<I, TRACKS> {
+    /// Create a sequencer with a melody and instrument per track
+    pub fn new(sample_rate: u32, tracks: [(I, Instrument); TRACKS]) -> Self {
+        Self {
+            synth: Synth::new(sample_rate),
+            tracks: tracks.map(|(notes, instrument)| Track {
+                notes,
+                instrument,
+                remaining: 0,
+                gate: 0,
+                done: false,
+            }),
+        }
+    }
+
+    /// The synthesizer, e.g. to play extra notes over the melodies
+    pub fn synth(&mut self) -> &mut Synth<TRACKS> {
+        &mut self.synth
+    }
+
+    /// Advance the tracks by one sample
+    fn step(&mut self) {
+        let sample_rate = self.synth.sample_rate;
+        for (voice, track) in self.tracks.iter_mut().enumerate() {
+            if track.done {
+                continue;
+            }
+
+            if track.gate > 0 {
+                track.gate -= 1;
+                if track.gate == 0 {
+                    self.synth.note_off(voice);
+                }
+            }
+
+            while track.remaining == 0 {
+                let Some(note) = track.notes.next() else {
+                    track.done = true;
+                    self.synth.note_off(voice);
+                    break;
+                };
+                let samples = (note.duration.as_micros() * sample_rate as u64 / 1_000_000) as u32;
+                track.remaining = samples;
+                match note.frequency {
+                    Some(frequency) if samples > 0 => {
+                        self.synth.play_on(voice, frequency, track.instrument);
+                        track.gate = ((samples as f32 * GATE) as u32).max(1);
+                    }
+                    _ => {
+                        self.synth.note_off(voice);
+                        track.gate = 0;
+                    }
+                }
+            }
+            track.remaining = track.remaining.saturating_sub(1);
+        }
+    }
+}
+
+impl<I: Iterator<Item = Note>, const TRACKS: usize> Iterator for Sequencer<I, TRACKS> {
+    type Item = [i16; 2];
+
+    fn next(&mut self) -> Option<[i16; 2]> {
+        self.step();
+        if self.tracks.iter().all(|t| t.done) && self.synth.is_idle() {
+            return None;
+        }
+        Some(self.synth.next_frame())
+    }
+}
+
+/// Mix two frames, saturating at full scale
+///
+/// # Example
+/// ```ignore
+/// let frames = wav.frames().zip(synth_frames).map(|(a, b)| mix(a, b));
+/// ```
+pub fn mix(a: [i16; 2], b: [i16; 2]) -> [i16; 2] {
+    [a[0].saturating_add(b[0]), a[1].saturating_add(b[1])]
+}
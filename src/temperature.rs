@@ -0,0 +1,141 @@
+//! Temperature readings from the onboard sensors
+//!
+//! Both MEMS sensors include a temperature sensor intended for drift compensation:
+//! - L3GD20: 8-bit output, -1 LSB/°C
+//! - LSM303DLHC: 12-bit output, 8 LSB/°C
+//!
+//! Neither datasheet specifies the output value at a known temperature, so the drivers
+//! convert readings relative to a nominal 25 °C. Apply a one-point
+//! [`TemperatureCalibration`] to get absolute readings. The STM32F411's internal
+//! temperature sensor ([`McuTemperature`]) can serve as the reference.
+//!
+//! [Datasheet](docs/stm32f411ve.pdf)
+
+use embassy_stm32::adc::{self, Adc, SampleTime, VrefInt};
+use embassy_stm32::peripherals::ADC1;
+use embassy_stm32::Peri;
+
+/// Temperature assumed when a MEMS sensor outputs zero
+pub(crate) const NOMINAL_CELSIUS: f32 = 25.0;
+
+/// Factory calibration values in system memory (STM32F411 datasheet, section 6.3.22)
+const TS_CAL1: *const u16 = 0x1FFF_7A2C as *const u16; // ADC reading at 30 °C, VDDA = 3.3 V
+const TS_CAL2: *const u16 = 0x1FFF_7A2E as *const u16; // ADC reading at 110 °C, VDDA = 3.3 V
+const VREFINT_CAL: *const u16 = 0x1FFF_7A2A as *const u16; // VREFINT reading at VDDA = 3.3 V
+const TS_CAL1_CELSIUS: f32 = 30.0;
+const TS_CAL2_CELSIUS: f32 = 110.0;
+
+/// A temperature reading
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, defmt::Format)]
+pub struct Temperature {
+    /// Temperature in degrees Celsius
+    pub celsius: f32,
+}
+
+impl Temperature {
+    /// Create a temperature from degrees Celsius
+    pub const fn from_celsius(celsius: f32) -> Self {
+        Self { celsius }
+    }
+
+    /// Temperature in degrees Fahrenheit
+    pub fn fahrenheit(&self) -> f32 {
+        self.celsius * 9.0 / 5.0 + 32.0
+    }
+}
+
+/// One-point offset calibration for a temperature sensor
+///
+/// # Example
+/// ```ignore
+/// // Calibrate the gyro's sensor against the MCU's internal sensor
+/// let cal = TemperatureCalibration::one_point(gyro.read_temperature(), mcu.read());
+/// gyro.set_temperature_calibration(cal);
+/// ```
+#[derive(Debug, Default, Clone, Copy, defmt::Format)]
+pub struct TemperatureCalibration {
+    /// Offset added to every reading in °C
+    pub offset: f32,
+}
+
+impl TemperatureCalibration {
+    /// No correction
+    pub const NONE: Self = Self { offset: 0.0 };
+
+    /// Compute the offset from a reading taken at a known reference temperature
+    ///
+    /// # Arguments
+    /// * `measured` - Reading from the sensor being calibrated
+    /// * `reference` - Actual temperature at the time of the reading
+    pub fn one_point(measured: Temperature, reference: Temperature) -> Self {
+        Self {
+            offset: reference.celsius - measured.celsius,
+        }
+    }
+
+    /// Apply the calibration to a reading
+    pub fn apply(&self, temperature: Temperature) -> Temperature {
+        Temperature::from_celsius(temperature.celsius + self.offset)
+    }
+}
+
+/// STM32F411 internal temperature sensor (ADC1_IN18)
+///
+/// ADC1_IN18 is shared with VBAT: while VBAT monitoring is enabled, the channel
+/// converts VBAT instead of the temperature sensor.
+///
+/// Uses the factory calibration values stored in system memory and compensates
+/// for the actual VDDA using the internal voltage reference. Accuracy is about
+/// ±1.5 °C, which is good enough to calibrate the MEMS sensors against.
+pub struct McuTemperature<'d> {
+    adc: Adc<'d, ADC1>,
+    sensor: adc::Temperature,
+    vrefint: VrefInt,
+}
+
+impl<'d> McuTemperature<'d> {
+    /// Enable the internal temperature sensor on ADC1
+    pub fn new(adc1: Peri<'d, ADC1>) -> Self {
+        let mut adc = Adc::new(adc1);
+        // The sensor needs at least 10 µs of sampling time
+        adc.set_sample_time(SampleTime::CYCLES480);
+        let sensor = adc.enable_temperature();
+        let vrefint = adc.enable_vrefint();
+
+        // Wait for the sensor and reference to stabilize
+        embassy_time::block_for(embassy_time::Duration::from_micros(
+            adc::Temperature::start_time_us().max(VrefInt::start_time_us()) as u64,
+        ));
+
+        Self {
+            adc,
+            sensor,
+            vrefint,
+        }
+    }
+
+    /// Read the die temperature
+    pub fn read(&mut self) -> Temperature {
+        let vrefint = self.adc.blocking_read(&mut self.vrefint);
+        let raw = self.adc.blocking_read(&mut self.sensor);
+
+        // SAFETY: the calibration values are read-only system memory present on every STM32F411
+        let (cal1, cal2, vref_cal) = unsafe {
+            (
+                TS_CAL1.read_volatile() as f32,
+                TS_CAL2.read_volatile() as f32,
+                VREFINT_CAL.read_volatile() as f32,
+            )
+        };
+
+        // Scale the reading to what it would have been at VDDA = 3.3 V
+        let raw = if vrefint > 0 {
+            raw as f32 * vref_cal / vrefint as f32
+        } else {
+            raw as f32
+        };
+
+        let slope = (TS_CAL2_CELSIUS - TS_CAL1_CELSIUS) / (cal2 - cal1);
+        Temperature::from_celsius(TS_CAL1_CELSIUS + (raw - cal1) * slope)
+    }
+}
@@ -0,0 +1,304 @@
+//! WAV (RIFF) file parser
+//!
+//! Parses WAV files held in memory, typically embedded in flash with
+//! `include_bytes!`, without copying the sample data.
+//!
+//! ## Supported Formats
+//! - PCM (format tag 1, or `WAVE_FORMAT_EXTENSIBLE` with a PCM sub-format),
+//!   8-bit unsigned or 16-bit signed samples
+//! - IMA ADPCM (format tag 0x11), 4 bits per sample, decoded by [`crate::adpcm`]
+//! - Mono or stereo
+//! - Any sample rate
+//!
+//! Chunks other than `fmt ` and `data` (`LIST`, `fact`, ...) are skipped.
+//!
+//! # Example
+//! ```ignore
+//! static CHIME: &[u8] = include_bytes!("chime.wav");
+//!
+//! let wav = Wav::parse(CHIME).unwrap();
+//! info!("{} Hz, {} frames", wav.format().sample_rate, wav.frame_count());
+//! for [left, right] in wav.frames() {
+//!     // ...
+//! }
+//! ```
+
+use crate::adpcm::{self, BlockDecoder};
+
+/// WAV format tag for integer PCM
+const FORMAT_PCM: u16 = 0x0001;
+/// WAV format tag for IMA ADPCM
+const FORMAT_IMA_ADPCM: u16 = 0x0011;
+/// WAV format tag for `WAVE_FORMAT_EXTENSIBLE`
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Size of the `fmt ` chunk fields read by the parser
+const FMT_SIZE: usize = 16;
+/// Offset of the sub-format in an extensible `fmt ` chunk
+const FMT_SUBFORMAT: usize = 24;
+
+/// WAV parsing errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum WavError {
+    /// The data does not start with a `RIFF` header
+    NotRiff,
+    /// The RIFF form type is not `WAVE`
+    NotWave,
+    /// No `fmt ` chunk before the `data` chunk
+    MissingFormat,
+    /// No `data` chunk
+    MissingData,
+    /// A chunk is cut off or the `fmt ` chunk is too short
+    Truncated,
+    /// The encoding is not PCM or IMA ADPCM (format tag)
+    UnsupportedEncoding(u16),
+    /// Only mono and stereo are supported (channel count)
+    UnsupportedChannels(u16),
+    /// Only 8 and 16 bits per sample (PCM) or 4 bits (ADPCM) are supported (bit depth)
+    UnsupportedBitDepth(u16),
+    /// The block size does not fit the encoding and channel count
+    InvalidBlockAlign,
+    /// The sample rate is zero
+    InvalidSampleRate,
+}
+
+/// Sample encoding of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum WavEncoding {
+    /// Uncompressed PCM
+    Pcm,
+    /// IMA/DVI ADPCM
+    ImaAdpcm,
+}
+
+/// Sample format of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct WavFormat {
+    /// Sample encoding
+    pub encoding: WavEncoding,
+    /// Number of channels (1 or 2)
+    pub channels: u16,
+    /// Frames per second
+    pub sample_rate: u32,
+    /// Bits per sample (8 or 16 for PCM, 4 for ADPCM)
+    pub bits_per_sample: u16,
+    /// Bytes per frame (PCM) or per block (ADPCM)
+    pub block_align: u16,
+}
+
+/// A parsed WAV file
+///
+/// Borrows the sample data from the file contents.
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct Wav<'a> {
+    format: WavFormat,
+    data: &'a [u8],
+}
+
+impl<'a> Wav<'a> {
+    /// Parse the contents of a WAV file
+    ///
+    /// A `data` chunk that claims to be longer than the file (as written by some
+    /// streaming encoders) is cut to the available bytes.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, WavError> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" {
+            return Err(WavError::NotRiff);
+        }
+        if &bytes[8..12] != b"WAVE" {
+            return Err(WavError::NotWave);
+        }
+
+        let mut format = None;
+        let mut rest = &bytes[12..];
+        while rest.len() >= 8 {
+            let id = &rest[0..4];
+            let size = read_u32(&rest[4..8]) as usize;
+            let body = &rest[8..];
+
+            if id == b"data" {
+                let format: WavFormat = format.ok_or(WavError::MissingFormat)?;
+                let data = &body[..size.min(body.len())];
+                // Drop a partial PCM frame at the end
+                let data = match format.encoding {
+                    WavEncoding::Pcm => &data[..data.len() - data.len() % format.block_align as usize],
+                    WavEncoding::ImaAdpcm => data,
+                };
+                return Ok(Self { format, data });
+            }
+
+            if size > body.len() {
+                return Err(WavError::Truncated);
+            }
+            if id == b"fmt " {
+                format = Some(parse_format(&body[..size])?);
+            }
+
+            // Chunks are padded to an even size
+            let next = (size + 1) & !1;
+            rest = &body[next.min(body.len())..];
+        }
+
+        Err(WavError::MissingData)
+    }
+
+    /// Sample format
+    pub fn format(&self) -> WavFormat {
+        self.format
+    }
+
+    /// Raw sample data
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Number of frames
+    pub fn frame_count(&self) -> usize {
+        self.frames().len()
+    }
+
+    /// Playing time in milliseconds
+    pub fn duration_ms(&self) -> u32 {
+        (self.frame_count() as u64 * 1000 / self.format.sample_rate as u64) as u32
+    }
+
+    /// Iterate over the frames as 16-bit stereo
+    ///
+    /// Mono files are played on both channels, 8-bit samples are scaled to 16 bits
+    /// and ADPCM is decoded on the fly.
+    pub fn frames(&self) -> Frames<'a> {
+        match self.format.encoding {
+            WavEncoding::Pcm => Frames::Pcm(PcmFrames {
+                format: self.format,
+                data: self.data,
+            }),
+            WavEncoding::ImaAdpcm => Frames::ImaAdpcm(
+                BlockDecoder::new(self.data, self.format.channels as usize, self.format.block_align as usize)
+                    .expect("block layout checked by Wav::parse"),
+            ),
+        }
+    }
+}
+
+/// Iterator over the frames of a [`Wav`] as `[left, right]`
+#[derive(Debug, Clone)]
+pub enum Frames<'a> {
+    /// PCM samples
+    Pcm(PcmFrames<'a>),
+    /// IMA ADPCM blocks
+    ImaAdpcm(BlockDecoder<'a>),
+}
+
+impl Iterator for Frames<'_> {
+    type Item = [i16; 2];
+
+    fn next(&mut self) -> Option<[i16; 2]> {
+        match self {
+            Frames::Pcm(frames) => frames.next(),
+            Frames::ImaAdpcm(frames) => frames.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Frames::Pcm(frames) => frames.size_hint(),
+            Frames::ImaAdpcm(frames) => frames.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for Frames<'_> {}
+
+/// Iterator over PCM frames as `[left, right]`
+#[derive(Debug, Clone)]
+pub struct PcmFrames<'a> {
+    format: WavFormat,
+    data: &'a [u8],
+}
+
+impl Iterator for PcmFrames<'_> {
+    type Item = [i16; 2];
+
+    fn next(&mut self) -> Option<[i16; 2]> {
+        let block_align = self.format.block_align as usize;
+        if self.data.len() < block_align {
+            return None;
+        }
+        let (frame, rest) = self.data.split_at(block_align);
+        self.data = rest;
+
+        let sample = |i: usize| match self.format.bits_per_sample {
+            // 8-bit WAV samples are unsigned
+            8 => ((frame[i] as i16) - 128) << 8,
+            _ => i16::from_le_bytes([frame[2 * i], frame[2 * i + 1]]),
+        };
+        let left = sample(0);
+        let right = if self.format.channels == 2 { sample(1) } else { left };
+        Some([left, right])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.data.len() / self.format.block_align as usize;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for PcmFrames<'_> {}
+
+/// Parse the body of a `fmt ` chunk
+fn parse_format(fmt: &[u8]) -> Result<WavFormat, WavError> {
+    if fmt.len() < FMT_SIZE {
+        return Err(WavError::Truncated);
+    }
+
+    let mut tag = read_u16(&fmt[0..2]);
+    if tag == FORMAT_EXTENSIBLE {
+        if fmt.len() < FMT_SUBFORMAT + 2 {
+            return Err(WavError::Truncated);
+        }
+        // The first two bytes of the sub-format GUID are the format tag
+        tag = read_u16(&fmt[FMT_SUBFORMAT..FMT_SUBFORMAT + 2]);
+    }
+    let encoding = match tag {
+        FORMAT_PCM => WavEncoding::Pcm,
+        FORMAT_IMA_ADPCM => WavEncoding::ImaAdpcm,
+        _ => return Err(WavError::UnsupportedEncoding(tag)),
+    };
+
+    let format = WavFormat {
+        encoding,
+        channels: read_u16(&fmt[2..4]),
+        sample_rate: read_u32(&fmt[4..8]),
+        block_align: read_u16(&fmt[12..14]),
+        bits_per_sample: read_u16(&fmt[14..16]),
+    };
+    if !matches!(format.channels, 1 | 2) {
+        return Err(WavError::UnsupportedChannels(format.channels));
+    }
+    let block_align_ok = match encoding {
+        WavEncoding::Pcm if matches!(format.bits_per_sample, 8 | 16) => {
+            format.block_align as usize == format.channels as usize * format.bits_per_sample as usize / 8
+        }
+        WavEncoding::ImaAdpcm if format.bits_per_sample == 4 => {
+            adpcm::samples_per_block(format.block_align as usize, format.channels as usize) > 0
+        }
+        _ => return Err(WavError::UnsupportedBitDepth(format.bits_per_sample)),
+    };
+    if !block_align_ok {
+        return Err(WavError::InvalidBlockAlign);
+    }
+    if format.sample_rate == 0 {
+        return Err(WavError::InvalidSampleRate);
+    }
+
+    Ok(format)
+}
+
+/// Read a little-endian u16
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+/// Read a little-endian u32
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
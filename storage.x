@@ -0,0 +1,4 @@
+/* Appended to memory.x by build.rs with the `storage` feature. The 16KB flash
+   sectors 1 and 2 (0x0800_4000 - 0x0800_BFFF) hold the key-value store, so the
+   code starts at sector 3, after a gap behind the vector table in sector 0 */
+_stext = 0x0800C000;